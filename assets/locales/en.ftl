@@ -0,0 +1,99 @@
+# English, also shown for any message missing from another language
+
+## Notices
+playing = Playing
+paused = Paused
+playing-entry = Playing { $entry }
+playback-speed = Playing at { $speed }x
+resuming = Resuming at { $time }
+seeking = Seeking to { $time }
+stepped = Frame { $frame } at { $time }
+rewinding = Rewinding
+rewind-limit = Can't rewind further, keep more frames with --history
+the-end = The end, press Space to play again
+still-loading = Still loading, playing once done
+background-paused = Paused while in the background
+sound-gesture = Click or press a key for sound
+no-frames = No frames found, visualizing the soundtrack
+low-power = Low-power mode
+restarting = Restarting
+reloaded = Reloaded { $file }
+reloaded-restart = Reloaded { $file }, restart to apply { $keys }
+timecode-on = Following the timecode
+timecode-off = Timecode stopped
+recording-to = Recording to { $path }
+recording-stopped = Recording stopped
+saved = Saved { $path }
+screenshot-failed = Couldn't save the screenshot
+exported = Exported { $count } particles to { $path }.ply and .obj
+export-failed = Couldn't export particles: { $error }
+particle-count = { $count } particles
+sim-rate = Simulating at { $hz }Hz, { $ticks } ticks per video frame
+playfield-resized = Playfield resized to { $width }x{ $height }
+fit-on = Window follows the source's shape
+fit-off = Window keeps its size, letterboxing the source
+movement = Movement: { $mode }
+layering = Layering: { $mode }
+palette = Palette: { $palette }
+output-palette = Output palette: { $palette }
+particle-shape = Particle shape: { $shape }
+gamma = Color gamma: { $gamma }
+bloom = Bloom: { $level }
+bloom-off = Bloom off
+msaa = Anti-aliasing: { $samples }x MSAA
+msaa-off = Anti-aliasing off
+multicam = Multicam on: { $shot }
+multicam-off = Multicam off
+crystal = Crystallizing on a { $lattice } lattice after { $delay }s
+crystal-off = Crystallization off
+lifespan = Recycling particles every ~{ $span }s
+lifespan-off = Particles live forever
+sway = Particles sway by { $px }px
+sway-off = Particles hold still
+budgets-on = Region budgets on
+budgets-off = Region budgets off
+flow-on = Particles follow the motion of the video
+flow-off = Particles only settle on each frame
+gravity-on = Bass gravity wells on
+gravity-off = Bass gravity wells off
+reactivity-on = Particles pulse with the bass
+reactivity-off = Particles ignore the music
+interpolation-on = Frame interpolation on
+interpolation-off = Frame interpolation off
+aging-on = Particle aging on
+aging-off = Particle aging off
+shading-on = Density shading on
+shading-off = Density shading off
+spotlight-on = Spotlight on, scroll to resize it
+spotlight-off = Spotlight off
+trails-on = Trails on
+trails-off = Trails off
+threshold-adaptive = Adaptive threshold
+threshold-fixed = Fixed threshold
+threshold-light = Following light pixels
+threshold-dark = Following dark pixels
+dark-background = Dark background detected, following light pixels
+
+## Loading
+buffering = Buffering...
+splash-looking = Looking for frames
+splash-frames = Loading frames { $frames }/{ $target }
+splash-loading-audio = Loading the soundtrack
+splash-decoding-audio = Decoding the soundtrack
+
+## Control panel
+panel-play-pause = Play/Pause
+panel-speed = Speed
+panel-particles = Particles
+panel-count = Count
+panel-jitter = Jitter
+panel-palette = Palette
+
+## Diagnostics overlay
+hud-frame = Frame { $frame } / { $frames }
+hud-frame-live = Frame { $frame } (live)
+hud-clock = Clock at { $clock }s, showing { $video }s
+hud-rates = { $fps } fps, { $ticks } ticks/s
+hud-particles = { $count } particles, { $settled } settled, covering { $coverage }% of the silhouette
+hud-buffered = Buffered { $buffered } / { $budget } frames, { $loading } loading
+hud-allocations = Allocations per frame: { $systems }
@@ -0,0 +1,99 @@
+# Français
+
+## Notices
+playing = Lecture
+paused = Pause
+playing-entry = Lecture de { $entry }
+playback-speed = Lecture à { $speed }x
+resuming = Reprise à { $time }
+seeking = Saut à { $time }
+stepped = Image { $frame } à { $time }
+rewinding = Retour en arrière
+rewind-limit = Impossible de revenir plus loin, gardez plus d'images avec --history
+the-end = Fin, appuyez sur Espace pour rejouer
+still-loading = Chargement en cours, la lecture commencera ensuite
+background-paused = En pause en arrière-plan
+sound-gesture = Cliquez ou appuyez sur une touche pour le son
+no-frames = Aucune image trouvée, visualisation de la bande-son
+low-power = Mode économie d'énergie
+restarting = Redémarrage
+reloaded = { $file } rechargé
+reloaded-restart = { $file } rechargé, redémarrez pour appliquer { $keys }
+timecode-on = Suivi du timecode
+timecode-off = Timecode arrêté
+recording-to = Enregistrement dans { $path }
+recording-stopped = Enregistrement arrêté
+saved = { $path } enregistré
+screenshot-failed = Impossible d'enregistrer la capture d'écran
+exported = { $count } particules exportées vers { $path }.ply et .obj
+export-failed = Impossible d'exporter les particules : { $error }
+particle-count = { $count } particules
+sim-rate = Simulation à { $hz } Hz, { $ticks } pas par image de la vidéo
+playfield-resized = Terrain redimensionné à { $width }x{ $height }
+fit-on = La fenêtre suit la forme de la source
+fit-off = La fenêtre garde sa taille, la source est encadrée
+movement = Mouvement : { $mode }
+layering = Superposition : { $mode }
+palette = Palette : { $palette }
+output-palette = Palette de sortie : { $palette }
+particle-shape = Forme des particules : { $shape }
+gamma = Gamma des couleurs : { $gamma }
+bloom = Halo : { $level }
+bloom-off = Halo désactivé
+msaa = Anticrénelage : MSAA { $samples }x
+msaa-off = Anticrénelage désactivé
+multicam = Multicam activée : { $shot }
+multicam-off = Multicam désactivée
+crystal = Cristallisation en réseau { $lattice } après { $delay } s
+crystal-off = Cristallisation désactivée
+lifespan = Particules recyclées toutes les ~{ $span } s
+lifespan-off = Les particules vivent pour toujours
+sway = Les particules ondulent de { $px } px
+sway-off = Les particules restent immobiles
+budgets-on = Budgets par région activés
+budgets-off = Budgets par région désactivés
+flow-on = Les particules suivent le mouvement de la vidéo
+flow-off = Les particules se posent seulement sur chaque image
+gravity-on = Puits de gravité des basses activés
+gravity-off = Puits de gravité des basses désactivés
+reactivity-on = Les particules pulsent avec les basses
+reactivity-off = Les particules ignorent la musique
+interpolation-on = Interpolation des images activée
+interpolation-off = Interpolation des images désactivée
+aging-on = Vieillissement des particules activé
+aging-off = Vieillissement des particules désactivé
+shading-on = Ombrage de densité activé
+shading-off = Ombrage de densité désactivé
+spotlight-on = Projecteur activé, faites défiler pour le redimensionner
+spotlight-off = Projecteur désactivé
+trails-on = Traînées activées
+trails-off = Traînées désactivées
+threshold-adaptive = Seuil adaptatif
+threshold-fixed = Seuil fixe
+threshold-light = Suivi des pixels clairs
+threshold-dark = Suivi des pixels sombres
+dark-background = Fond sombre détecté, suivi des pixels clairs
+
+## Loading
+buffering = Mise en mémoire tampon...
+splash-looking = Recherche des images
+splash-frames = Chargement des images { $frames }/{ $target }
+splash-loading-audio = Chargement de la bande-son
+splash-decoding-audio = Décodage de la bande-son
+
+## Control panel
+panel-play-pause = Lecture/Pause
+panel-speed = Vitesse
+panel-particles = Particules
+panel-count = Nombre
+panel-jitter = Agitation
+panel-palette = Palette
+
+## Diagnostics overlay
+hud-frame = Image { $frame } / { $frames }
+hud-frame-live = Image { $frame } (direct)
+hud-clock = Horloge à { $clock } s, affichage à { $video } s
+hud-rates = { $fps } ips, { $ticks } pas/s
+hud-particles = { $count } particules, { $settled } posées, couvrant { $coverage } % de la silhouette
+hud-buffered = { $buffered } / { $budget } images en mémoire, { $loading } en chargement
+hud-allocations = Allocations par image : { $systems }
@@ -0,0 +1,125 @@
+// Particle simulation on the GPU, see `src/gpu_sim.rs`.
+// Positions are in pixels from the top left corner of the frame.
+
+struct Params {
+    size: vec2<u32>,
+    count: u32,
+    // brightest value that counts as dark, in the texture's color space
+    threshold: f32,
+    inverted: u32,
+    jitter: f32,
+    seed: u32,
+    // simulation ticks to take this frame
+    steps: u32,
+    settled: vec4<f32>,
+    moving: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+@group(0) @binding(1)
+var<storage, read_write> particles: array<vec2<f32>>;
+// settled then moving particles on every pixel
+@group(0) @binding(2)
+var<storage, read_write> density: array<atomic<u32>>;
+@group(0) @binding(3)
+var frame: texture_2d<f32>;
+@group(0) @binding(4)
+var output: texture_storage_2d<rgba8unorm, write>;
+// settled particles, moving particles, dark pixels and dark pixels with a particle,
+// read back for the HUD
+@group(0) @binding(5)
+var<storage, read_write> counters: array<atomic<u32>, 4>;
+
+// PCG hash, from "Hash Functions for GPU Rendering"
+fn hash(n: u32) -> u32 {
+    let state = n * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn random(n: u32) -> f32 {
+    return f32(hash(n)) / 4294967295.0;
+}
+
+fn is_dark(pixel: vec2<u32>) -> bool {
+    let value = textureLoad(frame, vec2<i32>(pixel), 0).r;
+    return (value <= params.threshold) != (params.inverted != 0u);
+}
+
+fn pixel_index(pixel: vec2<u32>) -> u32 {
+    return pixel.y * params.size.x + pixel.x;
+}
+
+@compute @workgroup_size(64)
+fn clear(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x < 4u {
+        atomicStore(&counters[id.x], 0u);
+    }
+    if id.x >= params.size.x * params.size.y * 2u {
+        return;
+    }
+    atomicStore(&density[id.x], 0u);
+}
+
+@compute @workgroup_size(64)
+fn step(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.count {
+        return;
+    }
+
+    var pos = particles[id.x];
+    let size = vec2<f32>(params.size);
+    var pixel = min(vec2<u32>(pos), params.size - 1u);
+    // drawn as moving when it wasn't settled at the start of the frame, even without a tick
+    var offset = 0u;
+    if !is_dark(pixel) {
+        offset = params.size.x * params.size.y;
+    }
+
+    // random walk until landing on the silhouette
+    for (var tick = 0u; tick < params.steps; tick++) {
+        if is_dark(pixel) {
+            break;
+        }
+        let seed = hash(id.x ^ hash(params.seed + tick));
+        let walk = vec2<f32>(random(seed), random(hash(seed))) * 2.0 - 1.0;
+        pos += walk * params.jitter;
+        pos -= floor(pos / size) * size;
+        pixel = min(vec2<u32>(pos), params.size - 1u);
+    }
+    particles[id.x] = pos;
+
+    atomicAdd(&density[offset + pixel_index(pixel)], 1u);
+    if is_dark(pixel) {
+        atomicAdd(&counters[0], 1u);
+    } else {
+        atomicAdd(&counters[1], 1u);
+    }
+}
+
+@compute @workgroup_size(8, 8)
+fn resolve(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= params.size) {
+        return;
+    }
+
+    let idx = pixel_index(id.xy);
+    let settled = f32(atomicLoad(&density[idx]));
+    let moving = f32(atomicLoad(&density[params.size.x * params.size.y + idx]));
+    let total = settled + moving;
+    if is_dark(id.xy) {
+        atomicAdd(&counters[2], 1u);
+        if total > 0.0 {
+            atomicAdd(&counters[3], 1u);
+        }
+    }
+    if total == 0.0 {
+        textureStore(output, vec2<i32>(id.xy), vec4<f32>(0.0));
+        return;
+    }
+
+    // a few particles on a pixel are as opaque as one sprite
+    let color = mix(params.settled, params.moving, moving / total);
+    textureStore(output, vec2<i32>(id.xy), vec4<f32>(color.rgb, color.a * min(total, 1.0)));
+}
@@ -0,0 +1,50 @@
+// Particles drawn as instances of a quad, see `src/render.rs`.
+
+#import bevy_render::view
+
+@group(0) @binding(0)
+var<uniform> view: View;
+@group(1) @binding(0)
+var particle_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var particle_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vertex(
+    @builtin(vertex_index) index: u32,
+    @location(0) axis_x: vec3<f32>,
+    @location(1) axis_y: vec3<f32>,
+    @location(2) center: vec3<f32>,
+    @location(3) color: vec4<f32>,
+    // top left and bottom right corners of the part of the texture drawn
+    @location(4) uv_rect: vec4<f32>,
+) -> VertexOutput {
+    // two triangles covering the quad, from -0.5 to 0.5 on both sides
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-0.5, -0.5),
+        vec2<f32>(0.5, -0.5),
+        vec2<f32>(0.5, 0.5),
+        vec2<f32>(-0.5, -0.5),
+        vec2<f32>(0.5, 0.5),
+        vec2<f32>(-0.5, 0.5),
+    );
+    let corner = corners[index];
+    let position = center + corner.x * axis_x + corner.y * axis_y;
+
+    var out: VertexOutput;
+    out.position = view.view_proj * vec4<f32>(position, 1.0);
+    out.uv = mix(uv_rect.xy, uv_rect.zw, vec2<f32>(corner.x + 0.5, 0.5 - corner.y));
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color * textureSample(particle_texture, particle_sampler, in.uv);
+}
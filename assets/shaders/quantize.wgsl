@@ -0,0 +1,25 @@
+@group(1) @binding(0)
+var source: texture_2d<f32>;
+@group(1) @binding(1)
+var source_sampler: sampler;
+@group(1) @binding(2)
+var<uniform> colors: array<vec4<f32>, 4>;
+
+struct FragmentInput {
+    #import bevy_sprite::mesh2d_vertex_output
+};
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, in.uv).rgb;
+
+    // snap to the closest palette color
+    var best = colors[0].rgb;
+    for (var i = 1; i < 4; i = i + 1) {
+        if distance(color, colors[i].rgb) < distance(color, best) {
+            best = colors[i].rgb;
+        }
+    }
+
+    return vec4<f32>(best, 1.0);
+}
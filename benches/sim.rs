@@ -0,0 +1,130 @@
+use bad_apple_particle::{
+    sim::{
+        self, DistanceField, FrameView, MovementMode, Playfield, Settle, SimRng, Steering, JITTER,
+    },
+    verify::Frames,
+};
+use bevy::{
+    prelude::*,
+    tasks::{ParallelSliceMut, TaskPool},
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::{rngs::StdRng, SeedableRng};
+
+const PARTICLES: usize = 30000;
+
+/// Middle frame of the disc the verification plays, crossing the playfield
+fn disc_frame() -> (Playfield, Vec<u8>) {
+    let mut frames = Frames::disc();
+    let middle = frames.data.len() / 2;
+    (Playfield::new(frames.size), frames.data.swap_remove(middle))
+}
+
+fn step(c: &mut Criterion) {
+    let (playfield, data) = disc_frame();
+    let frame = FrameView::new(&data, playfield.size, 1);
+    let centroid = frame.centroid(&playfield);
+    let field = DistanceField::new(&frame);
+
+    for mode in [
+        MovementMode::Jitter,
+        MovementMode::Orbit,
+        MovementMode::Attract,
+        MovementMode::Flow,
+        MovementMode::Outline,
+    ] {
+        let steering = Steering {
+            mode,
+            settle: Settle::Fill,
+            centroid,
+            field: Some(&field),
+        };
+        c.bench_function(&format!("step {mode:?}"), |b| {
+            let mut rng = StdRng::seed_from_u64(0);
+            let mut particles: Vec<_> = (0..PARTICLES)
+                .map(|_| playfield.random_position(&mut rng).extend(0.0))
+                .collect();
+            let mut velocities = vec![Vec2::ZERO; PARTICLES];
+
+            b.iter(|| {
+                for (pos, velocity) in particles.iter_mut().zip(&mut velocities) {
+                    sim::step(
+                        pos, velocity, &frame, &playfield, &steering, JITTER, &mut rng,
+                    );
+                }
+            })
+        });
+    }
+}
+
+/// The same steps as the app takes them, on every thread with a generator per particle
+fn step_parallel(c: &mut Criterion) {
+    let (playfield, data) = disc_frame();
+    let frame = FrameView::new(&data, playfield.size, 1);
+    let steering = Steering {
+        mode: MovementMode::Jitter,
+        settle: Settle::Fill,
+        centroid: None,
+        field: None,
+    };
+    let pool = TaskPool::new();
+    let chunk = PARTICLES.div_ceil(pool.thread_num());
+
+    c.bench_function("step Jitter parallel", |b| {
+        let mut sim_rng = SimRng::new(0);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut particles: Vec<_> = (0..PARTICLES as u32)
+            .map(|id| {
+                (
+                    id,
+                    playfield.random_position(&mut rng).extend(0.0),
+                    Vec2::ZERO,
+                )
+            })
+            .collect();
+
+        b.iter(|| {
+            sim_rng.advance();
+            let sim_rng = &sim_rng;
+            particles.par_chunk_map_mut(&pool, chunk, |chunk| {
+                for (id, pos, velocity) in chunk {
+                    let mut rng = sim_rng.particle(*id);
+                    sim::step(
+                        pos, velocity, &frame, &playfield, &steering, JITTER, &mut rng,
+                    );
+                }
+            });
+        })
+    });
+}
+
+fn centroid(c: &mut Criterion) {
+    let (playfield, data) = disc_frame();
+    let frame = FrameView::new(&data, playfield.size, 1);
+
+    c.bench_function("centroid", |b| b.iter(|| frame.centroid(&playfield)));
+}
+
+fn distance_field(c: &mut Criterion) {
+    let (playfield, data) = disc_frame();
+    let frame = FrameView::new(&data, playfield.size, 1);
+
+    c.bench_function("distance field", |b| b.iter(|| DistanceField::new(&frame)));
+}
+
+fn otsu_threshold(c: &mut Criterion) {
+    let (playfield, data) = disc_frame();
+    let frame = FrameView::new(&data, playfield.size, 1);
+
+    c.bench_function("otsu threshold", |b| b.iter(|| frame.otsu_threshold()));
+}
+
+criterion_group!(
+    benches,
+    step,
+    step_parallel,
+    centroid,
+    distance_field,
+    otsu_threshold
+);
+criterion_main!(benches);
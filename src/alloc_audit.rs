@@ -0,0 +1,188 @@
+//! Counts the heap allocations made by the hot systems each frame, to keep them allocation free.
+//!
+//! Only active with the `alloc-audit` feature, which swaps in a counting global allocator.
+//! Without it, [`scope`] compiles down to nothing. Allocations are counted per thread, so
+//! systems iterating in parallel open a scope in each task too.
+
+use bevy::prelude::*;
+
+/// Systems expected not to allocate once playback is running
+#[derive(Clone, Copy, Debug)]
+pub enum Audited {
+    MoveParticle,
+    SwapFrame,
+}
+
+/// Marks the allocations made until it is dropped as belonging to a system
+#[must_use]
+pub struct Scope {
+    #[cfg(feature = "alloc-audit")]
+    system: Audited,
+    /// Allocations made on this thread when it was opened, `None` inside another scope,
+    /// which already counts them
+    #[cfg(feature = "alloc-audit")]
+    start: Option<u64>,
+}
+
+#[cfg(not(feature = "alloc-audit"))]
+pub fn scope(_system: Audited) -> Scope {
+    Scope {}
+}
+
+#[cfg(feature = "alloc-audit")]
+pub use counting::scope;
+
+#[cfg(feature = "alloc-audit")]
+mod counting {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+        sync::atomic::{AtomicU64, Ordering},
+    };
+
+    use bevy::diagnostic::DiagnosticId;
+
+    use super::{Audited, Scope};
+
+    impl Audited {
+        pub const ALL: [Audited; 2] = [Audited::MoveParticle, Audited::SwapFrame];
+
+        pub fn name(self) -> &'static str {
+            match self {
+                Audited::MoveParticle => "alloc/move_particle",
+                Audited::SwapFrame => "alloc/swap_frame",
+            }
+        }
+
+        pub fn id(self) -> DiagnosticId {
+            match self {
+                Audited::MoveParticle => {
+                    DiagnosticId::from_u128(0x5f0b_2b43_86b1_4c5e_9d0a_7c1e_3a4f_6d21)
+                }
+                Audited::SwapFrame => {
+                    DiagnosticId::from_u128(0x9c3e_1d7a_52f4_4b08_a6e2_0f8d_4b1c_7e93)
+                }
+            }
+        }
+    }
+
+    /// Allocations made since the last report, for every audited system
+    static COUNTS: [AtomicU64; Audited::ALL.len()] = [AtomicU64::new(0), AtomicU64::new(0)];
+
+    thread_local! {
+        /// Allocations made on this thread, so parallel iteration on other threads is not counted twice
+        static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+        /// Whether a scope is open on this thread
+        static IN_SCOPE: Cell<bool> = const { Cell::new(false) };
+    }
+
+    fn allocations() -> u64 {
+        ALLOCATIONS.with(Cell::get)
+    }
+
+    pub fn scope(system: Audited) -> Scope {
+        let outermost = !IN_SCOPE.with(|in_scope| in_scope.replace(true));
+        Scope {
+            system,
+            start: outermost.then(allocations),
+        }
+    }
+
+    impl Drop for Scope {
+        fn drop(&mut self) {
+            let Some(start) = self.start else {
+                return;
+            };
+            IN_SCOPE.with(|in_scope| in_scope.set(false));
+            let made = allocations() - start;
+            if made > 0 {
+                COUNTS[self.system as usize].fetch_add(made, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Takes the allocations counted since the last call
+    pub fn take(system: Audited) -> u64 {
+        COUNTS[system as usize].swap(0, Ordering::Relaxed)
+    }
+
+    struct Counting;
+
+    // SAFETY: forwards everything to the system allocator
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            // `try_with` as the thread local may already be gone while a thread shuts down
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.alloc_zeroed(layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: Counting = Counting;
+}
+
+/// Reports the allocation counts as diagnostics, logged every second
+pub struct AllocAuditPlugin;
+
+impl Plugin for AllocAuditPlugin {
+    #[cfg(not(feature = "alloc-audit"))]
+    fn build(&self, _app: &mut App) {}
+
+    #[cfg(feature = "alloc-audit")]
+    fn build(&self, app: &mut App) {
+        use bevy::diagnostic::LogDiagnosticsPlugin;
+
+        let ids = Audited::ALL.iter().map(|system| system.id()).collect();
+        app.add_plugin(LogDiagnosticsPlugin::filtered(ids))
+            .add_startup_system(register_diagnostics)
+            .add_system(report_allocations.in_base_set(CoreSet::Last));
+    }
+}
+
+#[cfg(feature = "alloc-audit")]
+fn register_diagnostics(mut diagnostics: ResMut<bevy::diagnostic::Diagnostics>) {
+    use bevy::diagnostic::Diagnostic;
+
+    for system in Audited::ALL {
+        diagnostics.add(Diagnostic::new(system.id(), system.name(), 20));
+    }
+}
+
+/// Allocations each audited system made per frame lately, for the F3 overlay
+#[cfg(feature = "alloc-audit")]
+pub fn summary(diagnostics: &bevy::diagnostic::Diagnostics) -> String {
+    Audited::ALL
+        .iter()
+        .map(|system| {
+            let average = diagnostics
+                .get(system.id())
+                .and_then(|diagnostic| diagnostic.average())
+                .unwrap_or_default();
+            let name = system.name().trim_start_matches("alloc/");
+            format!("{name} {average:.1}")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(feature = "alloc-audit")]
+fn report_allocations(mut diagnostics: ResMut<bevy::diagnostic::Diagnostics>) {
+    for system in Audited::ALL {
+        let count = counting::take(system);
+        diagnostics.add_measurement(system.id(), || count as f64);
+    }
+}
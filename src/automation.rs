@@ -0,0 +1,147 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{multicam::Multicam, palette::Palette, panel::Tuning, CurrentFrame, Particle};
+
+const PATH: &str = "automation.ron";
+
+/// Parameters held over a range of frames, unset ones are left alone
+#[derive(Deserialize, Clone, Debug)]
+struct Segment {
+    /// First frame of the segment
+    from: usize,
+    /// Frame the segment ends before
+    to: usize,
+    /// Fraction of the particles shown, from 0 to 1
+    #[serde(default)]
+    particles: Option<f32>,
+    /// Multiplier on the jitter of moving particles
+    #[serde(default)]
+    jitter: Option<f32>,
+    #[serde(default)]
+    palette: Option<Palette>,
+    /// Name of a shot in `shots.ron` to hold the camera on
+    #[serde(default)]
+    camera: Option<String>,
+}
+
+impl Segment {
+    fn contains(&self, frame: usize) -> bool {
+        (self.from..self.to).contains(&frame)
+    }
+}
+
+fn read_segments() -> Vec<Segment> {
+    let text = match fs::read_to_string(PATH) {
+        Ok(text) => text,
+        Err(e) => {
+            info!("no automation to play, couldn't read {PATH}: {e}");
+            return Vec::new();
+        }
+    };
+
+    ron::from_str(&text).unwrap_or_else(|e| {
+        warn!("couldn't parse {PATH}: {e}");
+        Vec::new()
+    })
+}
+
+/// Parameters keyframed across the video by `automation.ron`
+#[derive(Resource)]
+pub struct Automation {
+    segments: Vec<Segment>,
+    /// Segment the current frame is in
+    active: Option<usize>,
+    /// Palette picked before the active segment overrode it
+    saved_palette: Option<Palette>,
+    pub jitter: f32,
+    pub particles: f32,
+}
+
+impl Default for Automation {
+    fn default() -> Self {
+        Self {
+            segments: Vec::new(),
+            active: None,
+            saved_palette: None,
+            jitter: 1.0,
+            particles: 1.0,
+        }
+    }
+}
+
+pub fn load_automation(mut automation: ResMut<Automation>) {
+    automation.segments = read_segments();
+}
+
+/// Applies the segment the current frame is in, restoring what it overrode once it ends
+pub fn automate(
+    mut automation: ResMut<Automation>,
+    mut palette: ResMut<Palette>,
+    mut multicam: ResMut<Multicam>,
+    current: Res<CurrentFrame>,
+) {
+    let frame = current.front.index;
+    let active = automation.segments.iter().position(|s| s.contains(frame));
+    if active == automation.active {
+        return;
+    }
+    let automation = &mut *automation;
+
+    if let Some(previous) = automation.active.map(|idx| &automation.segments[idx]) {
+        if previous.camera.is_some() {
+            multicam.unpin();
+        }
+        if let Some(saved) = automation.saved_palette.take() {
+            *palette = saved;
+        }
+    }
+    automation.active = active;
+    automation.jitter = 1.0;
+    automation.particles = 1.0;
+
+    let Some(segment) = active.map(|idx| &automation.segments[idx]) else {
+        return;
+    };
+    debug!("automation segment {}..{}", segment.from, segment.to);
+
+    if let Some(jitter) = segment.jitter {
+        automation.jitter = jitter.max(0.0);
+    }
+    if let Some(particles) = segment.particles {
+        automation.particles = particles.clamp(0.0, 1.0);
+    }
+    if let Some(new) = segment.palette {
+        automation.saved_palette = Some(*palette);
+        *palette = new;
+    }
+    if let Some(name) = &segment.camera {
+        if !multicam.pin(name) {
+            warn!("{PATH}: no shot named {name:?}");
+        }
+    }
+}
+
+/// Hides particles past the automated fraction, further thinned from the panel
+pub fn thin_particles(
+    automation: Res<Automation>,
+    tuning: Res<Tuning>,
+    mut particles: Query<&mut Visibility, With<Particle>>,
+    spawned: Query<(), Added<Particle>>,
+) {
+    if !automation.is_changed() && !tuning.is_changed() && spawned.is_empty() {
+        return;
+    }
+
+    let fraction = automation.particles * tuning.particles;
+    let shown = (particles.iter().len() as f32 * fraction) as usize;
+    for (idx, mut visibility) in particles.iter_mut().enumerate() {
+        *visibility = if idx < shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
@@ -0,0 +1,203 @@
+use std::{fs, io, path::Path, time::Duration};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crystal::Crystallization,
+    idle::AutoPause,
+    low_power::LowPower,
+    msaa,
+    palette::{Gamma, Palette},
+    phase::Breathing,
+    sim::SpawnPattern,
+    texture::ParticleTexture,
+    trails::Trails,
+    Player, State,
+};
+
+const PATH: &str = "autosave.ron";
+const INTERVAL: Duration = Duration::from_secs(10);
+
+/// Bump when the layout of [`Autosave`] changes, and handle the old one in [`Autosave::migrate`]
+const VERSION: u32 = 1;
+
+/// Settings and playback position, kept on disk so a crash doesn't lose them
+#[derive(Resource, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Autosave {
+    version: u32,
+    pub palette: Palette,
+    pub frame: usize,
+    /// Samples per pixel of anti-aliasing
+    pub msaa: u32,
+    /// Amplitude of the particles' sway, in pixels
+    pub breathing: f32,
+    /// Pause and tick slowly while the window is in the background
+    pub auto_pause: bool,
+    pub texture: ParticleTexture,
+    /// Where particles start out, only read at launch
+    pub spawn: SpawnPattern,
+    pub crystallization: Crystallization,
+    /// Exponent on the particle colors
+    pub gamma: f32,
+    pub trails: Trails,
+}
+
+impl Default for Autosave {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            palette: Palette::default(),
+            frame: 0,
+            msaa: Msaa::default().samples(),
+            breathing: 0.0,
+            auto_pause: true,
+            texture: ParticleTexture::default(),
+            spawn: SpawnPattern::default(),
+            crystallization: Crystallization::default(),
+            gamma: Gamma::default().0,
+            trails: Trails::default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+impl Autosave {
+    fn read() -> Option<Self> {
+        let text = match fs::read_to_string(PATH) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("couldn't read {PATH}: {e}");
+                return None;
+            }
+        };
+
+        match Self::migrate(&text) {
+            Ok(save) => save,
+            Err(e) => {
+                warn!("couldn't parse {PATH}: {e}");
+                None
+            }
+        }
+    }
+
+    /// Upgrades an autosave written by any earlier version to the current layout
+    fn migrate(text: &str) -> ron::error::SpannedResult<Option<Self>> {
+        let Header { version } = ron::from_str(text)?;
+
+        match version {
+            // fields added since an autosave was written fall back on their defaults
+            1..=VERSION => Ok(Some(Self {
+                version: VERSION,
+                ..ron::from_str(text)?
+            })),
+            _ => {
+                warn!("{PATH} has unknown version {version}, ignoring it");
+                Ok(None)
+            }
+        }
+    }
+
+    fn write(&self) -> io::Result<()> {
+        let text = ron::ser::to_string_pretty(self, default()).map_err(io::Error::other)?;
+
+        // rename is atomic, so a crash mid-write leaves the previous autosave intact
+        let tmp = Path::new(PATH).with_extension("ron.tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(tmp, PATH)
+    }
+}
+
+#[derive(Resource)]
+pub struct AutosaveTimer(Timer);
+
+impl Default for AutosaveTimer {
+    fn default() -> Self {
+        Self(Timer::new(INTERVAL, TimerMode::Repeating))
+    }
+}
+
+pub fn load_autosave(mut commands: Commands) {
+    let save = Autosave::read().unwrap_or_default();
+
+    commands.insert_resource(save.palette);
+    commands.insert_resource(msaa::from_samples(save.msaa));
+    commands.insert_resource(Breathing(save.breathing));
+    commands.insert_resource(AutoPause::new(save.auto_pause));
+    commands.insert_resource(save.texture);
+    commands.insert_resource(save.crystallization);
+    commands.insert_resource(Gamma(save.gamma));
+    commands.insert_resource(save.trails);
+    commands.insert_resource(save);
+}
+
+/// Every setting kept in the autosave
+#[derive(SystemParam)]
+pub struct Settings<'w> {
+    palette: Res<'w, Palette>,
+    msaa: Res<'w, Msaa>,
+    breathing: Res<'w, Breathing>,
+    auto_pause: Res<'w, AutoPause>,
+    texture: Res<'w, ParticleTexture>,
+    crystallization: Res<'w, Crystallization>,
+    gamma: Res<'w, Gamma>,
+    trails: Res<'w, Trails>,
+    low_power: Res<'w, LowPower>,
+}
+
+impl Settings<'_> {
+    fn is_changed(&self) -> bool {
+        self.palette.is_changed()
+            || self.msaa.is_changed()
+            || self.breathing.is_changed()
+            || self.auto_pause.is_changed()
+            || self.texture.is_changed()
+            || self.crystallization.is_changed()
+            || self.gamma.is_changed()
+            || self.trails.is_changed()
+    }
+
+    fn store(&self, save: &mut Autosave) {
+        save.palette = *self.palette;
+        // keeps what was picked outside of low-power mode, which turns it off
+        if !self.low_power.0 {
+            save.msaa = self.msaa.samples();
+        }
+        save.breathing = self.breathing.0;
+        save.auto_pause = self.auto_pause.enabled;
+        save.texture = *self.texture;
+        save.crystallization = *self.crystallization;
+        save.gamma = self.gamma.0;
+        save.trails = *self.trails;
+    }
+}
+
+pub fn autosave(
+    mut save: ResMut<Autosave>,
+    mut timer: ResMut<AutosaveTimer>,
+    time: Res<Time>,
+    settings: Settings,
+    state: Res<State>,
+    player: Query<&Player>,
+) {
+    let changed = settings.is_changed() || state.is_changed();
+    if !timer.0.tick(time.delta()).just_finished() && !changed {
+        return;
+    }
+
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    settings.store(&mut save);
+    save.frame = player.play_index;
+
+    if let Err(e) = save.write() {
+        warn!("couldn't write {PATH}: {e}");
+    }
+}
@@ -0,0 +1,80 @@
+use std::iter;
+
+use bevy::prelude::*;
+
+use crate::{
+    layers::Layers,
+    locale::tr_with,
+    sim::{FrameView, MovementMode, Playfield},
+    threshold::Threshold,
+    toast::Toasts,
+    CurrentFrame,
+};
+
+pub fn cycle_movement_mode(
+    mut mode: ResMut<MovementMode>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::M) {
+        *mode = match *mode {
+            MovementMode::Jitter => MovementMode::Orbit,
+            MovementMode::Orbit => MovementMode::Attract,
+            MovementMode::Attract => MovementMode::Flow,
+            MovementMode::Flow => MovementMode::Outline,
+            MovementMode::Outline => MovementMode::Jitter,
+        };
+        toasts.push(tr_with("movement", &[("mode", &format!("{:?}", *mode))]));
+    }
+}
+
+/// Whether particles, or those of a layer, orbit the centroid of the silhouette
+pub fn is_orbiting(mode: Res<MovementMode>, layers: Res<Layers>) -> bool {
+    iter::once(*mode)
+        .chain(layers.0.iter().filter_map(|spec| spec.movement))
+        .any(|mode| mode == MovementMode::Orbit)
+}
+
+/// Whether particles, or those of a layer, steer by the distance field of the frame
+pub fn uses_field(mode: Res<MovementMode>, layers: Res<Layers>) -> bool {
+    iter::once(*mode)
+        .chain(layers.0.iter().filter_map(|spec| spec.movement))
+        .any(|mode| {
+            matches!(
+                mode,
+                MovementMode::Attract | MovementMode::Flow | MovementMode::Outline
+            )
+        })
+}
+
+/// Shape of the black region of the current frame
+#[derive(Resource, Default)]
+pub struct Silhouette {
+    /// Frame index this was computed for
+    index: usize,
+    /// Center of mass of the black pixels, in world space
+    pub centroid: Option<Vec2>,
+}
+
+pub fn find_centroid(
+    mut silhouette: ResMut<Silhouette>,
+    current: Res<CurrentFrame>,
+    images: Res<Assets<Image>>,
+    playfield: Res<Playfield>,
+    threshold: Res<Threshold>,
+) {
+    if silhouette.index == current.front.index && !threshold.is_changed() {
+        return;
+    }
+    let Some(image) = images.get(&current.front.image) else {
+        return;
+    };
+    if image.size().as_uvec2() != playfield.size {
+        return;
+    }
+
+    silhouette.index = current.front.index;
+    silhouette.centroid = threshold
+        .apply(FrameView::from_image(image))
+        .centroid(&playfield);
+}
@@ -0,0 +1,142 @@
+//! Turns a video into the frame archive played back by the app, through ffmpeg:
+//! ```text
+//! $ cargo run --release --bin pack -- bad_apple.mp4
+//! ```
+
+use std::{
+    fs,
+    io::{self, BufReader, Read},
+    path::PathBuf,
+    process::{Command, ExitCode, Stdio},
+};
+
+use bad_apple_particle::pack::{self, Metadata};
+
+const USAGE: &str = "usage: pack <video> [--size WIDTHxHEIGHT] [--fps FPS] [--out DIR]";
+
+struct Options {
+    video: PathBuf,
+    width: u32,
+    height: u32,
+    fps: f64,
+    out: PathBuf,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            video: PathBuf::new(),
+            width: 480,
+            height: 360,
+            fps: 30.0,
+            out: PathBuf::from("assets"),
+        };
+        let mut video = None;
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+            match arg.as_str() {
+                "--size" => {
+                    let size = value()?;
+                    let (width, height) = size
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                        .ok_or(format!("invalid size {size}"))?;
+                    options.width = width;
+                    options.height = height;
+                }
+                "--fps" => {
+                    let fps = value()?;
+                    options.fps = fps.parse().map_err(|_| format!("invalid fps {fps}"))?;
+                }
+                "--out" => options.out = value()?.into(),
+                _ if video.is_none() && !arg.starts_with("--") => video = Some(arg.into()),
+                _ => return Err(format!("unexpected argument {arg}")),
+            }
+        }
+
+        options.video = video.ok_or("missing video")?;
+        if options.width == 0 || options.height == 0 || options.fps <= 0.0 {
+            return Err("size and fps must be positive".to_string());
+        }
+        Ok(options)
+    }
+}
+
+fn pack(options: &Options) -> io::Result<Metadata> {
+    let mut ffmpeg = Command::new("ffmpeg")
+        .args(["-loglevel", "error", "-i"])
+        .arg(&options.video)
+        .args([
+            "-vf",
+            &format!(
+                "fps={},scale={}:{}",
+                options.fps, options.width, options.height
+            ),
+            "-pix_fmt",
+            "gray",
+            "-f",
+            "rawvideo",
+            "-",
+        ])
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdout = BufReader::new(ffmpeg.stdout.take().expect("stdout is piped"));
+
+    let mut frame = vec![0; (options.width * options.height) as usize];
+    let mut archive = Vec::new();
+    let mut offsets = vec![0];
+    loop {
+        match stdout.read_exact(&mut frame) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+        pack::encode(&frame, &mut archive);
+        offsets.push(archive.len() as u64);
+    }
+
+    let status = ffmpeg.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("ffmpeg exited with {status}")));
+    }
+
+    let metadata = Metadata {
+        width: options.width,
+        height: options.height,
+        fps: options.fps,
+        offsets,
+    };
+    let text = ron::ser::to_string(&metadata).map_err(io::Error::other)?;
+
+    fs::create_dir_all(&options.out)?;
+    fs::write(options.out.join(pack::ARCHIVE), &archive)?;
+    fs::write(options.out.join(pack::METADATA), text)?;
+    Ok(metadata)
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match pack(&options) {
+        Ok(metadata) => {
+            let archive = options.out.join(pack::ARCHIVE);
+            println!(
+                "packed {} frames into {}",
+                metadata.frames(),
+                archive.display()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("couldn't pack {}: {e}", options.video.display());
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Glow around the particles brighter than white, cycled with `Y`. The main camera renders
+//! in HDR while it's on, and moving particles get brighter than 1 the longer they move.
+
+use bevy::{
+    core_pipeline::bloom::{BloomCompositeMode, BloomPrefilterSettings, BloomSettings},
+    prelude::*,
+};
+
+use crate::{
+    locale::{tr, tr_with},
+    toast::Toasts,
+    MainCamera,
+};
+
+/// Intensities to pick from, off first
+const LEVELS: [f32; 4] = [0.0, 0.1, 0.25, 0.5];
+/// Brightness of the particles that moved the longest while glowing, white being 1
+const BOOST: f32 = 3.0;
+
+#[derive(Resource, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Bloom(usize);
+
+impl Bloom {
+    pub fn is_on(self) -> bool {
+        self.0 > 0
+    }
+
+    /// Multiplier on the colors of particles that moved the longest
+    pub fn boost(self) -> f32 {
+        if self.is_on() {
+            BOOST
+        } else {
+            1.0
+        }
+    }
+
+    fn settings(self) -> BloomSettings {
+        BloomSettings {
+            intensity: LEVELS[self.0],
+            // the white backdrop and everything else up to white stays as is
+            prefilter_settings: BloomPrefilterSettings {
+                threshold: 1.0,
+                threshold_softness: 0.5,
+            },
+            composite_mode: BloomCompositeMode::Additive,
+            ..BloomSettings::NATURAL
+        }
+    }
+}
+
+pub fn cycle_bloom(
+    mut bloom: ResMut<Bloom>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::Y) {
+        bloom.0 = (bloom.0 + 1) % LEVELS.len();
+        toasts.push(if bloom.is_on() {
+            tr_with("bloom", &[("level", &LEVELS[bloom.0])])
+        } else {
+            tr("bloom-off")
+        });
+    }
+}
+
+/// Switches the main camera to HDR with bloom, or back to plain rendering
+pub fn apply_bloom(
+    mut commands: Commands,
+    bloom: Res<Bloom>,
+    mut camera: Query<(Entity, &mut Camera), With<MainCamera>>,
+) {
+    if !bloom.is_changed() {
+        return;
+    }
+
+    let (entity, mut camera) = camera.single_mut();
+    camera.hdr = bloom.is_on();
+    if bloom.is_on() {
+        commands.entity(entity).insert(bloom.settings());
+    } else {
+        commands.entity(entity).remove::<BloomSettings>();
+    }
+}
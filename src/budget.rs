@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::{
+    locale::tr,
+    sim::{FrameView, Grid, Playfield, SimRng},
+    threshold::Threshold,
+    toast::Toasts,
+    CurrentFrame, Particle,
+};
+
+/// Side of a budget cell, in pixels
+const CELL: u32 = 16;
+/// How far above its fair share a cell can go before particles are taken out of it
+const SLACK: f32 = 1.5;
+/// Fraction of all particles that can be migrated in a single tick
+const MAX_MIGRATION: f32 = 0.01;
+/// Attempts at finding a dark pixel in a target cell before giving up on it
+const TRIES: usize = 8;
+
+/// Soft per-cell particle caps, moving surplus particles from crowded regions to
+/// starved ones so thin features don't go empty while big areas are oversaturated
+#[derive(Resource, Default)]
+pub struct RegionBudgets {
+    pub enabled: bool,
+    /// Frame index the dark pixel counts are from
+    index: usize,
+    grid: Option<Grid>,
+    dark: Vec<u32>,
+}
+
+pub fn toggle_budgets(
+    mut budgets: ResMut<RegionBudgets>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::B) {
+        budgets.enabled = !budgets.enabled;
+        toasts.push(if budgets.enabled {
+            tr("budgets-on")
+        } else {
+            tr("budgets-off")
+        });
+    }
+}
+
+pub fn budgets_enabled(budgets: Res<RegionBudgets>) -> bool {
+    budgets.enabled
+}
+
+pub fn balance_regions(
+    mut budgets: ResMut<RegionBudgets>,
+    mut particles: Query<(&mut Transform, &mut Particle)>,
+    current: Res<CurrentFrame>,
+    images: Res<Assets<Image>>,
+    playfield: Res<Playfield>,
+    threshold: Res<Threshold>,
+    mut sim_rng: ResMut<SimRng>,
+) {
+    let Some(image) = images.get(&current.front.image) else {
+        return;
+    };
+    if image.size().as_uvec2() != playfield.size {
+        return;
+    }
+    let frame = threshold.apply(FrameView::from_image(image));
+
+    let grid = Grid::new(&playfield, CELL);
+    if budgets.grid != Some(grid) || budgets.index != current.front.index || threshold.is_changed()
+    {
+        budgets.dark = frame.dark_per_cell(&grid);
+        budgets.grid = Some(grid);
+        budgets.index = current.front.index;
+    }
+
+    let total_dark: u32 = budgets.dark.iter().sum();
+    if total_dark == 0 {
+        return;
+    }
+
+    let mut counts = vec![0u32; grid.len()];
+    for (transform, _) in &particles {
+        counts[grid.cell_of(transform.translation.truncate(), &playfield)] += 1;
+    }
+
+    let total = particles.iter().len();
+    let density = total as f32 / total_dark as f32;
+    let target = |cell: usize| budgets.dark[cell] as f32 * density;
+
+    let mut starved: Vec<_> = (0..grid.len())
+        .filter(|&cell| budgets.dark[cell] > 0 && (counts[cell] as f32) < target(cell))
+        .collect();
+    if starved.is_empty() {
+        return;
+    }
+
+    let mut rng = sim_rng.serial();
+    starved.shuffle(&mut rng);
+
+    let mut migrations = (total as f32 * MAX_MIGRATION) as usize;
+    let mut starved = starved.into_iter().peekable();
+
+    for (mut transform, mut standstill) in &mut particles {
+        if migrations == 0 {
+            break;
+        }
+
+        // particles wandering over the background are left to find their own way
+        let from = grid.cell_of(transform.translation.truncate(), &playfield);
+        if budgets.dark[from] == 0 || counts[from] as f32 <= target(from) * SLACK {
+            continue;
+        }
+
+        // skip over cells that got their fill, or where no dark pixel turns up
+        let destination = loop {
+            let Some(&to) = starved.peek() else {
+                break None;
+            };
+            if counts[to] as f32 >= target(to) {
+                starved.next();
+                continue;
+            }
+
+            let pixel = (0..TRIES)
+                .map(|_| grid.random_pixel(to, &playfield, &mut rng))
+                .find(|pixel| frame.is_dark((pixel.y * playfield.size.x + pixel.x) as usize));
+            match pixel {
+                Some(pixel) => break Some((to, pixel)),
+                None => {
+                    starved.next();
+                }
+            }
+        };
+        let Some((to, pixel)) = destination else {
+            break;
+        };
+
+        transform.translation = playfield
+            .position(pixel.as_vec2())
+            .extend(transform.translation.z);
+        standstill.0 = current.front.index;
+
+        counts[from] -= 1;
+        counts[to] += 1;
+        migrations -= 1;
+    }
+}
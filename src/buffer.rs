@@ -0,0 +1,124 @@
+//! Sizes the frame buffer to a memory budget and to how long frames take to load, and
+//! unloads frames once they have been shown instead of waiting on their handles to drop
+
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{config::Config, CurrentFrame, Frame};
+
+/// Seconds of video buffered ahead when frames load instantly
+const AHEAD_SECS: f64 = 4.0;
+/// Seconds buffered ahead for every second a frame takes to load, to ride out slow disks
+const LATENCY_HEADROOM: f64 = 16.0;
+/// Frames buffered however large they are
+const MIN_FRAMES: usize = 8;
+/// Weight of the latest load in the smoothed latency
+const SMOOTHING: f64 = 0.1;
+/// Frames read at once in the browser, each one an HTTP request, where too many would hold
+/// up the page and too few would fall behind on slow connections
+const MIN_IN_FLIGHT: usize = 2;
+const MAX_IN_FLIGHT: usize = 32;
+/// Frames read at once for every frame that plays while one loads
+const IN_FLIGHT_HEADROOM: f64 = 2.0;
+
+/// How many frames to keep loaded ahead of the shown one
+#[derive(Resource)]
+pub struct FrameBudget {
+    /// Memory the buffered frames may take up, in bytes
+    bytes: usize,
+    /// Size of the last decoded frame, estimated from the configured size before the first one
+    frame_bytes: usize,
+    /// Smoothed time a frame takes to read and decode, in seconds
+    latency: f64,
+}
+
+impl FrameBudget {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            bytes: config.buffer_mb * 1024 * 1024,
+            frame_bytes: (config.size.x * config.size.y * 4) as usize,
+            latency: 0.0,
+        }
+    }
+
+    /// Records a frame going in the buffer, that took `latency` to load when it was measured
+    pub fn record(&mut self, image: &Image, latency: Option<Duration>) {
+        self.frame_bytes = image.data.len();
+        if let Some(latency) = latency {
+            self.latency += (latency.as_secs_f64() - self.latency) * SMOOTHING;
+        }
+    }
+
+    /// Frames to buffer at `fps`, as many as loading needs but no more than fit the budget
+    pub fn frames(&self, fps: f64) -> usize {
+        let wanted = (fps * (AHEAD_SECS + LATENCY_HEADROOM * self.latency)).ceil() as usize;
+        let affordable = self.bytes / self.frame_bytes.max(1);
+        wanted.min(affordable).max(MIN_FRAMES)
+    }
+
+    /// Frames to read at once at `fps`, as many as keep up with the latency in the browser,
+    /// and all of them natively
+    pub fn in_flight(&self, fps: f64) -> usize {
+        if !cfg!(target_arch = "wasm32") {
+            return usize::MAX;
+        }
+        let wanted = (fps * self.latency * IN_FLIGHT_HEADROOM).ceil() as usize;
+        wanted.clamp(MIN_IN_FLIGHT, MAX_IN_FLIGHT)
+    }
+}
+
+/// Frames that were shown, kept until the effects comparing frames are done with them,
+/// or for as long as the history goes back
+#[derive(Resource)]
+pub struct ShownFrames {
+    /// Most recent last
+    frames: VecDeque<Frame>,
+    current: Frame,
+    /// Frames kept after they stop being shown
+    kept: usize,
+}
+
+impl ShownFrames {
+    /// Frames always kept, the interpolation and scene cuts look one back
+    const MIN_KEPT: usize = 1;
+
+    pub fn new(history: usize) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            current: Frame::default(),
+            kept: history.max(Self::MIN_KEPT),
+        }
+    }
+
+    /// Takes back the frame shown right before the current one, which becomes current again.
+    /// `None` once the history runs out or goes back to before a seek or the first frame.
+    pub fn rewind(&mut self) -> Option<Frame> {
+        let previous = self
+            .frames
+            .back()
+            .filter(|frame| frame.index > 0 && frame.index + 1 == self.current.index)?
+            .clone();
+        self.frames.pop_back();
+        self.current = previous.clone();
+        Some(previous)
+    }
+}
+
+/// Removes the images of frames that were shown, freeing their texture on the GPU too
+pub fn unload_frames(
+    mut shown: ResMut<ShownFrames>,
+    mut images: ResMut<Assets<Image>>,
+    current: Res<CurrentFrame>,
+) {
+    if shown.current.image == current.front.image {
+        return;
+    }
+    let previous = std::mem::replace(&mut shown.current, current.front.clone());
+    shown.frames.push_back(previous);
+
+    while shown.frames.len() > shown.kept {
+        let frame = shown.frames.pop_front().expect("more than kept");
+        images.remove(&frame.image);
+    }
+}
@@ -0,0 +1,608 @@
+//! Records the particles to a video or an animated GIF, one image per video frame, and takes
+//! screenshots of them.
+//!
+//! While recording, a second camera renders the playfield into an image the size of the source,
+//! which is copied back from the GPU and piped to ffmpeg on a background thread. Screenshots
+//! take a single image from that camera and save it as a PNG. With `--supersample` the image is
+//! a few times larger, then scaled back down to the size of the source for smoother edges.
+
+use std::{
+    fs,
+    io::{BufWriter, Write},
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    thread,
+};
+
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::*,
+    render::{
+        camera::{RenderTarget, ScalingMode},
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        main_graph::node::CAMERA_DRIVER,
+        render_asset::RenderAssets,
+        render_graph::{self, RenderGraph},
+        render_resource::{
+            Buffer, BufferDescriptor, BufferUsages, Extent3d, ImageCopyBuffer, ImageDataLayout,
+            MapMode, TextureDimension, TextureFormat, TextureUsages,
+        },
+        renderer::{RenderContext, RenderDevice},
+        RenderApp, RenderSet,
+    },
+};
+
+use crate::{
+    config::Config,
+    locale::{tr, tr_with},
+    scene3d::ViewMode,
+    sim::Playfield,
+    toast::Toasts,
+    CurrentFrame, MainCamera,
+};
+
+const NODE: &str = "capture";
+/// Where recordings started with the hotkey go, numbered after the first one
+const RECORDING: &str = "recording.mp4";
+/// Folder screenshots are saved in by default
+pub const SCREENSHOTS: &str = "screenshots";
+
+/// Renders the playfield to an image while recording
+#[derive(Component)]
+pub struct CaptureCamera;
+
+/// Recording in progress, shared with the render world to copy its frames
+#[derive(Resource, Clone, ExtractResource, Default)]
+pub struct Recorder {
+    target: Handle<Image>,
+    /// Size of the captured image, `scale` times that of the source
+    size: UVec2,
+    scale: u32,
+    /// Frames go to the ffmpeg thread, dropped to finish the file, each with the number of
+    /// video frames skipped before it
+    frames: Option<Sender<(usize, Vec<u8>)>>,
+    /// Whether a new video frame is shown, to be captured after this render
+    capture: bool,
+    /// Video frames skipped since the last capture, when rendering falls behind the video
+    skipped: usize,
+    /// Video frame of the last capture, none before the first one
+    index: Option<usize>,
+    /// Recordings started with the hotkey, to number their files
+    count: usize,
+    /// Screenshot waiting for the next render, taken by the thread saving it
+    shot: Option<Sender<Vec<u8>>>,
+}
+
+/// Screenshots saved on background threads, reporting back the file or why it failed
+#[derive(Resource)]
+pub struct Screenshots {
+    report: Sender<Result<PathBuf, String>>,
+    saved: Mutex<Receiver<Result<PathBuf, String>>>,
+}
+
+impl Default for Screenshots {
+    fn default() -> Self {
+        let (report, saved) = mpsc::channel();
+        Self {
+            report,
+            saved: Mutex::new(saved),
+        }
+    }
+}
+
+pub struct CapturePlugin;
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        // there is no ffmpeg to pipe the frames to in the browser
+        if cfg!(target_arch = "wasm32") {
+            return;
+        }
+
+        app.init_resource::<Recorder>()
+            .init_resource::<Screenshots>()
+            .add_plugin(ExtractResourcePlugin::<Recorder>::default())
+            .add_startup_system(start_recording)
+            .add_system(toggle_recording)
+            .add_system(take_screenshot)
+            .add_system(finish_screenshot)
+            .add_system(follow_camera.after(toggle_recording).after(take_screenshot))
+            .add_system(mark_capture.in_base_set(CoreSet::PostUpdate));
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_system(prepare_readback.in_set(RenderSet::Prepare))
+            .add_system(read_back.in_set(RenderSet::Cleanup));
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        graph.add_node(NODE, CaptureNode);
+        graph.add_node_edge(CAMERA_DRIVER, NODE);
+    }
+}
+
+/// Encodes raw RGBA frames `scale` times the size of the source with ffmpeg, scaled down to it.
+/// ffmpeg picks the format from the file extension.
+/// The previous frame is written again for each skipped one, keeping the source frame rate.
+fn encode(path: &Path, size: UVec2, scale: u32, fps: f64, frames: Receiver<(usize, Vec<u8>)>) {
+    let gif = path.extension().and_then(|ext| ext.to_str()) == Some("gif");
+    let mut filters = Vec::new();
+    if scale > 1 {
+        // averaging the pixels of each block
+        filters.push(format!("scale={}:{}:flags=area", size.x, size.y));
+    }
+    if !gif {
+        // mp4 players expect 4:2:0, which needs even dimensions
+        filters.push("pad=ceil(iw/2)*2:ceil(ih/2)*2".to_string());
+    }
+
+    let ffmpeg = Command::new("ffmpeg")
+        .args([
+            "-loglevel",
+            "error",
+            "-y",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgba",
+        ])
+        .args(["-s", &format!("{}x{}", size.x * scale, size.y * scale)])
+        .args(["-r", &fps.to_string(), "-i", "-"])
+        .args(if gif {
+            &[][..]
+        } else {
+            &["-pix_fmt", "yuv420p"]
+        })
+        .args(if filters.is_empty() {
+            vec![]
+        } else {
+            vec!["-vf".to_string(), filters.join(",")]
+        })
+        .arg(path)
+        .stdin(Stdio::piped())
+        .spawn();
+    let mut ffmpeg = match ffmpeg {
+        Ok(ffmpeg) => ffmpeg,
+        Err(e) => {
+            warn!("couldn't start ffmpeg to record {}: {e}", path.display());
+            return;
+        }
+    };
+
+    let mut stdin = BufWriter::new(ffmpeg.stdin.take().expect("stdin is piped"));
+    let mut last = Vec::new();
+    for (skipped, frame) in frames {
+        let written = (0..skipped)
+            .map(|_| last.as_slice())
+            .chain([frame.as_slice()])
+            .try_for_each(|frame| stdin.write_all(frame));
+        if let Err(e) = written {
+            warn!("couldn't record {}: {e}", path.display());
+            break;
+        }
+        last = frame;
+    }
+    drop(stdin);
+
+    match ffmpeg.wait() {
+        Ok(status) if status.success() => info!("recorded {}", path.display()),
+        Ok(status) => warn!("ffmpeg failed recording {}: {status}", path.display()),
+        Err(e) => warn!("couldn't record {}: {e}", path.display()),
+    }
+}
+
+fn target_image(size: UVec2) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC | TextureUsages::RENDER_ATTACHMENT;
+    image
+}
+
+impl Recorder {
+    fn start(
+        &mut self,
+        path: PathBuf,
+        commands: &mut Commands,
+        images: &mut Assets<Image>,
+        playfield: &Playfield,
+        mode: ViewMode,
+        fps: f64,
+    ) {
+        let (size, scale) = (playfield.size, self.scale);
+        let (frames, receiver) = mpsc::channel();
+        thread::spawn(move || encode(&path, size, scale, fps, receiver));
+
+        if !self.is_open() {
+            self.open(commands, images, playfield, mode);
+        }
+        self.frames = Some(frames);
+        self.index = None;
+    }
+
+    /// Whether the capture camera is rendering, for a recording or a screenshot
+    fn is_open(&self) -> bool {
+        self.frames.is_some() || self.shot.is_some()
+    }
+
+    /// Spawns the camera rendering into the captured image, a 3D one following the orbiting
+    /// camera in `--mode 3d`
+    fn open(
+        &mut self,
+        commands: &mut Commands,
+        images: &mut Assets<Image>,
+        playfield: &Playfield,
+        mode: ViewMode,
+    ) {
+        let size = playfield.size;
+        self.target = images.add(target_image(size * self.scale));
+        self.size = size * self.scale;
+
+        if mode == ViewMode::Space {
+            commands.spawn((
+                CaptureCamera,
+                Camera3dBundle {
+                    camera: Camera {
+                        target: RenderTarget::Image(self.target.clone()),
+                        order: -2,
+                        ..default()
+                    },
+                    camera_3d: Camera3d {
+                        clear_color: ClearColorConfig::Custom(Color::BLACK),
+                        ..default()
+                    },
+                    ..default()
+                },
+                UiCameraConfig { show_ui: false },
+            ));
+            return;
+        }
+        commands.spawn((
+            CaptureCamera,
+            Camera2dBundle {
+                camera: Camera {
+                    target: RenderTarget::Image(self.target.clone()),
+                    // before the main camera, which clears the window
+                    order: -1,
+                    ..default()
+                },
+                camera_2d: Camera2d {
+                    clear_color: ClearColorConfig::Custom(Color::BLACK),
+                },
+                projection: OrthographicProjection {
+                    scaling_mode: ScalingMode::Fixed {
+                        width: size.x as f32,
+                        height: size.y as f32,
+                    },
+                    ..default()
+                },
+                ..default()
+            },
+            UiCameraConfig { show_ui: false },
+        ));
+    }
+}
+
+/// Starts recording with `--record`
+pub fn start_recording(
+    mut commands: Commands,
+    mut recorder: ResMut<Recorder>,
+    mut images: ResMut<Assets<Image>>,
+    playfield: Res<Playfield>,
+    config: Res<Config>,
+) {
+    recorder.scale = config.supersample;
+    if let Some(path) = &config.record {
+        recorder.start(
+            path.clone(),
+            &mut commands,
+            &mut images,
+            &playfield,
+            config.mode,
+            config.fps,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn toggle_recording(
+    mut commands: Commands,
+    mut recorder: ResMut<Recorder>,
+    mut images: ResMut<Assets<Image>>,
+    mut toasts: ResMut<Toasts>,
+    camera: Query<Entity, With<CaptureCamera>>,
+    playfield: Res<Playfield>,
+    config: Res<Config>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if !keyboard.just_released(KeyCode::F9) {
+        return;
+    }
+
+    if recorder.frames.take().is_some() {
+        // a screenshot still needs the camera, it goes once that is saved
+        if recorder.shot.is_none() {
+            for camera in &camera {
+                commands.entity(camera).despawn();
+            }
+        }
+        toasts.push(tr("recording-stopped"));
+        return;
+    }
+
+    recorder.count += 1;
+    let path = match recorder.count {
+        1 => PathBuf::from(RECORDING),
+        count => PathBuf::from(RECORDING).with_file_name(format!("recording-{count}.mp4")),
+    };
+    toasts.push(tr_with("recording-to", &[("path", &path.display())]));
+    recorder.start(
+        path,
+        &mut commands,
+        &mut images,
+        &playfield,
+        config.mode,
+        config.fps,
+    );
+}
+
+/// Saves the next render to `frame_<index>.png` in the screenshots folder
+#[allow(clippy::too_many_arguments)]
+pub fn take_screenshot(
+    mut commands: Commands,
+    mut recorder: ResMut<Recorder>,
+    mut images: ResMut<Assets<Image>>,
+    screenshots: Res<Screenshots>,
+    playfield: Res<Playfield>,
+    current: Res<CurrentFrame>,
+    config: Res<Config>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if !keyboard.just_released(KeyCode::F12) || recorder.shot.is_some() {
+        return;
+    }
+
+    if !recorder.is_open() {
+        recorder.open(&mut commands, &mut images, &playfield, config.mode);
+    }
+    let (shot, receiver) = mpsc::channel();
+    recorder.shot = Some(shot);
+
+    let path = config
+        .screenshots
+        .join(format!("frame_{}.png", current.front.index));
+    let (size, scale, report) = (recorder.size, recorder.scale, screenshots.report.clone());
+    thread::spawn(move || {
+        // the camera goes away without rendering when the app quits
+        let Ok(frame) = receiver.recv() else {
+            return;
+        };
+        let _ = report.send(save_png(&path, size, scale, frame).map(|()| path));
+    });
+}
+
+/// Saves a frame of `size`, scaled down `scale` times
+fn save_png(path: &Path, size: UVec2, scale: u32, frame: Vec<u8>) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let image = Image::new(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        frame,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    let mut image = image.try_into_dynamic().map_err(|e| e.to_string())?;
+    if scale > 1 {
+        image = image.thumbnail_exact(size.x / scale, size.y / scale);
+    }
+    image.save(path).map_err(|e| e.to_string())
+}
+
+/// Reports saved screenshots, putting the camera away unless recording
+pub fn finish_screenshot(
+    mut commands: Commands,
+    mut recorder: ResMut<Recorder>,
+    mut toasts: ResMut<Toasts>,
+    screenshots: Res<Screenshots>,
+    camera: Query<Entity, With<CaptureCamera>>,
+) {
+    let Ok(saved) = screenshots.saved.lock().unwrap().try_recv() else {
+        return;
+    };
+    match saved {
+        Ok(path) => toasts.push(tr_with("saved", &[("path", &path.display())])),
+        Err(e) => {
+            warn!("couldn't save the screenshot: {e}");
+            toasts.push(tr("screenshot-failed"));
+        }
+    }
+
+    recorder.shot = None;
+    if recorder.frames.is_none() {
+        for camera in &camera {
+            commands.entity(camera).despawn();
+        }
+    }
+}
+
+/// Frames the recording like the main camera, zoomed in by the multicam.
+/// The 3D one follows the orbiting camera instead.
+#[allow(clippy::type_complexity)]
+pub fn follow_camera(
+    mut capture: Query<(&mut Transform, &mut OrthographicProjection), With<CaptureCamera>>,
+    main: Query<(&Transform, &OrthographicProjection), (With<MainCamera>, Without<CaptureCamera>)>,
+) {
+    let Ok((mut transform, mut projection)) = capture.get_single_mut() else {
+        return;
+    };
+    let (main_transform, main_projection) = main.single();
+    *transform = *main_transform;
+    projection.scale = main_projection.scale;
+}
+
+/// Captures the next render once a new video frame is shown, to record at the source frame rate.
+/// Video frames shown between two renders are counted to be filled in with the previous one,
+/// seeking back skips none.
+pub fn mark_capture(mut recorder: ResMut<Recorder>, current: Res<CurrentFrame>) {
+    let index = current.front.index;
+    let capture = recorder.frames.is_some() && recorder.index != Some(index);
+    recorder.skipped = match recorder.index {
+        Some(last) if capture => index.saturating_sub(last + 1),
+        _ => 0,
+    };
+    if capture {
+        recorder.index = Some(index);
+    }
+    recorder.capture = capture;
+}
+
+/// Buffer the recorded image is copied to, in the render world
+#[derive(Resource)]
+struct Readback {
+    buffer: Buffer,
+    size: UVec2,
+}
+
+impl Readback {
+    fn padded_row(size: UVec2) -> usize {
+        RenderDevice::align_copy_bytes_per_row(size.x as usize * 4)
+    }
+}
+
+fn prepare_readback(
+    mut commands: Commands,
+    recorder: Res<Recorder>,
+    readback: Option<Res<Readback>>,
+    device: Res<RenderDevice>,
+) {
+    if !recorder.is_open() || readback.is_some_and(|readback| readback.size == recorder.size) {
+        return;
+    }
+
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("capture_readback"),
+        size: (Readback::padded_row(recorder.size) * recorder.size.y as usize) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    commands.insert_resource(Readback {
+        buffer,
+        size: recorder.size,
+    });
+}
+
+/// Whether the recorded image was rendered this frame and copied to the readback buffer,
+/// for the recording or a screenshot
+fn is_capturing(world: &World) -> bool {
+    let recorder = world.resource::<Recorder>();
+    let ready = world
+        .get_resource::<Readback>()
+        .is_some_and(|readback| readback.size == recorder.size);
+    (recorder.capture && recorder.frames.is_some() || recorder.shot.is_some())
+        && ready
+        && world
+            .resource::<RenderAssets<Image>>()
+            .contains_key(&recorder.target)
+}
+
+/// Copies the recorded image once the cameras have drawn
+struct CaptureNode;
+
+impl render_graph::Node for CaptureNode {
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        if !is_capturing(world) {
+            return Ok(());
+        }
+        let recorder = world.resource::<Recorder>();
+        let readback = world.resource::<Readback>();
+        let image = &world.resource::<RenderAssets<Image>>()[&recorder.target];
+
+        render_context.command_encoder().copy_texture_to_buffer(
+            image.texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &readback.buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(Readback::padded_row(readback.size) as u32),
+                    rows_per_image: None,
+                },
+            },
+            Extent3d {
+                width: readback.size.x,
+                height: readback.size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Waits for the copy, then sends the image without its row padding to ffmpeg
+fn read_back(world: &mut World) {
+    if !is_capturing(world) {
+        return;
+    }
+    let readback = world.resource::<Readback>();
+    let device = world.resource::<RenderDevice>();
+    let slice = readback.buffer.slice(..);
+
+    let (mapped, result) = mpsc::channel();
+    slice.map_async(MapMode::Read, move |result| {
+        let _ = mapped.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    match result.recv() {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            warn!("couldn't read the recorded frame back: {e}");
+            return;
+        }
+        // the callback was dropped without being called, the device was lost
+        Err(_) => {
+            warn!("couldn't read the recorded frame back");
+            return;
+        }
+    }
+
+    let row = readback.size.x as usize * 4;
+    let frame: Vec<u8> = slice
+        .get_mapped_range()
+        .chunks(Readback::padded_row(readback.size))
+        .flat_map(|padded| &padded[..row])
+        .copied()
+        .collect();
+    readback.buffer.unmap();
+
+    let recorder = world.resource::<Recorder>();
+    // the thread saving the screenshot only takes the first one
+    if let Some(shot) = &recorder.shot {
+        let _ = shot.send(frame.clone());
+    }
+    if let Some(frames) = recorder.frames.as_ref().filter(|_| recorder.capture) {
+        // the encoder went away, the warning is on its side
+        let _ = frames.send((recorder.skipped, frame));
+    }
+}
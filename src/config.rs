@@ -0,0 +1,673 @@
+use std::{ffi::OsString, fmt::Debug, fs, path::PathBuf, time::Duration};
+
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::asset::FileAssetIo;
+use bevy::prelude::*;
+use clap::{
+    builder::ValueParser, parser::ValueSource, value_parser, Arg, ArgAction, ArgMatches, Command,
+};
+use serde::Deserialize;
+
+use crate::{
+    capture::SCREENSHOTS,
+    layers::LayerSpec,
+    palette::Palette,
+    present::Presentation,
+    reactivity::AudioReactivity,
+    scene3d::ViewMode,
+    sim::{MovementMode, THRESHOLD},
+    speed::{MAX_SPEED, MIN_SPEED},
+    subtitles::SUBTITLES_FILE,
+    sync::{CatchUp, Lag},
+    FPS, FRAMES, HEIGHT, PARTICLES, SOUNDTRACK, WIDTH,
+};
+
+/// Settings file read from the working directory, under the command line options
+pub const CONFIG_FILE: &str = "config.ron";
+
+/// Frame folder looked for in the assets folder
+const FRAMES_DIR: &str = "frames";
+/// Reach of the mouse buttons by default, in pixels
+const MOUSE_RADIUS: f32 = 80.0;
+/// Memory the buffered frames may take up by default, in megabytes
+const BUFFER_MB: usize = 256;
+/// Frames kept after they were shown by default, two seconds of the original video
+const HISTORY: usize = 60;
+/// Drift between the video and the soundtrack tolerated by default, in seconds
+const SYNC_TOLERANCE: f64 = 0.1;
+
+/// Settings of `config.ron`, every one optional.
+/// Options given on the command line take precedence over them.
+#[derive(Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct ConfigFile {
+    /// Folder of PNG frames, relative to the working directory or absolute
+    pub frames: Option<PathBuf>,
+    /// Soundtrack, relative to the assets folder or absolute
+    pub audio: Option<PathBuf>,
+    pub fps: Option<f64>,
+    pub speed: Option<f64>,
+    pub particles: Option<usize>,
+    /// Memory the frames loaded ahead may take up, in megabytes
+    pub buffer_mb: Option<usize>,
+    /// Frames kept loaded after they were shown
+    pub history: Option<usize>,
+    /// Width and height of the window
+    pub size: Option<(u32, u32)>,
+    pub mouse_radius: Option<f32>,
+    /// Multiplier on the jitter of moving particles
+    pub jitter: Option<f32>,
+    pub palette: Option<Palette>,
+    pub movement: Option<MovementMode>,
+    /// How strongly particles pulse with the bass, once toggled on
+    pub reactivity: Option<AudioReactivity>,
+    /// Groups of particles spawned on top of the others
+    pub layers: Option<Vec<LayerSpec>>,
+    pub present_mode: Option<Presentation>,
+    /// Highest frame rate to render at
+    pub max_fps: Option<f64>,
+    /// Low-power mode on or off, instead of following the battery
+    pub low_power: Option<bool>,
+}
+
+impl ConfigFile {
+    /// Reads `config.ron`, empty when there is none and `None` when it doesn't parse.
+    /// Values out of range are left out.
+    pub fn read() -> Option<Self> {
+        let text = match fs::read_to_string(CONFIG_FILE) {
+            Ok(text) => text,
+            Err(e) => {
+                info!("no settings to load, couldn't read {CONFIG_FILE}: {e}");
+                return Some(Self::default());
+            }
+        };
+
+        match ron::from_str::<Self>(&text) {
+            Ok(file) => Some(file.validated()),
+            Err(e) => {
+                warn!("couldn't parse {CONFIG_FILE}: {e}");
+                None
+            }
+        }
+    }
+
+    fn validated(mut self) -> Self {
+        fn check<T: Debug>(value: &mut Option<T>, key: &str, valid: impl Fn(&T) -> bool) {
+            if let Some(invalid) = value.as_ref().filter(|v| !valid(v)) {
+                warn!("ignoring {key} in {CONFIG_FILE}, {invalid:?} is out of range");
+                *value = None;
+            }
+        }
+
+        check(&mut self.fps, "fps", |&fps| fps > 0.0);
+        check(&mut self.max_fps, "max_fps", |&fps| fps > 0.0);
+        check(&mut self.speed, "speed", |speed| {
+            (MIN_SPEED..=MAX_SPEED).contains(speed)
+        });
+        check(&mut self.size, "size", |&(width, height)| {
+            width > 0 && height > 0
+        });
+        check(&mut self.mouse_radius, "mouse_radius", |&radius| {
+            radius > 0.0
+        });
+        check(&mut self.jitter, "jitter", |&jitter| jitter >= 0.0);
+        self
+    }
+}
+
+/// Options given on the command line, defaulting to the original video
+#[derive(Resource, Clone, Debug)]
+pub struct Config {
+    /// Folder of the extracted PNG frames, relative to the assets folder or absolute
+    pub frames_dir: PathBuf,
+    /// Frames there are to play from the PNGs
+    pub frames: usize,
+    /// Webcam to play instead of the frames, when set
+    pub webcam: Option<String>,
+    /// Audio input to follow the LTC timecode of instead of the player's own clock, when set
+    pub timecode: Option<String>,
+    /// Language of the text on screen, the system's when unset
+    pub lang: Option<String>,
+    /// Soundtrack, relative to the assets folder or absolute
+    pub audio: PathBuf,
+    /// Subtitles or lyrics, relative to the working directory or absolute
+    pub subtitles: PathBuf,
+    pub fps: f64,
+    /// Playback speed to start at, 1 being real time
+    pub speed: f64,
+    pub particles: usize,
+    /// Size of the window and of frames decoded from a video, in pixels
+    pub size: UVec2,
+    /// Start in borderless fullscreen instead of a window
+    pub fullscreen: bool,
+    pub present_mode: Presentation,
+    /// Highest frame rate to render at, uncapped when `None` unless in low-power mode
+    pub max_fps: Option<f64>,
+    /// Memory the frames loaded ahead may take up, in megabytes
+    pub buffer_mb: usize,
+    /// Frames kept loaded after they were shown, to rewind through
+    pub history: usize,
+    /// Low-power mode on or off, following the battery when `None`
+    pub low_power: Option<bool>,
+    /// Simulate the particles in a compute shader
+    pub gpu: bool,
+    /// Draw the particles as instances of a quad instead of sprites
+    pub instanced: bool,
+    /// Brightest value particles settle on
+    pub threshold: u8,
+    /// Read color frames by their luma rather than their red channel
+    pub luma: bool,
+    /// Dither gray pixels rather than cutting them off at the threshold
+    pub dither: bool,
+    pub mode: ViewMode,
+    /// How far the darkest pixels stand out of the plane in 3D, in pixels, when set
+    pub relief: Option<f32>,
+    /// Reach of the cursor when pulling or pushing particles, in pixels
+    pub mouse_radius: f32,
+    /// Drift between the video and the soundtrack before the video catches up
+    pub sync_tolerance: Duration,
+    pub catch_up: CatchUp,
+    pub lag: Lag,
+    /// Video or GIF to record the particles to
+    pub record: Option<PathBuf>,
+    /// Folder the screenshots are saved in
+    pub screenshots: PathBuf,
+    /// Times the source's size recordings and screenshots are rendered at, before being
+    /// scaled down to it
+    pub supersample: u32,
+    /// CSV file to append metrics to for every frame
+    pub metrics: Option<PathBuf>,
+    pub verify: bool,
+    pub bless: bool,
+    /// Video frames to play without a window to time the simulation, when set
+    pub headless: Option<usize>,
+    /// Seed of the spawn patterns and of every move, random when not given
+    pub seed: Option<u64>,
+    /// Settings read from `config.ron`, which is watched for changes when set
+    pub file: Option<ConfigFile>,
+}
+
+fn parse_size(text: &str) -> Result<UVec2, String> {
+    match text.split_once('x').map(|(w, h)| (w.parse(), h.parse())) {
+        Some((Ok(width), Ok(height))) if width > 0 && height > 0 => Ok(UVec2::new(width, height)),
+        _ => Err(format!("expected WIDTHxHEIGHT, got {text}")),
+    }
+}
+
+fn parse_fps(text: &str) -> Result<f64, String> {
+    match text.parse() {
+        Ok(fps) if fps > 0.0 => Ok(fps),
+        _ => Err(format!("expected a positive number, got {text}")),
+    }
+}
+
+fn parse_speed(text: &str) -> Result<f64, String> {
+    match text.parse() {
+        Ok(speed) if (MIN_SPEED..=MAX_SPEED).contains(&speed) => Ok(speed),
+        _ => Err(format!(
+            "expected a speed between {MIN_SPEED} and {MAX_SPEED}, got {text}"
+        )),
+    }
+}
+
+fn parse_radius(text: &str) -> Result<f32, String> {
+    match text.parse() {
+        Ok(radius) if radius > 0.0 => Ok(radius),
+        _ => Err(format!("expected a positive number of pixels, got {text}")),
+    }
+}
+
+fn parse_supersample(text: &str) -> Result<u32, String> {
+    match text.parse() {
+        Ok(factor) if (1..=4).contains(&factor) => Ok(factor),
+        _ => Err(format!("expected a factor from 1 to 4, got {text}")),
+    }
+}
+
+fn parse_tolerance(text: &str) -> Result<Duration, String> {
+    text.parse()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("expected a number of seconds, got {text}"))
+}
+
+fn parse_catch_up(text: &str) -> Result<CatchUp, String> {
+    match text {
+        "jump" => Ok(CatchUp::Jump),
+        "nudge" => Ok(CatchUp::Nudge),
+        _ => Err(format!("expected jump or nudge, got {text}")),
+    }
+}
+
+fn parse_mode(text: &str) -> Result<ViewMode, String> {
+    match text {
+        "2d" => Ok(ViewMode::Flat),
+        "3d" => Ok(ViewMode::Space),
+        _ => Err(format!("expected 2d or 3d, got {text}")),
+    }
+}
+
+fn parse_lag(text: &str) -> Result<Lag, String> {
+    match text {
+        "drop" => Ok(Lag::Drop),
+        "slow" => Ok(Lag::Slow),
+        _ => Err(format!("expected drop or slow, got {text}")),
+    }
+}
+
+fn parse_presentation(text: &str) -> Result<Presentation, String> {
+    match text {
+        "vsync" => Ok(Presentation::Vsync),
+        "mailbox" => Ok(Presentation::Mailbox),
+        "immediate" => Ok(Presentation::Immediate),
+        _ => Err(format!("expected vsync, mailbox or immediate, got {text}")),
+    }
+}
+
+fn command() -> Command {
+    Command::new(env!("CARGO_PKG_NAME"))
+        .about("Bad Apple!! played with particles")
+        .arg(
+            Arg::new("frames")
+                .long("frames")
+                .value_name("DIR")
+                .value_parser(value_parser!(PathBuf))
+                .help("Folder of PNG frames named out0001.png, out0002.png... [default: assets/frames]"),
+        )
+        .arg(
+            Arg::new("webcam")
+                .long("webcam")
+                .value_name("DEVICE")
+                .help("Play a webcam live instead of the frames, such as /dev/video0 on Linux, 0 on macOS or its name on Windows"),
+        )
+        .arg(
+            Arg::new("timecode")
+                .long("timecode")
+                .value_name("DEVICE")
+                .help("Follow the LTC timecode on this audio input, or the default one with 'default', instead of the player's own clock"),
+        )
+        .arg(
+            Arg::new("lang")
+                .long("lang")
+                .value_name("LANG")
+                .help("Language of the text on screen, en or fr, instead of the system's"),
+        )
+        .arg(
+            Arg::new("audio")
+                .long("audio")
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .default_value(SOUNDTRACK)
+                .help("Soundtrack, relative to the assets folder or absolute"),
+        )
+        .arg(
+            Arg::new("subtitles")
+                .long("subtitles")
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .default_value(SUBTITLES_FILE)
+                .help("Subtitles in SubRip format, or lyrics as lines of [mm:ss.xx] timestamps and text"),
+        )
+        .arg(
+            Arg::new("fps")
+                .long("fps")
+                .value_name("FPS")
+                .value_parser(ValueParser::new(parse_fps))
+                .default_value(FPS.to_string())
+                .help("Frame rate of the video"),
+        )
+        .arg(
+            Arg::new("speed")
+                .long("speed")
+                .value_name("SPEED")
+                .value_parser(ValueParser::new(parse_speed))
+                .default_value("1")
+                .help("Playback speed, from 0.25 for slow motion to 4 for fast forward"),
+        )
+        .arg(
+            Arg::new("particles")
+                .long("particles")
+                .value_name("COUNT")
+                .value_parser(value_parser!(usize))
+                .default_value(PARTICLES.to_string())
+                .help("Number of particles"),
+        )
+        .arg(
+            Arg::new("size")
+                .long("size")
+                .value_name("WIDTHxHEIGHT")
+                .value_parser(ValueParser::new(parse_size))
+                .default_value(format!("{WIDTH}x{HEIGHT}"))
+                .help("Size of the window, and of the frames when decoding a video"),
+        )
+        .arg(
+            Arg::new("fullscreen")
+                .long("fullscreen")
+                .action(ArgAction::SetTrue)
+                .help("Start in borderless fullscreen, F11 going back to the window"),
+        )
+        .arg(
+            Arg::new("present-mode")
+                .long("present-mode")
+                .value_name("vsync|mailbox|immediate")
+                .value_parser(ValueParser::new(parse_presentation))
+                .default_value("vsync")
+                .help("Wait on the display's refresh, replace the frame waiting for it where supported (else vsync), or show frames as soon as they are done"),
+        )
+        .arg(
+            Arg::new("max-fps")
+                .long("max-fps")
+                .value_name("FPS")
+                .value_parser(ValueParser::new(parse_fps))
+                .help("Highest frame rate to render at, sleeping off the rest of each frame to spare the CPU"),
+        )
+        .arg(
+            Arg::new("buffer-mb")
+                .long("buffer-mb")
+                .value_name("MB")
+                .value_parser(value_parser!(usize))
+                .default_value(BUFFER_MB.to_string())
+                .help("Memory the frames loaded ahead may take up, fewer are loaded when they load fast"),
+        )
+        .arg(
+            Arg::new("history")
+                .long("history")
+                .value_name("FRAMES")
+                .value_parser(value_parser!(usize))
+                .default_value(HISTORY.to_string())
+                .help("Frames kept loaded after they were shown, to rewind through with R"),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("N")
+                .value_parser(value_parser!(u64))
+                .help("Seed the particles so that two runs move them the same way"),
+        )
+        .arg(
+            Arg::new("mouse-radius")
+                .long("mouse-radius")
+                .value_name("PIXELS")
+                .value_parser(ValueParser::new(parse_radius))
+                .default_value(MOUSE_RADIUS.to_string())
+                .help("Reach of the cursor when holding a mouse button to pull or push particles"),
+        )
+        .arg(
+            Arg::new("sync-tolerance")
+                .long("sync-tolerance")
+                .value_name("SECS")
+                .value_parser(ValueParser::new(parse_tolerance))
+                .default_value(SYNC_TOLERANCE.to_string())
+                .help("Drift between the video and the soundtrack before the video catches up"),
+        )
+        .arg(
+            Arg::new("catch-up")
+                .long("catch-up")
+                .value_name("jump|nudge")
+                .value_parser(ValueParser::new(parse_catch_up))
+                .default_value("nudge")
+                .help("Whether the video jumps to the soundtrack's position or gradually speeds up or slows down"),
+        )
+        .arg(
+            Arg::new("lag")
+                .long("lag")
+                .value_name("drop|slow")
+                .value_parser(ValueParser::new(parse_lag))
+                .default_value("drop")
+                .help("Whether the video skips the frames it is late on, or shows them all and slows down the soundtrack to catch up, skipping frames past half a second late"),
+        )
+        .arg(
+            Arg::new("low-power")
+                .long("low-power")
+                .action(ArgAction::SetTrue)
+                .help("Halve the particles and the simulation rate, as on battery"),
+        )
+        .arg(
+            Arg::new("no-low-power")
+                .long("no-low-power")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("low-power")
+                .help("Keep every particle and effect on battery too"),
+        )
+        .arg(
+            Arg::new("gpu")
+                .long("gpu")
+                .action(ArgAction::SetTrue)
+                .help("Simulate the particles on the GPU, for millions of them but only the jitter movement"),
+        )
+        .arg(
+            Arg::new("instanced")
+                .long("instanced")
+                .action(ArgAction::SetTrue)
+                .help("Draw the particles with instancing, in one draw call, for hundreds of thousands of them"),
+        )
+        .arg(
+            Arg::new("threshold")
+                .long("threshold")
+                .value_name("0-255")
+                .value_parser(value_parser!(u8))
+                .default_value(THRESHOLD.to_string())
+                .help("Brightest value of the pixels particles settle on"),
+        )
+        .arg(
+            Arg::new("luma")
+                .long("luma")
+                .action(ArgAction::SetTrue)
+                .help("Read color frames by their brightness rather than by their red channel"),
+        )
+        .arg(
+            Arg::new("dither")
+                .long("dither")
+                .action(ArgAction::SetTrue)
+                .help("Settle particles on gray pixels as densely as they are dark, rather than cutting them off at the threshold"),
+        )
+        .arg(
+            Arg::new("mode")
+                .long("mode")
+                .value_name("2d|3d")
+                .value_parser(ValueParser::new(parse_mode))
+                .default_value("2d")
+                .help("Draw the particles flat, or floating in front of the video seen from an orbiting camera"),
+        )
+        .arg(
+            Arg::new("relief")
+                .long("relief")
+                .value_name("PIXELS")
+                .value_parser(ValueParser::new(parse_radius))
+                .help("In 3D, raise the particles towards the camera by how dark the video is under them"),
+        )
+        .arg(
+            Arg::new("record")
+                .long("record")
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .help("Record the particles to a video or a GIF, going by the extension, with ffmpeg"),
+        )
+        .arg(
+            Arg::new("screenshots")
+                .long("screenshots")
+                .value_name("DIR")
+                .value_parser(value_parser!(PathBuf))
+                .default_value(SCREENSHOTS)
+                .help("Folder the screenshots taken with F12 are saved in"),
+        )
+        .arg(
+            Arg::new("supersample")
+                .long("supersample")
+                .value_name("FACTOR")
+                .value_parser(ValueParser::new(parse_supersample))
+                .default_value("1")
+                .help("Render recordings and screenshots at 2 to 4 times the size of the source, scaled back down for smoother edges"),
+        )
+        .arg(
+            Arg::new("metrics")
+                .long("metrics")
+                .value_name("CSV")
+                .value_parser(value_parser!(PathBuf))
+                .help("Append the moving particles, coverage and system times of every frame to a CSV file"),
+        )
+        .arg(
+            Arg::new("verify")
+                .long("verify")
+                .action(ArgAction::SetTrue)
+                .help("Check the simulation is deterministic and matches the golden images, without a window"),
+        )
+        .arg(
+            Arg::new("bless")
+                .long("bless")
+                .action(ArgAction::SetTrue)
+                .requires("verify")
+                .help("Record the golden images again"),
+        )
+        .arg(
+            Arg::new("headless")
+                .long("headless")
+                .value_name("FRAMES")
+                .value_parser(value_parser!(usize))
+                .conflicts_with("verify")
+                .help("Time the simulation over generated frames, without a window"),
+        )
+}
+
+impl Default for Config {
+    /// The original video, as with no options
+    fn default() -> Self {
+        Self::from_matches(&command().get_matches_from([env!("CARGO_PKG_NAME")]), None)
+    }
+}
+
+/// Value of an option given on the command line, else from `config.ron`, else its default
+fn pick<T: Clone + Send + Sync + 'static>(matches: &ArgMatches, id: &str, file: Option<T>) -> T {
+    match file {
+        Some(value) if matches.value_source(id) != Some(ValueSource::CommandLine) => value,
+        _ => matches.get_one::<T>(id).cloned().expect("has a default"),
+    }
+}
+
+impl Config {
+    /// Reads the command line and `config.ron`, exiting with the usage on invalid options
+    pub fn parse() -> Self {
+        let file = ConfigFile::read().unwrap_or_default();
+        Self::from_matches(&command().get_matches(), Some(file))
+    }
+
+    /// Reads `args`, starting with the program's name, leaving out `config.ron`
+    pub fn from_args(args: impl IntoIterator<Item = impl Into<OsString> + Clone>) -> Self {
+        Self::from_matches(&command().get_matches_from(args), None)
+    }
+
+    fn from_matches(matches: &ArgMatches, file: Option<ConfigFile>) -> Self {
+        let settings = file.clone().unwrap_or_default();
+
+        // a folder given on the command line or in the file is relative to the working directory
+        let dir = matches
+            .get_one::<PathBuf>("frames")
+            .or(settings.frames.as_ref());
+        let (frames_dir, frames) = match dir {
+            Some(dir) => {
+                let dir = std::env::current_dir()
+                    .map(|cwd| cwd.join(dir))
+                    .unwrap_or(dir.clone());
+                let frames = count_frames(&dir);
+                (dir, frames)
+            }
+            None => (PathBuf::from(FRAMES_DIR), FRAMES),
+        };
+
+        Self {
+            frames_dir,
+            frames,
+            webcam: matches.get_one("webcam").cloned(),
+            timecode: matches.get_one("timecode").cloned(),
+            lang: matches.get_one("lang").cloned(),
+            audio: pick(matches, "audio", settings.audio),
+            subtitles: matches
+                .get_one::<PathBuf>("subtitles")
+                .expect("has a default")
+                .clone(),
+            fps: pick(matches, "fps", settings.fps),
+            speed: pick(matches, "speed", settings.speed),
+            particles: pick(matches, "particles", settings.particles),
+            size: pick(
+                matches,
+                "size",
+                settings
+                    .size
+                    .map(|(width, height)| UVec2::new(width, height)),
+            ),
+            fullscreen: matches.get_flag("fullscreen"),
+            present_mode: pick(matches, "present-mode", settings.present_mode),
+            max_fps: matches.get_one("max-fps").copied().or(settings.max_fps),
+            buffer_mb: pick(matches, "buffer-mb", settings.buffer_mb),
+            history: pick(matches, "history", settings.history),
+            low_power: if matches.get_flag("low-power") {
+                Some(true)
+            } else if matches.get_flag("no-low-power") {
+                Some(false)
+            } else {
+                settings.low_power
+            },
+            gpu: matches.get_flag("gpu"),
+            instanced: matches.get_flag("instanced"),
+            threshold: *matches.get_one("threshold").expect("has a default"),
+            luma: matches.get_flag("luma"),
+            dither: matches.get_flag("dither"),
+            mode: *matches.get_one("mode").expect("has a default"),
+            relief: matches.get_one("relief").copied(),
+            mouse_radius: pick(matches, "mouse-radius", settings.mouse_radius),
+            sync_tolerance: *matches.get_one("sync-tolerance").expect("has a default"),
+            catch_up: *matches.get_one("catch-up").expect("has a default"),
+            lag: *matches.get_one("lag").expect("has a default"),
+            record: matches.get_one("record").cloned(),
+            screenshots: matches
+                .get_one::<PathBuf>("screenshots")
+                .expect("has a default")
+                .clone(),
+            supersample: *matches.get_one("supersample").expect("has a default"),
+            metrics: matches.get_one("metrics").cloned(),
+            verify: matches.get_flag("verify"),
+            bless: matches.get_flag("bless"),
+            headless: matches.get_one("headless").copied(),
+            seed: matches.get_one("seed").copied(),
+            file,
+        }
+    }
+
+    /// Plays the PNGs of another folder, relative to the assets folder or absolute
+    pub fn set_frames_dir(&mut self, dir: PathBuf) {
+        self.frames = count_frames(&assets_dir().join(&dir));
+        self.frames_dir = dir;
+    }
+
+    /// Path of a PNG frame for the asset server, numbered from 1
+    pub fn frame_path(&self, idx: usize) -> PathBuf {
+        self.frames_dir.join(format!("out{idx:04}.png"))
+    }
+}
+
+/// The assets folder on disk
+#[cfg(not(target_arch = "wasm32"))]
+pub fn assets_dir() -> PathBuf {
+    FileAssetIo::get_base_path().join("assets")
+}
+
+/// The assets folder next to the page, which can't be listed from the browser
+#[cfg(target_arch = "wasm32")]
+pub fn assets_dir() -> PathBuf {
+    PathBuf::from("assets")
+}
+
+/// Number of `out*.png` frames in a folder
+fn count_frames(dir: &PathBuf) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("out") && name.ends_with(".png")
+        })
+        .count()
+}
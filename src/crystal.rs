@@ -0,0 +1,281 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locale::{tr, tr_with},
+    sim::{FrameSampler, FrameView, Playfield},
+    texture::{ParticleSize, ParticleTexture, ShapeAtlas},
+    threshold::Threshold,
+    toast::Toasts,
+    CurrentFrame, Particle,
+};
+
+/// Arrangement crystallized particles snap to
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Lattice {
+    Square,
+    /// Rows offset by half a cell, packing particles like a honeycomb
+    Hexagonal,
+}
+
+/// Particles settled for long enough snap to a lattice and grow, so static regions
+/// look crystallized while moving edges stay loose
+#[derive(Resource, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Crystallization {
+    /// `None` to leave particles where they settled
+    pub lattice: Option<Lattice>,
+    /// Seconds a particle stays settled before crystallizing
+    pub delay: f32,
+    /// Size of a crystallized particle relative to a loose one
+    pub scale: f32,
+}
+
+impl Default for Crystallization {
+    fn default() -> Self {
+        Self {
+            lattice: None,
+            delay: 3.0,
+            scale: 1.5,
+        }
+    }
+}
+
+impl Lattice {
+    /// Angle the lattice looks the same again after turning by
+    fn symmetry(self) -> f32 {
+        match self {
+            Lattice::Square => PI / 2.0,
+            Lattice::Hexagonal => PI / 3.0,
+        }
+    }
+
+    /// Lattice point closest to `pos`, cells being `spacing` pixels apart
+    fn snap(self, pos: Vec2, spacing: f32) -> Vec2 {
+        match self {
+            Lattice::Square => (pos / spacing).round() * spacing,
+            Lattice::Hexagonal => {
+                let row_height = spacing * 3f32.sqrt() / 2.0;
+                let row = (pos.y / row_height).round();
+                let offset = if row as i32 % 2 == 0 { 0.0 } else { 0.5 };
+                let col = (pos.x / spacing - offset).round() + offset;
+                Vec2::new(col * spacing, row * row_height)
+            }
+        }
+    }
+}
+
+/// Pixels across the neighborhoods growing a lattice of their own, in lattice spacings
+const GRAIN_SPACINGS: f32 = 8.0;
+
+/// Lattice of a neighborhood, laid out from the particles settled in it when its first one
+/// crystallizes, so neighboring regions meet at grain boundaries like a real crystal
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Grain {
+    /// Lattice point at a settled particle
+    origin: Vec2,
+    /// Turn of the lattice, lining a row up with that particle's nearest neighbor
+    angle: f32,
+    /// Crystals formed on it, it goes once they have all dissolved
+    members: usize,
+}
+
+impl Grain {
+    /// Grain through the settled particle closest to `center`, turned towards its nearest
+    /// neighbor among `settled`
+    fn seed(settled: &[Vec2], center: Vec2, lattice: Lattice) -> Option<Self> {
+        let nearest = |to: Vec2, among: &mut dyn Iterator<Item = Vec2>| {
+            among.min_by(|a, b| a.distance_squared(to).total_cmp(&b.distance_squared(to)))
+        };
+        let origin = nearest(center, &mut settled.iter().copied())?;
+        let angle = nearest(
+            origin,
+            &mut settled.iter().copied().filter(|&pos| pos != origin),
+        )
+        .map_or(0.0, |neighbor| {
+            let offset = neighbor - origin;
+            offset.y.atan2(offset.x).rem_euclid(lattice.symmetry())
+        });
+        Some(Self {
+            origin,
+            angle,
+            members: 0,
+        })
+    }
+
+    fn snap(&self, pos: Vec2, lattice: Lattice, spacing: f32) -> Vec2 {
+        let turn = Vec2::from_angle(self.angle);
+        let local = Vec2::from_angle(-self.angle).rotate(pos - self.origin);
+        self.origin + turn.rotate(lattice.snap(local, spacing))
+    }
+}
+
+/// Neighborhood of `pos`, `size` pixels across
+fn grain_cell(pos: Vec2, size: f32) -> IVec2 {
+    (pos / size).floor().as_ivec2()
+}
+
+#[derive(Component, Default)]
+pub struct Crystal {
+    /// Seconds the particle has been settled for
+    settled: f32,
+    /// Neighborhood whose lattice it snapped to, once formed
+    grain: Option<IVec2>,
+}
+
+impl Crystal {
+    fn formed(&self) -> bool {
+        self.grain.is_some()
+    }
+}
+
+pub fn cycle_lattice(
+    mut crystallization: ResMut<Crystallization>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::K) {
+        crystallization.lattice = match crystallization.lattice {
+            None => Some(Lattice::Square),
+            Some(Lattice::Square) => Some(Lattice::Hexagonal),
+            Some(Lattice::Hexagonal) => None,
+        };
+        toasts.push(match crystallization.lattice {
+            Some(lattice) => tr_with(
+                "crystal",
+                &[
+                    ("lattice", &format!("{lattice:?}")),
+                    ("delay", &crystallization.delay),
+                ],
+            ),
+            None => tr("crystal-off"),
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn crystallize(
+    mut particles: Query<(
+        &mut Transform,
+        &mut Sprite,
+        &mut Crystal,
+        &Particle,
+        &ParticleSize,
+    )>,
+    crystallization: Res<Crystallization>,
+    texture: Res<ParticleTexture>,
+    atlas: Res<ShapeAtlas>,
+    current: Res<CurrentFrame>,
+    images: Res<Assets<Image>>,
+    playfield: Res<Playfield>,
+    threshold: Res<Threshold>,
+    time: Res<Time>,
+    mut grains: Local<HashMap<IVec2, Grain>>,
+) {
+    // laid out again for a different spacing
+    if crystallization.is_changed() || texture.is_changed() || atlas.is_changed() {
+        grains.clear();
+    }
+    // dissolve everything when turned off
+    let Some(lattice) = crystallization.lattice else {
+        if crystallization.is_changed() {
+            for (_, mut sprite, mut crystal, _, size) in &mut particles {
+                *crystal = Crystal::default();
+                sprite.custom_size = Some(size.0);
+            }
+        }
+        return;
+    };
+    let Some(image) = images.get(&current.front.image) else {
+        return;
+    };
+    if image.size().as_uvec2() != playfield.size {
+        return;
+    }
+    let sampler = FrameSampler::new(threshold.apply(FrameView::from_image(image)), *playfield);
+    // neighbors touch once grown
+    let spacing = texture.size(&atlas).max_element() * crystallization.scale;
+    let grain_size = spacing * GRAIN_SPACINGS;
+    // neighborhoods without a lattice yet, laid out after looking at all their particles
+    let mut unseeded = HashSet::new();
+
+    for (mut transform, mut sprite, mut crystal, standstill, size) in &mut particles {
+        if standstill.0 != current.front.index {
+            if let Some(cell) = crystal.grain {
+                sprite.custom_size = Some(size.0);
+                if let Some(grain) = grains.get_mut(&cell) {
+                    grain.members -= 1;
+                    if grain.members == 0 {
+                        grains.remove(&cell);
+                    }
+                }
+            }
+            *crystal = Crystal::default();
+            continue;
+        }
+
+        crystal.settled += time.delta_seconds();
+        if crystal.formed() || crystal.settled < crystallization.delay {
+            continue;
+        }
+
+        let pos = transform.translation.truncate();
+        let cell = grain_cell(pos, grain_size);
+        let Some(grain) = grains.get_mut(&cell) else {
+            // crystallizes on the next update
+            unseeded.insert(cell);
+            continue;
+        };
+        let snapped = grain.snap(pos, lattice, spacing);
+        // stay in place rather than snapping off the silhouette
+        if sampler.is_dark_at(snapped) {
+            transform.translation = snapped.extend(transform.translation.z);
+        }
+        sprite.custom_size = Some(size.0 * crystallization.scale);
+        grain.members += 1;
+        crystal.grain = Some(cell);
+    }
+
+    if unseeded.is_empty() {
+        return;
+    }
+    let mut settled: HashMap<IVec2, Vec<Vec2>> = HashMap::new();
+    for (transform, _, _, standstill, _) in &particles {
+        let pos = transform.translation.truncate();
+        let cell = grain_cell(pos, grain_size);
+        if standstill.0 == current.front.index && unseeded.contains(&cell) {
+            settled.entry(cell).or_default().push(pos);
+        }
+    }
+    for (cell, settled) in settled {
+        let center = (cell.as_vec2() + 0.5) * grain_size;
+        if let Some(grain) = Grain::seed(&settled, center, lattice) {
+            grains.insert(cell, grain);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grain_follows_its_neighborhood() {
+        // a row of particles 2px apart, turned by 100 degrees
+        let along = Vec2::from_angle(100f32.to_radians());
+        let offset = Vec2::new(0.3, 0.7);
+        let settled: Vec<Vec2> = (0..5).map(|i| offset + along * 2.0 * i as f32).collect();
+
+        let grain = Grain::seed(&settled, offset, Lattice::Square).unwrap();
+        // the same square lattice as one turned by 10 degrees
+        assert!((grain.angle - 10f32.to_radians()).abs() < 1e-4);
+        for &pos in &settled {
+            assert!(grain.snap(pos, Lattice::Square, 2.0).distance(pos) < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+
+use crate::{sim::FrameView, CurrentFrame};
+
+/// Fraction of pixels that must change between two frames for it to count as a scene cut
+const CUT_THRESHOLD: f32 = 0.3;
+/// Extra jitter right after a cut, as a multiple of the normal amplitude
+const BOOST: f32 = 2.0;
+/// Fraction of the boost kept on each following frame
+const BOOST_DECAY: f32 = 0.9;
+
+/// Tracks hard cuts in the video, so particles scatter to the new shape quickly
+/// instead of smearing over from the previous one
+#[derive(Resource, Default)]
+pub struct SceneCut {
+    previous: Handle<Image>,
+    index: usize,
+    pub boost: f32,
+}
+
+impl SceneCut {
+    /// Multiplier on the jitter amplitude
+    pub fn jitter_scale(&self) -> f32 {
+        1.0 + self.boost
+    }
+
+    /// Whether the current frame is the first one after a cut
+    pub fn just_cut(&self) -> bool {
+        self.boost == BOOST
+    }
+}
+
+pub fn detect_cut(
+    mut cut: ResMut<SceneCut>,
+    current: Res<CurrentFrame>,
+    images: Res<Assets<Image>>,
+) {
+    if cut.index == current.front.index {
+        return;
+    }
+
+    cut.index = current.front.index;
+    cut.boost *= BOOST_DECAY;
+
+    if let (Some(previous), Some(image)) =
+        (images.get(&cut.previous), images.get(&current.front.image))
+    {
+        if previous.size() == image.size() {
+            let difference =
+                FrameView::from_image(image).difference(&FrameView::from_image(previous));
+            if difference > CUT_THRESHOLD {
+                debug!(
+                    "scene cut at frame {} ({:.0}% changed)",
+                    cut.index,
+                    difference * 100.0
+                );
+                cut.boost = BOOST;
+            }
+        }
+    }
+
+    cut.previous = current.front.image.clone();
+}
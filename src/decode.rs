@@ -0,0 +1,163 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use bevy::{
+    asset::AssetIo,
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::{CompressedImageFormats, ImageType},
+    },
+    utils::Instant,
+};
+use futures_lite::future;
+
+use crate::{
+    config::Config,
+    job::Job,
+    pack::{self, Metadata, Pack},
+    video::{find_video, has_ffmpeg},
+};
+
+/// Where the frames of the video are read from
+#[derive(Resource, Clone)]
+pub enum FrameStore {
+    /// One PNG per frame in the frames folder, as extracted by ffmpeg
+    Png,
+    /// The archive written by the `pack` binary
+    Packed(Arc<Pack>),
+    /// Decoded from the video file as it plays, see [`crate::video`]
+    Video(PathBuf),
+    /// Captured live from the webcam with `--webcam`, and never ending
+    Camera(String),
+}
+
+impl FrameStore {
+    /// Prefers the webcam when asked for, then the archive, then the PNGs, then the video.
+    /// `None` when there is nothing to play.
+    pub fn open(io: &dyn AssetIo, config: &Config) -> Option<Self> {
+        if let Some(device) = &config.webcam {
+            if has_ffmpeg() {
+                return Some(FrameStore::Camera(device.clone()));
+            }
+            warn!("can't capture {device} without ffmpeg, install it to use the webcam");
+        }
+
+        // the browser can't tell whether a file exists without fetching it,
+        // and the archive would have to be downloaded whole before playing
+        if cfg!(target_arch = "wasm32") {
+            return Some(FrameStore::Png);
+        }
+
+        if io.is_file(Path::new(pack::METADATA)) {
+            match read_pack(io) {
+                Ok(pack) => return Some(FrameStore::Packed(Arc::new(pack))),
+                Err(e) => warn!("couldn't open {}: {e}", pack::ARCHIVE),
+            }
+        }
+
+        if io.is_file(&config.frame_path(1)) {
+            return Some(FrameStore::Png);
+        }
+        find_video().map(FrameStore::Video)
+    }
+
+    /// Frames in the archive, the PNGs are expected to cover the whole video
+    pub fn frames(&self) -> Option<usize> {
+        match self {
+            FrameStore::Png | FrameStore::Video(_) => None,
+            FrameStore::Packed(pack) => Some(pack.metadata.frames()),
+            FrameStore::Camera(_) => Some(usize::MAX),
+        }
+    }
+}
+
+fn read_pack(io: &dyn AssetIo) -> Result<Pack, String> {
+    let load = |path| future::block_on(io.load_path(Path::new(path))).map_err(|e| e.to_string());
+
+    let metadata: Metadata =
+        ron::de::from_bytes(&load(pack::METADATA)?).map_err(|e| e.to_string())?;
+    Pack::new(metadata, load(pack::ARCHIVE)?)
+}
+
+/// Expands grayscale pixels to the RGBA of a PNG frame
+pub fn gray_image(gray: &[u8], size: UVec2) -> Image {
+    let data = gray
+        .iter()
+        .flat_map(|&value| [value, value, value, 255])
+        .collect();
+
+    Image::new(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+fn packed_image(pack: &Pack, idx: usize) -> Result<Image, String> {
+    let size = UVec2::new(pack.metadata.width, pack.metadata.height);
+    Ok(gray_image(&pack.frame(idx)?, size))
+}
+
+/// Decodes a PNG frame, the same way the asset server would
+pub fn decode_png(bytes: &[u8]) -> Result<Image, String> {
+    Image::from_buffer(
+        bytes,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+    )
+    .map_err(|e| e.to_string())
+}
+
+async fn read_png(server: &AssetServer, path: &Path) -> Option<Image> {
+    let bytes = match server.asset_io().load_path(path).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("couldn't read {}: {e}", path.display());
+            return None;
+        }
+    };
+
+    decode_png(&bytes)
+        .map_err(|e| warn!("couldn't decode {}: {e}", path.display()))
+        .ok()
+}
+
+/// Reads and decodes a frame of the video on the async compute pool, so the buffer fills
+/// up from several threads at once instead of one asset load at a time, along with how long
+/// that took. `None` when it couldn't be read.
+/// Frames are numbered from 1, like the extracted PNGs. The video streams on its own instead.
+pub fn spawn_decode(
+    store: &FrameStore,
+    server: &AssetServer,
+    config: &Config,
+    idx: usize,
+) -> Job<(Option<Image>, Duration)> {
+    if let FrameStore::Packed(pack) = store {
+        let pack = pack.clone();
+        return Job::spawn(async move {
+            let start = Instant::now();
+            let image = packed_image(&pack, idx - 1)
+                .map_err(|e| warn!("couldn't decode frame {idx}: {e}"))
+                .ok();
+            (image, start.elapsed())
+        });
+    }
+
+    let server = server.clone();
+    let path = config.frame_path(idx);
+    Job::spawn(async move {
+        let start = Instant::now();
+        let image = read_png(&server, &path).await;
+        (image, start.elapsed())
+    })
+}
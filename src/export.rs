@@ -0,0 +1,87 @@
+use std::{
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+use crate::{locale::tr_with, toast::Toasts, CurrentFrame, Particle};
+
+const DIR: &str = "exports";
+
+/// Position and color of a settled particle
+struct Point {
+    pos: Vec3,
+    color: [u8; 3],
+}
+
+fn write_ply(path: &Path, points: &[Point]) -> io::Result<()> {
+    let mut text = String::new();
+    let _ = write!(
+        text,
+        "ply\nformat ascii 1.0\nelement vertex {}\n\
+         property float x\nproperty float y\nproperty float z\n\
+         property uchar red\nproperty uchar green\nproperty uchar blue\nend_header\n",
+        points.len()
+    );
+    for Point {
+        pos,
+        color: [r, g, b],
+    } in points
+    {
+        let _ = writeln!(text, "{} {} {} {r} {g} {b}", pos.x, pos.y, pos.z);
+    }
+    fs::write(path, text)
+}
+
+/// Points as vertices only, OBJ has no standard way to store a point cloud
+fn write_obj(path: &Path, points: &[Point]) -> io::Result<()> {
+    let mut text = String::new();
+    for Point { pos, .. } in points {
+        let _ = writeln!(text, "v {} {} {}", pos.x, pos.y, pos.z);
+    }
+    fs::write(path, text)
+}
+
+fn export(frame: usize, points: &[Point]) -> io::Result<PathBuf> {
+    fs::create_dir_all(DIR)?;
+
+    let path = Path::new(DIR).join(format!("particles_{frame:04}"));
+    write_ply(&path.with_extension("ply"), points)?;
+    write_obj(&path.with_extension("obj"), points)?;
+    Ok(path)
+}
+
+/// Saves the particles settled on the current frame as a point cloud
+pub fn export_particles(
+    particles: Query<(&Particle, &Transform, &Sprite)>,
+    current: Res<CurrentFrame>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if !keyboard.just_released(KeyCode::E) {
+        return;
+    }
+
+    let points: Vec<_> = particles
+        .iter()
+        .filter(|(standstill, ..)| standstill.0 == current.front.index)
+        .map(|(_, transform, sprite)| {
+            let [r, g, b, _] = sprite.color.as_rgba_f32();
+            Point {
+                // drawing layers become depth
+                pos: transform.translation,
+                color: [r, g, b].map(|c| (c * 255.0).round() as u8),
+            }
+        })
+        .collect();
+
+    match export(current.front.index, &points) {
+        Ok(path) => toasts.push(tr_with(
+            "exported",
+            &[("count", &points.len()), ("path", &path.display())],
+        )),
+        Err(e) => toasts.push(tr_with("export-failed", &[("error", &e)])),
+    }
+}
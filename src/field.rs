@@ -0,0 +1,97 @@
+//! Distance fields of the buffered frames, computed on the async compute pool as they are
+//! loaded so that showing a frame doesn't wait on one
+
+use std::iter;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    job::Job,
+    sim::{DistanceField, FrameView},
+    threshold::Threshold,
+    CurrentFrame, Player,
+};
+
+/// Threshold, inversion, luma and dithering a field was computed with
+type Settings = (u8, bool, bool, bool);
+
+/// Fields of the shown and buffered frames, by image
+#[derive(Resource, Default)]
+pub struct FieldCache {
+    fields: HashMap<Handle<Image>, (Settings, DistanceField)>,
+    pending: HashMap<Handle<Image>, (Settings, Job<DistanceField>)>,
+}
+
+impl FieldCache {
+    pub fn get(&self, image: &Handle<Image>) -> Option<&DistanceField> {
+        self.fields.get(image).map(|(_, field)| field)
+    }
+}
+
+/// Starts computing the fields of new frames, collects the finished ones and drops
+/// those of frames that were shown or dropped from the buffer.
+/// Inverting, or changing how pixels are read, starts over. A new threshold value alone,
+/// which comes with every frame with the adaptive threshold, only recomputes the shown
+/// frame's field, keeping the previous one until it is done.
+pub fn compute_fields(
+    mut cache: ResMut<FieldCache>,
+    player: Query<&Player>,
+    current: Res<CurrentFrame>,
+    images: Res<Assets<Image>>,
+    threshold: Res<Threshold>,
+) {
+    let FieldCache { fields, pending } = &mut *cache;
+    let settings = (
+        threshold.value,
+        threshold.inverted,
+        threshold.luma,
+        threshold.dithered,
+    );
+    let same_reading = |computed: &Settings| {
+        (computed.1, computed.2, computed.3) == (settings.1, settings.2, settings.3)
+    };
+    fields.retain(|_, (computed, _)| same_reading(computed));
+    pending.retain(|_, (computed, _)| same_reading(computed));
+
+    let player = player.single();
+    let live: Vec<_> = iter::once(&current.front.image)
+        .chain(&player.buffer)
+        .collect();
+    fields.retain(|image, _| live.contains(&image));
+    pending.retain(|image, _| live.contains(&image));
+
+    for &handle in &live {
+        let stale = handle == &current.front.image
+            && fields
+                .get(handle)
+                .is_some_and(|(computed, _)| *computed != settings);
+        if pending.contains_key(handle) || fields.contains_key(handle) && !stale {
+            continue;
+        }
+        let Some(image) = images.get(handle) else {
+            continue;
+        };
+
+        let data = image.data.clone();
+        let size = image.size().as_uvec2();
+        let block_size = image.texture_descriptor.format.describe().block_size as usize;
+        let (value, inverted, luma, dithered) = settings;
+        let task = Job::spawn(async move {
+            let frame = FrameView::new(&data, size, block_size)
+                .with_threshold(value)
+                .inverted(inverted)
+                .with_luma(luma)
+                .dithered(dithered);
+            DistanceField::new(&frame)
+        });
+        pending.insert(handle.clone(), (settings, task));
+    }
+
+    pending.retain(|image, (computed, task)| match task.poll() {
+        Some(field) => {
+            fields.insert(image.clone(), (*computed, field));
+            false
+        }
+        None => true,
+    });
+}
@@ -0,0 +1,223 @@
+//! Optical flow between consecutive frames, estimated by matching blocks of the previous
+//! frame in the next one, which drags particles along with the motion of the silhouette.
+//! Frames are matched on the async compute pool as they are buffered.
+
+use std::iter;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    job::Job,
+    locale::tr,
+    sim::{FrameView, Playfield},
+    threshold::Threshold,
+    toast::Toasts,
+    CurrentFrame, Particle, Player,
+};
+
+/// Side of the blocks matched between frames, in pixels
+const BLOCK: u32 = 16;
+/// Furthest a block is looked for in the next frame, in pixels on each axis
+const SEARCH: i32 = 6;
+/// Spacing of the pixels compared within a block, to keep matching cheap
+const SAMPLE: usize = 2;
+
+/// Motion of every block of a frame from the one before it
+pub struct Flow {
+    /// Size of the frames, in pixels
+    size: UVec2,
+    /// Blocks across and down
+    blocks: UVec2,
+    /// Displacement of each block, in pixels
+    motion: Vec<IVec2>,
+}
+
+impl Flow {
+    /// Matches every block of `previous` in `current`
+    fn estimate(previous: &FrameView, current: &FrameView) -> Self {
+        let size = previous.size();
+        let blocks = UVec2::new(size.x.div_ceil(BLOCK), size.y.div_ceil(BLOCK));
+        let motion = (0..blocks.y)
+            .flat_map(|y| (0..blocks.x).map(move |x| UVec2::new(x, y) * BLOCK))
+            .map(|origin| block_motion(previous, current, origin))
+            .collect();
+        Self {
+            size,
+            blocks,
+            motion,
+        }
+    }
+
+    /// Displacement of the pixel under `pos` in world space
+    fn at(&self, pos: Vec2, playfield: &Playfield) -> Vec2 {
+        let idx = playfield.pixel_index(pos) as u32;
+        let block = UVec2::new(idx % playfield.size.x, idx / playfield.size.x) / BLOCK;
+        let motion = self.motion[(block.y * self.blocks.x + block.x) as usize];
+        // rows go down the frame but up the world
+        Vec2::new(motion.x as f32, -motion.y as f32)
+    }
+}
+
+/// Flows into the shown and buffered frames, by image, computed on the async compute pool
+/// as frames are buffered. Each is tagged with whether it was read as luma.
+#[derive(Resource, Default)]
+pub struct OpticalFlow {
+    pub enabled: bool,
+    index: usize,
+    flows: HashMap<Handle<Image>, (bool, Flow)>,
+    pending: HashMap<Handle<Image>, (bool, Job<Flow>)>,
+}
+
+/// Offset at which the block at `origin` of `previous` best matches `current`, the smallest
+/// one among equally good matches. Blocks of a single shade can't tell and don't move.
+fn block_motion(previous: &FrameView, current: &FrameView, origin: UVec2) -> IVec2 {
+    let size = previous.size().as_ivec2();
+    let end = (origin + BLOCK).min(previous.size());
+    let pixels: Vec<_> = (origin.y..end.y)
+        .step_by(SAMPLE)
+        .flat_map(|y| {
+            (origin.x..end.x)
+                .step_by(SAMPLE)
+                .map(move |x| IVec2::new(x as i32, y as i32))
+        })
+        .map(|pixel| {
+            let value = previous.value((pixel.y * size.x + pixel.x) as usize);
+            (pixel, value)
+        })
+        .collect();
+
+    let (lowest, highest) = pixels
+        .iter()
+        .fold((u8::MAX, u8::MIN), |(lo, hi), &(_, value)| {
+            (lo.min(value), hi.max(value))
+        });
+    if lowest == highest {
+        return IVec2::ZERO;
+    }
+
+    let cost = |offset: IVec2| -> Option<u32> {
+        pixels.iter().try_fold(0, |sum, &(pixel, value)| {
+            let to = pixel + offset;
+            if to.cmplt(IVec2::ZERO).any() || to.cmpge(size).any() {
+                return None;
+            }
+            let moved = current.value((to.y * size.x + to.x) as usize);
+            Some(sum + value.abs_diff(moved) as u32)
+        })
+    };
+
+    (-SEARCH..=SEARCH)
+        .flat_map(|y| (-SEARCH..=SEARCH).map(move |x| IVec2::new(x, y)))
+        .filter_map(|offset| Some((cost(offset)?, offset)))
+        .min_by_key(|&(cost, offset)| (cost, offset.dot(offset)))
+        .map_or(IVec2::ZERO, |(_, offset)| offset)
+}
+
+pub fn flow_enabled(flow: Res<OpticalFlow>) -> bool {
+    flow.enabled
+}
+
+pub fn toggle_flow(
+    mut flow: ResMut<OpticalFlow>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::F) {
+        flow.enabled = !flow.enabled;
+        if !flow.enabled {
+            flow.flows.clear();
+            flow.pending.clear();
+        }
+        toasts.push(if flow.enabled {
+            tr("flow-on")
+        } else {
+            tr("flow-off")
+        });
+    }
+}
+
+/// Starts estimating the flow into each buffered frame from the one before it, collects the
+/// finished ones and drops those of frames that were shown or dropped from the buffer
+pub fn compute_flows(
+    mut flow: ResMut<OpticalFlow>,
+    player: Query<&Player>,
+    current: Res<CurrentFrame>,
+    images: Res<Assets<Image>>,
+    threshold: Res<Threshold>,
+) {
+    let OpticalFlow { flows, pending, .. } = &mut *flow;
+    let luma = threshold.luma;
+    flows.retain(|_, (computed, _)| *computed == luma);
+    pending.retain(|_, (computed, _)| *computed == luma);
+
+    let player = player.single();
+    let live: Vec<_> = iter::once(&current.front.image)
+        .chain(&player.buffer)
+        .collect();
+    flows.retain(|image, _| live.contains(&image));
+    pending.retain(|image, _| live.contains(&image));
+
+    for pair in live.windows(2) {
+        let handle = pair[1];
+        if pending.contains_key(handle) || flows.contains_key(handle) {
+            continue;
+        }
+        let (Some(previous), Some(image)) = (images.get(pair[0]), images.get(handle)) else {
+            continue;
+        };
+        if previous.size() != image.size() {
+            continue;
+        }
+
+        let (previous, data) = (previous.data.clone(), image.data.clone());
+        let size = image.size().as_uvec2();
+        let block_size = image.texture_descriptor.format.describe().block_size as usize;
+        let task = Job::spawn(async move {
+            Flow::estimate(
+                &FrameView::new(&previous, size, block_size).with_luma(luma),
+                &FrameView::new(&data, size, block_size).with_luma(luma),
+            )
+        });
+        pending.insert(handle.clone(), (luma, task));
+    }
+
+    pending.retain(|image, (computed, task)| match task.poll() {
+        Some(motion) => {
+            flows.insert(image.clone(), (*computed, motion));
+            false
+        }
+        None => true,
+    });
+}
+
+/// Moves every particle by the motion under it when the next frame comes up, so the
+/// silhouette carries them along. Seeks, skipped frames and frames whose flow isn't
+/// ready yet don't move them.
+pub fn advect_particles(
+    mut flow: ResMut<OpticalFlow>,
+    mut particles: Query<&mut Transform, With<Particle>>,
+    current: Res<CurrentFrame>,
+    playfield: Res<Playfield>,
+) {
+    if flow.index == current.front.index {
+        return;
+    }
+    let follows = flow.index + 1 == current.front.index;
+    flow.index = current.front.index;
+    if !follows {
+        return;
+    }
+
+    let Some((_, motion)) = flow.flows.get(&current.front.image) else {
+        return;
+    };
+    if motion.size != playfield.size {
+        return;
+    }
+
+    for mut transform in &mut particles {
+        let shift = motion.at(transform.translation.truncate(), &playfield);
+        transform.translation += shift.extend(0.0);
+        playfield.wrap(&mut transform.translation);
+    }
+}
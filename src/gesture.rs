@@ -0,0 +1,40 @@
+//! Browsers keep pages silent until the user interacts with them, so on the web the
+//! soundtrack waits for a click, a tap or a key press before starting
+
+use bevy::prelude::*;
+
+use crate::{locale::tr, toast::Toasts};
+
+/// Whether the soundtrack may start, always on natively
+#[derive(Resource)]
+pub struct AudioUnlocked(pub bool);
+
+impl Default for AudioUnlocked {
+    fn default() -> Self {
+        Self(!cfg!(target_arch = "wasm32"))
+    }
+}
+
+pub fn audio_unlocked(unlocked: Res<AudioUnlocked>) -> bool {
+    unlocked.0
+}
+
+pub fn prompt_gesture(unlocked: Res<AudioUnlocked>, mut toasts: ResMut<Toasts>) {
+    if !unlocked.0 {
+        toasts.push(tr("sound-gesture"));
+    }
+}
+
+pub fn unlock_audio(
+    mut unlocked: ResMut<AudioUnlocked>,
+    keyboard: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+) {
+    if keyboard.get_just_pressed().next().is_some()
+        || mouse.get_just_pressed().next().is_some()
+        || touches.iter_just_pressed().next().is_some()
+    {
+        unlocked.0 = true;
+    }
+}
@@ -0,0 +1,652 @@
+//! Moves the particles in a compute shader, for far more of them than the CPU can handle.
+//!
+//! Particles live in a GPU buffer instead of being entities: every simulation tick they take a
+//! jitter step unless they are on the silhouette, and every frame they get splatted into a
+//! texture drawn over the video. The ticks of the fixed update schedule that went by since the
+//! last frame are all stepped before it is drawn, so the particles move as fast as on the CPU
+//! whatever the frame rate.
+//! Only the jitter movement and the palette apply to them, the other effects need the CPU path,
+//! which is used when the GPU can't run compute shaders.
+//! The shader counts settled particles and covered pixels as it goes, read back into
+//! [`SimMetrics`] a few frames late rather than stalling the GPU.
+
+use std::{
+    borrow::Cow,
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Mutex,
+    },
+};
+
+use bevy::{
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        main_graph::node::CAMERA_DRIVER,
+        render_asset::RenderAssets,
+        render_graph::{self, RenderGraph},
+        render_resource::*,
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        texture::ImageSampler,
+        RenderApp, RenderSet,
+    },
+};
+use rand::prelude::*;
+
+use crate::{
+    automation::Automation,
+    cut::SceneCut,
+    layering::BASE_Z,
+    palette::{ColorScheme, Gamma, Palette},
+    panel::Tuning,
+    sim::{Playfield, JITTER},
+    threshold::Threshold,
+    CurrentFrame, State,
+};
+use params::Params;
+
+const SHADER: &str = "shaders/gpu_sim.wgsl";
+const NODE: &str = "gpu_sim";
+const WORKGROUP_SIZE: u32 = 64;
+/// Side of the square workgroups writing the output texture
+const TILE_SIZE: u32 = 8;
+/// Counters of the shader, in the order of `SimMetrics`
+const COUNTERS: u64 = 4;
+
+/// Runs the simulation on the GPU when `enabled`, leaving the CPU path otherwise
+pub struct GpuSimPlugin {
+    pub enabled: bool,
+    pub particles: usize,
+    pub size: UVec2,
+    /// Seed of the spawn pattern and of the random walk
+    pub seed: u64,
+}
+
+impl Plugin for GpuSimPlugin {
+    fn build(&self, app: &mut App) {
+        if !self.enabled {
+            return;
+        }
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            warn!("no renderer, simulating on the CPU");
+            return;
+        };
+        if render_app
+            .world
+            .resource::<RenderDevice>()
+            .limits()
+            .max_compute_workgroups_per_dimension
+            == 0
+        {
+            warn!("compute shaders aren't supported, simulating on the CPU");
+            return;
+        }
+
+        let settings = Settings {
+            particles: self.particles as u32,
+            size: self.size,
+            seed: self.seed,
+        };
+        let (report, metrics) = mpsc::channel();
+        let readback = MetricsReadback::new(render_app.world.resource::<RenderDevice>(), report);
+        render_app
+            .insert_resource(settings)
+            .insert_resource(readback)
+            .init_resource::<GpuSimPipeline>()
+            .add_system(queue_bind_group.in_set(RenderSet::Queue))
+            .add_system(read_metrics.in_set(RenderSet::Cleanup));
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        graph.add_node(NODE, GpuSimNode);
+        graph.add_node_edge(NODE, CAMERA_DRIVER);
+
+        app.insert_resource(settings)
+            .init_resource::<SimMetrics>()
+            .insert_resource(MetricsReport(Mutex::new(metrics)))
+            .add_system(receive_metrics)
+            .add_plugin(ExtractResourcePlugin::<GpuSim>::default())
+            .add_startup_system(setup_gpu_sim.in_base_set(StartupSet::PreStartup))
+            .add_system(count_ticks.in_schedule(CoreSchedule::FixedUpdate))
+            .add_system(resize_gpu_sim)
+            .add_system(update_gpu_sim);
+    }
+}
+
+/// What the simulation starts with, its size then follows the playfield
+#[derive(Resource, Clone, Copy)]
+struct Settings {
+    particles: u32,
+    size: UVec2,
+    seed: u64,
+}
+
+/// State of the simulation shared with the render world, its presence means the
+/// CPU particles aren't spawned
+#[derive(Resource, Clone, ExtractResource)]
+pub struct GpuSim {
+    /// Where the particles are drawn
+    output: Handle<Image>,
+    /// Size of the playfield, the particles spawn again when it changes
+    size: UVec2,
+    frame: Handle<Image>,
+    running: bool,
+    /// Simulation ticks to step before the next draw
+    ticks: u32,
+    /// Ticks that went by since the last frame, while playing
+    pending: u32,
+    threshold: u8,
+    inverted: bool,
+    jitter: f32,
+    /// Changes every tick to get new random numbers, each step of a frame adding its own
+    seed: u32,
+    settled: Color,
+    moving: Color,
+}
+
+/// Sprite showing the particles
+#[derive(Component)]
+struct Splats;
+
+/// Counts of the last frame read back from the GPU, a few frames behind the one shown
+#[derive(Resource, Clone, Copy, Default, Debug)]
+pub struct SimMetrics {
+    /// Particles on the silhouette
+    pub settled: u32,
+    pub moving: u32,
+    /// Pixels of the silhouette
+    pub dark: u32,
+    /// Pixels of the silhouette with a particle on them
+    pub covered: u32,
+}
+
+impl SimMetrics {
+    pub fn particles(&self) -> u32 {
+        self.settled + self.moving
+    }
+
+    /// Share of the silhouette covered by particles
+    pub fn coverage(&self) -> f32 {
+        if self.dark == 0 {
+            return 0.0;
+        }
+        self.covered as f32 / self.dark as f32
+    }
+}
+
+/// Metrics sent by the render world as they are read back
+#[derive(Resource)]
+struct MetricsReport(Mutex<Receiver<SimMetrics>>);
+
+fn receive_metrics(mut metrics: ResMut<SimMetrics>, report: Res<MetricsReport>) {
+    if let Some(latest) = report.0.lock().unwrap().try_iter().last() {
+        *metrics = latest;
+    }
+}
+
+fn output_image(size: UVec2) -> Image {
+    let mut output = Image::new_fill(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8Unorm,
+    );
+    output.texture_descriptor.usage =
+        TextureUsages::COPY_DST | TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING;
+    // one particle per pixel, kept sharp
+    output.sampler_descriptor = ImageSampler::nearest();
+    output
+}
+
+fn setup_gpu_sim(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    settings: Res<Settings>,
+) {
+    let output = images.add(output_image(settings.size));
+
+    commands.spawn((
+        Splats,
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(settings.size.as_vec2()),
+                ..default()
+            },
+            texture: output.clone(),
+            transform: Transform::from_xyz(0.0, 0.0, BASE_Z),
+            ..default()
+        },
+    ));
+    commands.insert_resource(GpuSim {
+        output,
+        size: settings.size,
+        frame: Handle::default(),
+        running: false,
+        ticks: 0,
+        pending: 0,
+        threshold: 0,
+        inverted: false,
+        jitter: 0.0,
+        seed: settings.seed as u32,
+        settled: Color::NONE,
+        moving: Color::NONE,
+    });
+}
+
+/// Follows the playfield to a source of another size, the render world spawning the
+/// particles again over it
+fn resize_gpu_sim(
+    mut sim: ResMut<GpuSim>,
+    mut images: ResMut<Assets<Image>>,
+    mut splats: Query<&mut Sprite, With<Splats>>,
+    playfield: Res<Playfield>,
+) {
+    if sim.size == playfield.size {
+        return;
+    }
+    sim.size = playfield.size;
+    if let Some(output) = images.get_mut(&sim.output) {
+        *output = output_image(playfield.size);
+    }
+    splats.single_mut().custom_size = Some(playfield.size.as_vec2());
+}
+
+fn count_ticks(mut sim: ResMut<GpuSim>, state: Res<State>) {
+    if matches!(*state, State::Playing) {
+        sim.pending += 1;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_gpu_sim(
+    mut sim: ResMut<GpuSim>,
+    current: Res<CurrentFrame>,
+    state: Res<State>,
+    threshold: Res<Threshold>,
+    cut: Res<SceneCut>,
+    automation: Res<Automation>,
+    tuning: Res<Tuning>,
+    palette: Res<Palette>,
+    scheme: Res<ColorScheme>,
+    gamma: Res<Gamma>,
+) {
+    sim.frame = current.front.image.clone();
+    sim.running = matches!(*state, State::Playing);
+    sim.threshold = threshold.value;
+    sim.inverted = threshold.inverted;
+    sim.jitter = JITTER * cut.jitter_scale() * automation.jitter * tuning.jitter;
+    sim.seed = sim.seed.wrapping_add(sim.ticks);
+    sim.ticks = std::mem::take(&mut sim.pending);
+    // only the ends of multi-stop gradients, particles don't age on the GPU
+    sim.settled = gamma.apply(palette.color(0.0, &scheme));
+    sim.moving = gamma.apply(palette.color(1.0, &scheme));
+}
+
+mod params {
+    // the derive generates layout checks that are never called
+    #![allow(dead_code)]
+
+    use bevy::{prelude::*, render::render_resource::ShaderType};
+
+    /// Mirror of `Params` in the shader
+    #[derive(ShaderType, Default)]
+    pub struct Params {
+        pub size: UVec2,
+        pub count: u32,
+        pub threshold: f32,
+        pub inverted: u32,
+        pub jitter: f32,
+        pub seed: u32,
+        pub steps: u32,
+        pub settled: Vec4,
+        pub moving: Vec4,
+    }
+}
+
+#[derive(Resource)]
+struct GpuSimPipeline {
+    layout: BindGroupLayout,
+    clear: CachedComputePipelineId,
+    step: CachedComputePipelineId,
+    resolve: CachedComputePipelineId,
+    params: UniformBuffer<Params>,
+    /// Size the buffers were made for
+    size: UVec2,
+    particles: Buffer,
+    density: Buffer,
+    counters: Buffer,
+}
+
+impl GpuSimPipeline {
+    /// Spawns the particles over a playfield of `size`, the same pattern on every launch,
+    /// in pixels from the top left
+    fn create_buffers(device: &RenderDevice, settings: &Settings, size: UVec2) -> (Buffer, Buffer) {
+        let mut rng = StdRng::seed_from_u64(settings.seed);
+        let extent = size.as_vec2();
+        let positions: Vec<u8> = (0..settings.particles)
+            .flat_map(|_| [rng.gen_range(0.0..extent.x), rng.gen_range(0.0..extent.y)])
+            .flat_map(f32::to_ne_bytes)
+            .collect();
+        let particles = device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("gpu_sim_particles"),
+            contents: &positions,
+            usage: BufferUsages::STORAGE,
+        });
+        let density = device.create_buffer(&BufferDescriptor {
+            label: Some("gpu_sim_density"),
+            size: size.x as u64 * size.y as u64 * 2 * 4,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        (particles, density)
+    }
+}
+
+impl FromWorld for GpuSimPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let settings = *world.resource::<Settings>();
+        let device = world.resource::<RenderDevice>();
+
+        let storage = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("gpu_sim_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(Params::min_size()),
+                    },
+                    count: None,
+                },
+                storage(1),
+                storage(2),
+                storage(5),
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::StorageTexture {
+                        access: StorageTextureAccess::WriteOnly,
+                        format: TextureFormat::Rgba8Unorm,
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let (particles, density) = Self::create_buffers(device, &settings, settings.size);
+        let counters = device.create_buffer(&BufferDescriptor {
+            label: Some("gpu_sim_counters"),
+            size: COUNTERS * 4,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let shader = world.resource::<AssetServer>().load(SHADER);
+        let cache = world.resource::<PipelineCache>();
+        let queue = |entry_point: &'static str| {
+            cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some(Cow::from(entry_point)),
+                layout: vec![layout.clone()],
+                push_constant_ranges: Vec::new(),
+                shader: shader.clone(),
+                shader_defs: vec![],
+                entry_point: Cow::from(entry_point),
+            })
+        };
+
+        Self {
+            clear: queue("clear"),
+            step: queue("step"),
+            resolve: queue("resolve"),
+            layout,
+            params: UniformBuffer::default(),
+            size: settings.size,
+            particles,
+            density,
+            counters,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct GpuSimBindGroup(BindGroup);
+
+fn queue_bind_group(
+    mut commands: Commands,
+    mut pipeline: ResMut<GpuSimPipeline>,
+    sim: Res<GpuSim>,
+    settings: Res<Settings>,
+    images: Res<RenderAssets<Image>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    // nothing to step on until the frame is on the GPU
+    let (Some(frame), Some(output)) = (images.get(&sim.frame), images.get(&sim.output)) else {
+        commands.remove_resource::<GpuSimBindGroup>();
+        return;
+    };
+    // the playfield follows the frame on the next update
+    if !sim.running || frame.size.as_uvec2() != sim.size {
+        commands.remove_resource::<GpuSimBindGroup>();
+        return;
+    }
+    if pipeline.size != sim.size {
+        info!(
+            "spawning the GPU particles again over {}x{}",
+            sim.size.x, sim.size.y
+        );
+        (pipeline.particles, pipeline.density) =
+            GpuSimPipeline::create_buffers(&device, &settings, sim.size);
+        pipeline.size = sim.size;
+    }
+
+    // sRGB frames read back linear
+    let threshold = sim.threshold as f32 / u8::MAX as f32;
+    let threshold = if frame.texture_format.describe().srgb {
+        Color::rgb(threshold, 0.0, 0.0).as_linear_rgba_f32()[0]
+    } else {
+        threshold
+    };
+    pipeline.params.set(Params {
+        size: sim.size,
+        count: settings.particles,
+        threshold,
+        inverted: sim.inverted as u32,
+        jitter: sim.jitter,
+        seed: sim.seed,
+        steps: sim.ticks,
+        settled: Vec4::from(sim.settled.as_rgba_f32()),
+        moving: Vec4::from(sim.moving.as_rgba_f32()),
+    });
+    pipeline.params.write_buffer(&device, &queue);
+    let Some(params) = pipeline.params.binding() else {
+        return;
+    };
+
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("gpu_sim_bind_group"),
+        layout: &pipeline.layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: params,
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: pipeline.particles.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: pipeline.density.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: BindingResource::TextureView(&frame.texture_view),
+            },
+            BindGroupEntry {
+                binding: 4,
+                resource: BindingResource::TextureView(&output.texture_view),
+            },
+            BindGroupEntry {
+                binding: 5,
+                resource: pipeline.counters.as_entire_binding(),
+            },
+        ],
+    });
+    commands.insert_resource(GpuSimBindGroup(bind_group));
+}
+
+/// Steps the particles by the ticks since the last frame and draws them, before the cameras draw.
+/// Their counters are copied out unless the last copy is still being read.
+struct GpuSimNode;
+
+impl render_graph::Node for GpuSimNode {
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let Some(bind_group) = world.get_resource::<GpuSimBindGroup>() else {
+            return Ok(());
+        };
+        let pipeline = world.resource::<GpuSimPipeline>();
+        let settings = world.resource::<Settings>();
+        let cache = world.resource::<PipelineCache>();
+        // still compiling
+        let (Some(clear), Some(step), Some(resolve)) = (
+            cache.get_compute_pipeline(pipeline.clear),
+            cache.get_compute_pipeline(pipeline.step),
+            cache.get_compute_pipeline(pipeline.resolve),
+        ) else {
+            return Ok(());
+        };
+
+        let encoder = render_context.command_encoder();
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor { label: Some(NODE) });
+        pass.set_bind_group(0, &bind_group.0, &[]);
+
+        let size = pipeline.size;
+        let pixels = size.x * size.y;
+        pass.set_pipeline(clear);
+        pass.dispatch_workgroups((pixels * 2).div_ceil(WORKGROUP_SIZE), 1, 1);
+        pass.set_pipeline(step);
+        pass.dispatch_workgroups(settings.particles.div_ceil(WORKGROUP_SIZE), 1, 1);
+        pass.set_pipeline(resolve);
+        pass.dispatch_workgroups(size.x.div_ceil(TILE_SIZE), size.y.div_ceil(TILE_SIZE), 1);
+        drop(pass);
+
+        let readback = world.resource::<MetricsReadback>();
+        let mut state = readback.state.lock().unwrap();
+        if matches!(*state, ReadbackState::Idle) {
+            encoder.copy_buffer_to_buffer(&pipeline.counters, 0, &readback.buffer, 0, COUNTERS * 4);
+            *state = ReadbackState::Copied;
+        }
+
+        Ok(())
+    }
+}
+
+/// Where the counters are read back from
+enum ReadbackState {
+    /// Free for the next copy
+    Idle,
+    /// Copied this frame, to be mapped once submitted
+    Copied,
+    /// Waiting for the buffer to be mapped
+    Mapping(Receiver<Result<(), wgpu::BufferAsyncError>>),
+}
+
+/// Buffer the counters are copied to, with the metrics going back to the main world
+#[derive(Resource)]
+struct MetricsReadback {
+    buffer: Buffer,
+    state: Mutex<ReadbackState>,
+    report: Sender<SimMetrics>,
+}
+
+impl MetricsReadback {
+    fn new(device: &RenderDevice, report: Sender<SimMetrics>) -> Self {
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("gpu_sim_metrics"),
+            size: COUNTERS * 4,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Self {
+            buffer,
+            state: Mutex::new(ReadbackState::Idle),
+            report,
+        }
+    }
+}
+
+/// Maps the counters copied this frame, and sends them on once they are, without waiting
+fn read_metrics(readback: Res<MetricsReadback>, device: Res<RenderDevice>) {
+    let mut state = readback.state.lock().unwrap();
+    match &*state {
+        ReadbackState::Idle => {}
+        ReadbackState::Copied => {
+            let (mapped, result) = mpsc::channel();
+            readback
+                .buffer
+                .slice(..)
+                .map_async(MapMode::Read, move |result| {
+                    let _ = mapped.send(result);
+                });
+            *state = ReadbackState::Mapping(result);
+        }
+        ReadbackState::Mapping(result) => {
+            device.poll(wgpu::Maintain::Poll);
+            match result.try_recv() {
+                Err(TryRecvError::Empty) => return,
+                Ok(Ok(())) => {
+                    let counts: Vec<u32> = readback
+                        .buffer
+                        .slice(..)
+                        .get_mapped_range()
+                        .chunks_exact(4)
+                        .map(|bytes| u32::from_ne_bytes(bytes.try_into().expect("4 bytes")))
+                        .collect();
+                    readback.buffer.unmap();
+                    // the main world goes away first when the app quits
+                    let _ = readback.report.send(SimMetrics {
+                        settled: counts[0],
+                        moving: counts[1],
+                        dark: counts[2],
+                        covered: counts[3],
+                    });
+                }
+                Ok(Err(e)) => warn!("couldn't read the GPU particle metrics back: {e}"),
+                // the callback was dropped without being called, the device was lost
+                Err(TryRecvError::Disconnected) => {}
+            }
+            *state = ReadbackState::Idle;
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+
+use crate::{
+    locale::tr,
+    sim::{FrameSampler, FrameView, Playfield, SimRng, Well},
+    spectrum::Spectrum,
+    threshold::Threshold,
+    toast::Toasts,
+    CurrentFrame, Particle,
+};
+
+/// How far above its recent average the bass has to jump to count as a beat
+const ONSET: f32 = 1.4;
+/// Quietest bass that can spawn a well
+const MIN_ENERGY: f32 = 0.4;
+/// Fraction of the running bass average made of the previous frames
+const SMOOTHING: f32 = 0.9;
+const MAX_WELLS: usize = 4;
+/// Fraction of its life a well keeps on each frame
+const DECAY: f32 = 0.93;
+
+struct Attractor {
+    well: Well,
+    /// Fades from 1 to 0, the strength is this times the bass energy
+    life: f32,
+}
+
+/// Wells spawned on bass beats, pulling loose particles between them
+#[derive(Resource)]
+pub struct Gravity {
+    pub enabled: bool,
+    /// How hard the wells pull compared to the particles' own movement
+    pub weight: f32,
+    index: usize,
+    average: f32,
+    attractors: Vec<Attractor>,
+}
+
+impl Default for Gravity {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            weight: 1.0,
+            index: 0,
+            average: 0.0,
+            attractors: Vec::new(),
+        }
+    }
+}
+
+pub fn gravity_enabled(gravity: Res<Gravity>) -> bool {
+    gravity.enabled
+}
+
+pub fn toggle_gravity(
+    mut gravity: ResMut<Gravity>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::G) {
+        gravity.enabled = !gravity.enabled;
+        gravity.attractors.clear();
+        toasts.push(if gravity.enabled {
+            tr("gravity-on")
+        } else {
+            tr("gravity-off")
+        });
+    }
+}
+
+/// Spawns wells on bass onsets and makes their strength follow the bass
+pub fn spawn_wells(
+    mut gravity: ResMut<Gravity>,
+    current: Res<CurrentFrame>,
+    spectrum: Res<Spectrum>,
+    playfield: Res<Playfield>,
+    mut sim_rng: ResMut<SimRng>,
+) {
+    if gravity.index == current.front.index {
+        return;
+    }
+    gravity.index = current.front.index;
+
+    let Some(bass) = spectrum.bass(current.front.index) else {
+        return;
+    };
+
+    for attractor in &mut gravity.attractors {
+        attractor.life *= DECAY;
+        attractor.well.strength = attractor.life * bass;
+    }
+    gravity.attractors.retain(|a| a.life > 0.05);
+
+    if bass > gravity.average * ONSET && bass > MIN_ENERGY && gravity.attractors.len() < MAX_WELLS {
+        let position = playfield.random_position(&mut sim_rng.serial());
+        gravity.attractors.push(Attractor {
+            well: Well {
+                position,
+                strength: bass,
+            },
+            life: 1.0,
+        });
+    }
+    gravity.average = gravity.average * SMOOTHING + bass * (1.0 - SMOOTHING);
+}
+
+/// Pulls the particles that are still looking for the silhouette towards the wells
+pub fn pull_particles(
+    mut particles: Query<&mut Transform, With<Particle>>,
+    gravity: Res<Gravity>,
+    images: Res<Assets<Image>>,
+    current: Res<CurrentFrame>,
+    playfield: Res<Playfield>,
+    threshold: Res<Threshold>,
+) {
+    if gravity.attractors.is_empty() {
+        return;
+    }
+    let Some(image) = images.get(&current.front.image) else {
+        return;
+    };
+    if image.size().as_uvec2() != playfield.size {
+        return;
+    }
+    let frame = threshold.apply(FrameView::from_image(image));
+    let sampler = FrameSampler::new(frame, *playfield);
+
+    for mut transform in &mut particles {
+        let pos = transform.translation.truncate();
+        if sampler.is_dark_at(pos) {
+            continue;
+        }
+
+        let pull: Vec2 = gravity.attractors.iter().map(|a| a.well.pull(pos)).sum();
+        transform.translation += (pull * gravity.weight).extend(0.0);
+        playfield.wrap(&mut transform.translation);
+    }
+}
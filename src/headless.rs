@@ -0,0 +1,214 @@
+//! `--headless` mode: plays generated frames without a window or a renderer, stepping the
+//! particles and the player as fast as possible, then prints how long the ticks took.
+//! Only the CPU path is timed, the compute shader needs a renderer.
+
+use std::{process::ExitCode, time::Duration};
+
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    time::TimeUpdateStrategy,
+    utils::Instant,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    automation::Automation,
+    behavior::Silhouette,
+    config::Config,
+    cut::SceneCut,
+    field::FieldCache,
+    interpolate::Interpolation,
+    layers::Layers,
+    metrics::{self, Timed},
+    move_particle,
+    panel::Tuning,
+    phase::{Breathing, Phase},
+    reactivity::AudioReactivity,
+    sim::{FrameView, MovementMode, Playfield, SimRng},
+    sim_rate::SimRate,
+    speed::PlaybackSpeed,
+    spotlight::Spotlight,
+    swap_frame,
+    sync::Lagging,
+    threshold::Threshold,
+    update_sprite,
+    verify::Frames,
+    CurrentFrame, Particle, Player, Velocity, SPAWN_SEED,
+};
+
+/// The generated frames, played in a loop
+#[derive(Resource)]
+struct Clip(Vec<Handle<Image>>);
+
+/// Keeps the player buffering the next frames of the clip, as if they loaded instantly
+fn buffer_clip(mut player: Query<&mut Player>, clip: Res<Clip>) {
+    let mut player = player.single_mut();
+    while player.buffer.len() < 2 {
+        let image = clip.0[player.load_index % clip.0.len()].clone();
+        player.buffer.push_back(image);
+        player.load_index += 1;
+    }
+}
+
+/// Mean, median, 95th percentile and worst of some durations, in milliseconds
+fn stats(name: &str, mut times: Vec<Duration>) {
+    if times.is_empty() {
+        return;
+    }
+    times.sort();
+    let ms = |time: Duration| time.as_secs_f64() * 1e3;
+    let mean = times.iter().sum::<Duration>() / times.len() as u32;
+    let percentile = |p: f64| ms(times[((times.len() - 1) as f64 * p) as usize]);
+    println!(
+        "{name:>14}: mean {:.3}ms, median {:.3}ms, p95 {:.3}ms, max {:.3}ms",
+        ms(mean),
+        percentile(0.5),
+        percentile(0.95),
+        percentile(1.0),
+    );
+}
+
+/// What a headless run came to
+pub struct Outcome {
+    /// Updates it took to show the last frame
+    pub ticks: usize,
+    /// Where the particles ended up
+    pub positions: Vec<Vec2>,
+    /// Share of the particles on the silhouette of the last frame
+    pub on_silhouette: f32,
+    /// Time every update took, and `move_particle` within it
+    tick_times: Vec<Duration>,
+    move_times: Vec<Duration>,
+}
+
+/// Plays `frames` generated frames with the particles of `config`, as fast as possible
+pub fn simulate(config: &Config, frames: usize) -> Outcome {
+    let clip = Frames::disc();
+    let period = SimRate::default().period();
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin::default())
+        .add_asset::<Image>()
+        .insert_resource(Playfield::new(clip.size))
+        .insert_resource(PlaybackSpeed(config.speed))
+        .insert_resource(SimRng::new(config.seed.unwrap_or(SPAWN_SEED)))
+        .init_resource::<CurrentFrame>()
+        .init_resource::<MovementMode>()
+        .init_resource::<Silhouette>()
+        .init_resource::<FieldCache>()
+        .init_resource::<SceneCut>()
+        .init_resource::<Interpolation>()
+        .init_resource::<Breathing>()
+        .insert_resource(Threshold::new(config))
+        .init_resource::<AudioReactivity>()
+        .init_resource::<Lagging>()
+        .init_resource::<Layers>()
+        .init_resource::<Automation>()
+        .init_resource::<Tuning>()
+        .init_resource::<Spotlight>()
+        .add_systems((buffer_clip, update_sprite, swap_frame, move_particle).chain());
+
+    let handles = {
+        let mut images = app.world.resource_mut::<Assets<Image>>();
+        clip.data
+            .into_iter()
+            .map(|data| {
+                let size = Extent3d {
+                    width: clip.size.x,
+                    height: clip.size.y,
+                    depth_or_array_layers: 1,
+                };
+                images.add(Image::new(
+                    size,
+                    TextureDimension::D2,
+                    data,
+                    TextureFormat::R8Unorm,
+                ))
+            })
+            .collect()
+    };
+    app.insert_resource(Clip(handles));
+
+    let playfield = Playfield::new(clip.size);
+    let mut rng = StdRng::seed_from_u64(config.seed.unwrap_or(SPAWN_SEED));
+    let mut rhythms = app.world.resource_mut::<SimRng>().serial();
+    let particles: Vec<_> = (0..config.particles)
+        .map(|_| {
+            (
+                Particle(0),
+                Velocity::default(),
+                Phase::new(&mut rhythms),
+                Transform::from_translation(playfield.random_position(&mut rng).extend(0.0)),
+            )
+        })
+        .collect();
+    app.world.spawn_batch(particles);
+    app.world
+        .spawn((Player::at(0, config.fps), Handle::<Image>::default()));
+    app.insert_resource(config.clone());
+
+    // a tick of simulated time goes by on every update, however long it takes
+    let mut clock = Instant::now();
+    let (mut tick_times, mut move_times) = (Vec::new(), Vec::new());
+    while app.world.resource::<CurrentFrame>().front.index < frames {
+        clock += period;
+        app.insert_resource(TimeUpdateStrategy::ManualInstant(clock));
+
+        let tick = Instant::now();
+        app.update();
+        tick_times.push(tick.elapsed());
+        move_times.push(metrics::take(Timed::MoveParticle));
+    }
+
+    let positions: Vec<_> = app
+        .world
+        .query_filtered::<&Transform, With<Particle>>()
+        .iter(&app.world)
+        .map(|transform| transform.translation.truncate())
+        .collect();
+    let images = app.world.resource::<Assets<Image>>();
+    let image = images
+        .get(&app.world.resource::<CurrentFrame>().front.image)
+        .expect("the clip stays loaded");
+    let frame = app
+        .world
+        .resource::<Threshold>()
+        .apply(FrameView::from_image(image));
+    let on = positions
+        .iter()
+        .filter(|&&pos| frame.is_dark(playfield.pixel_index(pos)))
+        .count();
+
+    Outcome {
+        ticks: tick_times.len(),
+        on_silhouette: on as f32 / positions.len().max(1) as f32,
+        positions,
+        tick_times,
+        move_times,
+    }
+}
+
+pub fn run(config: Config, frames: usize) -> ExitCode {
+    if config.gpu {
+        println!("--headless has no renderer, timing the CPU path instead of the GPU one");
+    }
+
+    let start = Instant::now();
+    let outcome = simulate(&config, frames);
+    let elapsed = start.elapsed();
+
+    println!(
+        "{frames} frames, {} ticks of {} particles in {:.2}s, {:.1} frames per second, \
+         {:.0}% on the silhouette",
+        outcome.ticks,
+        config.particles,
+        elapsed.as_secs_f64(),
+        frames as f64 / elapsed.as_secs_f64(),
+        outcome.on_silhouette * 100.0,
+    );
+    stats("tick", outcome.tick_times);
+    stats("move_particle", outcome.move_times);
+    ExitCode::SUCCESS
+}
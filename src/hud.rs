@@ -0,0 +1,154 @@
+//! Diagnostics overlay toggled with F3, to tell stutter from slow loading apart from a slow
+//! simulation
+
+use bevy::prelude::*;
+
+use crate::{
+    buffer::FrameBudget, config::Config, decode::FrameStore, gpu_sim::SimMetrics, locale::tr_with,
+    toast::UiFont, Particle, Player,
+};
+
+/// Seconds the rates are averaged over
+const WINDOW: f32 = 0.5;
+
+#[derive(Resource, Default)]
+pub struct Hud {
+    visible: bool,
+    /// Ticks and rendered frames since the rates were last measured
+    ticks: u32,
+    frames: u32,
+    elapsed: f32,
+    /// Simulation ticks and rendered frames per second
+    tick_rate: f32,
+    frame_rate: f32,
+}
+
+#[derive(Component)]
+pub struct HudText;
+
+pub fn setup_hud(mut commands: Commands, font: Res<UiFont>) {
+    commands.spawn((
+        HudText,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.0.clone(),
+                font_size: 14.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Px(8.0),
+                top: Val::Px(8.0),
+                ..default()
+            },
+            padding: UiRect::new(Val::Px(6.0), Val::Px(6.0), Val::Px(2.0), Val::Px(2.0)),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.7)),
+    ));
+}
+
+pub fn toggle_hud(mut hud: ResMut<Hud>, keyboard: Res<Input<KeyCode>>) {
+    if keyboard.just_released(KeyCode::F3) {
+        hud.visible = !hud.visible;
+    }
+}
+
+/// Counts the simulation's ticks, which run at their own rate
+pub fn count_tick(mut hud: ResMut<Hud>) {
+    hud.ticks += 1;
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_hud(
+    mut hud: ResMut<Hud>,
+    mut text: Query<(&mut Text, &mut Visibility), With<HudText>>,
+    player: Query<&Player>,
+    particles: Query<(), With<Particle>>,
+    metrics: Option<Res<SimMetrics>>,
+    store: Option<Res<FrameStore>>,
+    budget: Res<FrameBudget>,
+    config: Res<Config>,
+    time: Res<Time>,
+    #[cfg(feature = "alloc-audit")] diagnostics: Res<bevy::diagnostic::Diagnostics>,
+) {
+    let (mut text, mut visibility) = text.single_mut();
+    *visibility = if hud.visible {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    hud.frames += 1;
+    hud.elapsed += time.delta_seconds();
+    if hud.elapsed >= WINDOW {
+        hud.tick_rate = hud.ticks as f32 / hud.elapsed;
+        hud.frame_rate = hud.frames as f32 / hud.elapsed;
+        (hud.ticks, hud.frames, hud.elapsed) = (0, 0, 0.0);
+    }
+    if !hud.visible {
+        return;
+    }
+
+    let player = player.single();
+    let frames = store
+        .and_then(|store| store.frames())
+        .unwrap_or(config.frames);
+    let video_secs = player.play_index as f64 / config.fps;
+
+    let lines = [
+        match frames {
+            usize::MAX => tr_with("hud-frame-live", &[("frame", &player.play_index)]),
+            frames => tr_with(
+                "hud-frame",
+                &[("frame", &player.play_index), ("frames", &frames)],
+            ),
+        },
+        tr_with(
+            "hud-clock",
+            &[
+                ("clock", &format!("{:.1}", player.time.elapsed_secs_f64())),
+                ("video", &format!("{video_secs:.1}")),
+            ],
+        ),
+        tr_with(
+            "hud-rates",
+            &[
+                ("fps", &format!("{:.0}", hud.frame_rate)),
+                ("ticks", &format!("{:.0}", hud.tick_rate)),
+            ],
+        ),
+        match metrics {
+            // the particles aren't entities on the GPU
+            Some(metrics) => tr_with(
+                "hud-particles",
+                &[
+                    ("count", &metrics.particles()),
+                    ("settled", &metrics.settled),
+                    ("coverage", &format!("{:.0}", metrics.coverage() * 100.0)),
+                ],
+            ),
+            None => tr_with("particle-count", &[("count", &particles.iter().len())]),
+        },
+        tr_with(
+            "hud-buffered",
+            &[
+                ("buffered", &player.buffer.len()),
+                ("budget", &budget.frames(config.fps)),
+                ("loading", &player.decoding.len()),
+            ],
+        ),
+    ];
+    text.sections[0].value = lines.join("\n");
+
+    #[cfg(feature = "alloc-audit")]
+    {
+        let summary = crate::alloc_audit::summary(&diagnostics);
+        let value = &mut text.sections[0].value;
+        value.push('\n');
+        value.push_str(&tr_with("hud-allocations", &[("systems", &summary)]));
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    window::WindowFocused,
+    winit::{UpdateMode, WinitSettings},
+};
+
+use crate::{locale::tr, toast::Toasts, State};
+
+/// Time between ticks while nobody is looking, 5fps
+const BACKGROUND_TICK: Duration = Duration::from_millis(200);
+
+/// Whether to pause and tick slowly while the window is out of focus or minimized
+#[derive(Resource)]
+pub struct AutoPause {
+    pub enabled: bool,
+    /// Set when playback was paused by losing focus, so it only resumes if it was playing
+    paused: bool,
+}
+
+impl AutoPause {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            paused: false,
+        }
+    }
+}
+
+/// Keeps the background tick rate in sync with the setting
+pub fn apply_auto_pause(auto_pause: Res<AutoPause>, mut winit: ResMut<WinitSettings>) {
+    if !auto_pause.is_changed() {
+        return;
+    }
+
+    winit.unfocused_mode = if auto_pause.enabled {
+        UpdateMode::ReactiveLowPower {
+            max_wait: BACKGROUND_TICK,
+        }
+    } else {
+        UpdateMode::Continuous
+    };
+}
+
+pub fn pause_when_unfocused(
+    mut focus: EventReader<WindowFocused>,
+    mut auto_pause: ResMut<AutoPause>,
+    mut state: ResMut<State>,
+    mut toasts: ResMut<Toasts>,
+) {
+    let Some(event) = focus.iter().last() else {
+        return;
+    };
+    if !auto_pause.enabled {
+        return;
+    }
+
+    match (event.focused, &*state) {
+        (false, State::Playing) => {
+            *state = State::Paused;
+            auto_pause.paused = true;
+            toasts.push(tr("background-paused"));
+        }
+        (true, State::Paused) if auto_pause.paused => {
+            *state = State::Playing;
+            toasts.push(tr("playing"));
+        }
+        _ => {}
+    }
+    if event.focused {
+        auto_pause.paused = false;
+    }
+}
@@ -0,0 +1,115 @@
+//! Keeps a second copy of the app from starting and fighting the first one over the audio device
+
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use bevy::{app::AppExit, prelude::*};
+
+const LOCK: &str = "bad-apple-particle.lock";
+
+/// Where the lock lives, shared by every instance
+pub fn lock_path() -> PathBuf {
+    env::temp_dir().join(LOCK)
+}
+
+/// Lock file holding the process id of the running instance, removed when it exits.
+/// `None` when running without a lock.
+#[derive(Resource)]
+pub struct InstanceLock(Option<PathBuf>);
+
+impl InstanceLock {
+    /// Takes the lock, or returns the process id of the instance holding it.
+    /// Runs without the lock when it can't be written at all.
+    pub fn acquire() -> Result<Self, u32> {
+        // every tab is on its own, and there is no temporary directory to share
+        if cfg!(target_arch = "wasm32") {
+            return Ok(Self(None));
+        }
+
+        let path = lock_path();
+
+        // a second try after clearing a stale lock
+        for _ in 0..2 {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    if let Err(e) = write!(file, "{}", std::process::id()) {
+                        eprintln!("couldn't write {}: {e}", path.display());
+                    }
+                    return Ok(Self(Some(path)));
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    let pid = fs::read_to_string(&path)
+                        .ok()
+                        .and_then(|text| text.trim().parse().ok());
+                    match pid {
+                        Some(pid) if is_running(pid) => return Err(pid),
+                        _ => {
+                            let _ = fs::remove_file(&path);
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("couldn't create {}: {e}", path.display());
+                    return Ok(Self(None));
+                }
+            }
+        }
+        Ok(Self(None))
+    }
+}
+
+/// Whether a process is still alive
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // signal 0 only checks the process exists, refused for those of other users
+    let signaled = unsafe { libc::kill(pid, 0) } == 0;
+    signaled || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Whether a process is still alive
+#[cfg(windows)]
+fn is_running(pid: u32) -> bool {
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, STILL_ACTIVE},
+        System::Threading::{GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION},
+    };
+
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process == 0 {
+            return false;
+        }
+        let mut code = 0;
+        let alive = GetExitCodeProcess(process, &mut code) != 0 && code == STILL_ACTIVE as u32;
+        CloseHandle(process);
+        alive
+    }
+}
+
+/// Whether a process is still alive, taken as gone when there is no way to tell, so a
+/// lock left by a crash doesn't keep the app from starting
+#[cfg(not(any(unix, windows)))]
+fn is_running(_pid: u32) -> bool {
+    false
+}
+
+pub fn release_lock(mut exit: EventReader<AppExit>, lock: Res<InstanceLock>) {
+    if exit.iter().last().is_none() {
+        return;
+    }
+    if let Some(path) = &lock.0 {
+        if let Err(e) = fs::remove_file(path) {
+            warn!("couldn't remove {}: {e}", path.display());
+        }
+    }
+}
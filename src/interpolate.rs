@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+
+use crate::{
+    config::Config, locale::tr, sim::FrameView, threshold::Threshold, toast::Toasts, CurrentFrame,
+    Player,
+};
+
+/// Smooths the target shape between video frames, so particles react at display rate
+/// instead of stepping at the video's 30Hz
+#[derive(Resource, Default)]
+pub struct Interpolation {
+    pub enabled: bool,
+    /// Frame shown before the current one, and the current one
+    previous: Option<Handle<Image>>,
+    current: Handle<Image>,
+    /// One byte per pixel, empty when there is nothing to blend
+    pub blended: Vec<u8>,
+}
+
+impl Interpolation {
+    /// Blended target for this tick, if any, read dithered so that pixels between two
+    /// frames hold as many particles as they are along. The threshold and inversion
+    /// were applied to both frames already.
+    pub fn view(&self, size: UVec2) -> Option<FrameView<'_>> {
+        (self.enabled && self.blended.len() == (size.x * size.y) as usize)
+            .then(|| FrameView::new(&self.blended, size, 1).dithered(true))
+    }
+}
+
+pub fn toggle_interpolation(
+    mut interpolation: ResMut<Interpolation>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::I) {
+        interpolation.enabled = !interpolation.enabled;
+        toasts.push(if interpolation.enabled {
+            tr("interpolation-on")
+        } else {
+            tr("interpolation-off")
+        });
+    }
+}
+
+pub fn interpolation_enabled(interpolation: Res<Interpolation>) -> bool {
+    interpolation.enabled
+}
+
+/// Crossfades from the previous frame to the current one over the current frame's duration.
+/// This lags the video by a frame, but never has to guess the next one.
+pub fn blend_frames(
+    mut interpolation: ResMut<Interpolation>,
+    current: Res<CurrentFrame>,
+    images: Res<Assets<Image>>,
+    player: Query<&Player>,
+    config: Res<Config>,
+    threshold: Res<Threshold>,
+) {
+    if interpolation.current != current.front.image {
+        let previous = std::mem::replace(&mut interpolation.current, current.front.image.clone());
+        interpolation.previous = Some(previous);
+    }
+
+    let interpolation = &mut *interpolation;
+    interpolation.blended.clear();
+
+    let Some(previous) = &interpolation.previous else {
+        return;
+    };
+    let (Some(from), Some(to)) = (images.get(previous), images.get(&interpolation.current)) else {
+        return;
+    };
+    if from.size() != to.size() {
+        return;
+    }
+
+    let t = (player.single().time.elapsed_secs_f64() * config.fps).fract() as f32;
+    let (from, to) = (
+        threshold.apply(FrameView::from_image(from)),
+        threshold.apply(FrameView::from_image(to)),
+    );
+    from.blend(&to, t, &mut interpolation.blended);
+}
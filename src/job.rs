@@ -0,0 +1,53 @@
+//! Work spawned on the async compute pool and collected once it is done.
+//!
+//! In the browser the pool runs futures on the page's event loop and hands back no task to
+//! poll, so their result is left for the job to take instead. Futures there don't need to be
+//! `Send`, the asset readers' aren't.
+
+use std::future::Future;
+#[cfg(target_arch = "wasm32")]
+use std::sync::{Arc, Mutex};
+
+use bevy::tasks::AsyncComputeTaskPool;
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::tasks::Task;
+#[cfg(not(target_arch = "wasm32"))]
+use futures_lite::future;
+
+pub struct Job<T> {
+    #[cfg(not(target_arch = "wasm32"))]
+    task: Task<T>,
+    #[cfg(target_arch = "wasm32")]
+    result: Arc<Mutex<Option<T>>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + 'static> Job<T> {
+    pub fn spawn(work: impl Future<Output = T> + Send + 'static) -> Self {
+        Self {
+            task: AsyncComputeTaskPool::get().spawn(work),
+        }
+    }
+
+    /// The result once the work is done, only given once
+    pub fn poll(&mut self) -> Option<T> {
+        future::block_on(future::poll_once(&mut self.task))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl<T: 'static> Job<T> {
+    pub fn spawn(work: impl Future<Output = T> + 'static) -> Self {
+        let result = Arc::new(Mutex::new(None));
+        let done = result.clone();
+        AsyncComputeTaskPool::get().spawn(async move {
+            *done.lock().unwrap() = Some(work.await);
+        });
+        Self { result }
+    }
+
+    /// The result once the work is done, only given once
+    pub fn poll(&mut self) -> Option<T> {
+        self.result.lock().unwrap().take()
+    }
+}
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+use crate::{locale::tr_with, toast::Toasts, CurrentFrame, Particle};
+
+/// Depth of the bottom particle layer, above the backdrop
+pub const BASE_Z: f32 = 5.0;
+const LAYERS: f32 = 4.0;
+const LAYER_GAP: f32 = 0.1;
+
+/// What decides which Z layer a particle is drawn in
+#[derive(Resource, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Layering {
+    /// Everything on one layer, drawn in whatever order the sprites come in
+    #[default]
+    Off,
+    /// Particles that have been moving for longer are drawn on top
+    Age,
+    /// Darker particles are drawn on top
+    Darkness,
+}
+
+impl Layering {
+    pub fn next(self) -> Self {
+        match self {
+            Layering::Off => Layering::Age,
+            Layering::Age => Layering::Darkness,
+            Layering::Darkness => Layering::Off,
+        }
+    }
+}
+
+pub fn cycle_layering(
+    mut layering: ResMut<Layering>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::Z) {
+        *layering = layering.next();
+        toasts.push(tr_with(
+            "layering",
+            &[("mode", &format!("{:?}", *layering))],
+        ));
+    }
+}
+
+pub fn layer_particles(
+    mut particles: Query<(&Particle, &Sprite, &mut Transform)>,
+    current: Res<CurrentFrame>,
+    layering: Res<Layering>,
+) {
+    for (standstill, sprite, mut transform) in &mut particles {
+        // in [0, 1], bottom to top
+        let t = match *layering {
+            Layering::Off => 0.0,
+            Layering::Age => {
+                let diff = current.front.index.saturating_sub(standstill.0);
+                1.0 - (-(diff as f32) / 12.0).exp()
+            }
+            Layering::Darkness => {
+                let [r, g, b, _] = sprite.color.as_rgba_f32();
+                1.0 - (0.2126 * r + 0.7152 * g + 0.0722 * b)
+            }
+        };
+
+        transform.translation.z = BASE_Z + (t * (LAYERS - 1.0)).round() * LAYER_GAP;
+    }
+}
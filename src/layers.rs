@@ -0,0 +1,147 @@
+//! Extra groups of particles listed under `layers` in `config.ron`, each with its own count,
+//! color, size and way of moving, on top of the particles of `--particles`
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    particle_bundle,
+    sim::{MovementMode, Playfield, Settle, SimRng, Steering},
+    texture::{ParticleSize, ParticleTexture, ParticleTextures, ShapeAtlas},
+    CurrentFrame, Particle, BASE_Z,
+};
+
+/// One layer of particles, the settings it leaves out following the others
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct LayerSpec {
+    pub count: usize,
+    /// RGB from 0 to 1, instead of the palette
+    pub color: Option<[f32; 3]>,
+    /// Multiplier on the size of the particle shape
+    pub size: f32,
+    /// Multiplier on the jitter of moving particles
+    pub jitter: f32,
+    /// Instead of the movement picked with `M`
+    pub movement: Option<MovementMode>,
+    pub settle: Settle,
+}
+
+impl Default for LayerSpec {
+    fn default() -> Self {
+        Self {
+            count: 5000,
+            color: None,
+            size: 1.0,
+            jitter: 1.0,
+            movement: None,
+            settle: Settle::Fill,
+        }
+    }
+}
+
+impl LayerSpec {
+    /// Steering and jitter of the layer's particles
+    pub fn steer<'a>(&self, steering: Steering<'a>, jitter: f32) -> (Steering<'a>, f32) {
+        let steering = Steering {
+            mode: self.movement.unwrap_or(steering.mode),
+            settle: self.settle,
+            ..steering
+        };
+        (steering, jitter * self.jitter)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Layers(pub Vec<LayerSpec>);
+
+impl Layers {
+    /// Settings of a particle's layer, `None` for those of `--particles`
+    pub fn of(&self, layer: Option<&Layer>) -> Option<&LayerSpec> {
+        self.0.get(layer?.0)
+    }
+}
+
+/// Index in [`Layers`] of the layer a particle belongs to
+#[derive(Component, Clone, Copy)]
+pub struct Layer(pub usize);
+
+/// Spawns or despawns the particles of every layer to match its count
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_layers(
+    mut commands: Commands,
+    layers: Res<Layers>,
+    particles: Query<(Entity, &Layer)>,
+    texture: Res<ParticleTexture>,
+    atlas: Res<ShapeAtlas>,
+    mut textures: ResMut<ParticleTextures>,
+    server: Res<AssetServer>,
+    mut sim_rng: ResMut<SimRng>,
+    playfield: Res<Playfield>,
+    current: Res<CurrentFrame>,
+) {
+    if !layers.is_changed() {
+        return;
+    }
+
+    let mut counts = vec![0; layers.0.len()];
+    for (entity, layer) in &particles {
+        match counts.get_mut(layer.0) {
+            Some(count) if *count < layers.0[layer.0].count => *count += 1,
+            _ => commands.entity(entity).despawn(),
+        }
+    }
+
+    let handle = textures.get(*texture, &atlas, &server);
+    let mut rng = sim_rng.serial();
+    for (idx, (spec, &count)) in layers.0.iter().zip(&counts).enumerate() {
+        let added: Vec<_> = (count..spec.count)
+            .map(|_| {
+                let position = playfield.random_position(&mut rng);
+                let (rect, size) = texture.look(&atlas, position, &playfield, &mut rng);
+                (
+                    particle_bundle(
+                        current.front.index,
+                        position.extend(BASE_Z),
+                        handle.clone(),
+                        rect,
+                        // scaled to the layer by `resize_layers`
+                        size,
+                        &mut rng,
+                    ),
+                    Layer(idx),
+                )
+            })
+            .collect();
+        commands.spawn_batch(added);
+    }
+}
+
+/// Sizes the particles of layers after their shape changed
+pub fn resize_layers(
+    mut particles: Query<(&Layer, &mut Sprite, &mut ParticleSize), Changed<ParticleSize>>,
+    layers: Res<Layers>,
+) {
+    for (layer, mut sprite, mut size) in &mut particles {
+        let Some(spec) = layers.of(Some(layer)) else {
+            continue;
+        };
+        let scaled = size.0 * spec.size;
+        // bypass change detection, or this would scale them again on every frame
+        size.bypass_change_detection().0 = scaled;
+        sprite.custom_size = Some(scaled);
+    }
+}
+
+/// Draws the particles of layers with a color in it, over the palette
+pub fn tint_layers(
+    mut particles: Query<(&Layer, &mut Sprite), With<Particle>>,
+    layers: Res<Layers>,
+) {
+    for (layer, mut sprite) in &mut particles {
+        if let Some([r, g, b]) = layers.of(Some(layer)).and_then(|spec| spec.color) {
+            let alpha = sprite.color.a();
+            sprite.color = Color::rgba(r, g, b, alpha);
+        }
+    }
+}
@@ -0,0 +1,1271 @@
+//! Bad Apple!! played with particles, as a Bevy plugin.
+//!
+//! Add [`BadAppleParticlePlugin`] after `DefaultPlugins` to play the effect in another app:
+//!
+//! ```no_run
+//! use bad_apple_particle::{palette::Palette, BadAppleParticlePlugin};
+//! use bevy::prelude::*;
+//!
+//! App::new()
+//!     .add_plugins(DefaultPlugins)
+//!     .add_plugin(
+//!         BadAppleParticlePlugin::default()
+//!             .particles(10000)
+//!             .palette(Palette::Deuteranopia),
+//!     )
+//!     .run();
+//! ```
+
+use std::{collections::VecDeque, path::PathBuf, sync::Arc, time::Duration};
+
+use bevy::{
+    audio::AddAudioSource, prelude::*, render::camera::ScalingMode, sprite::Material2dPlugin,
+    time::Stopwatch,
+};
+use rand::prelude::*;
+
+mod alloc_audit;
+mod automation;
+mod autosave;
+mod behavior;
+mod bloom;
+mod budget;
+mod buffer;
+mod capture;
+pub mod config;
+mod crystal;
+mod cut;
+mod decode;
+mod export;
+mod field;
+mod flow;
+mod gesture;
+mod gpu_sim;
+mod gravity;
+pub mod headless;
+mod hud;
+mod idle;
+pub mod instance;
+mod interpolate;
+mod job;
+mod layering;
+mod layers;
+mod lifetime;
+mod locale;
+mod low_power;
+mod metrics;
+mod mouse;
+mod msaa;
+mod multicam;
+mod music;
+pub mod pack;
+pub mod palette;
+mod panel;
+mod phase;
+mod playfield;
+mod playlist;
+mod present;
+mod quantize;
+mod reactivity;
+mod reload;
+mod render;
+mod restart;
+mod rewind;
+mod scene3d;
+mod seek;
+mod sequence;
+mod shading;
+pub mod sim;
+mod sim_rate;
+mod spawner;
+mod spectrum;
+mod speed;
+mod splash;
+mod spotlight;
+mod stall;
+mod stepping;
+mod subtitles;
+mod sync;
+mod texture;
+mod threshold;
+mod thumbnails;
+mod timecode;
+mod toast;
+mod trails;
+mod transition;
+pub mod verify;
+mod video;
+mod visualizer;
+
+use sim::{FrameView, MovementMode, Playfield, Settle, SimRng, Steering, Step, JITTER};
+
+use alloc_audit::{AllocAuditPlugin, Audited};
+use automation::{automate, load_automation, thin_particles, Automation};
+use autosave::{autosave, load_autosave, Autosave, AutosaveTimer};
+use behavior::{cycle_movement_mode, find_centroid, is_orbiting, uses_field, Silhouette};
+use bloom::{apply_bloom, cycle_bloom, Bloom};
+use budget::{balance_regions, budgets_enabled, toggle_budgets, RegionBudgets};
+use buffer::{unload_frames, FrameBudget, ShownFrames};
+use capture::CapturePlugin;
+use config::Config;
+use crystal::{crystallize, cycle_lattice, Crystal};
+use cut::{detect_cut, SceneCut};
+use decode::{gray_image, spawn_decode, FrameStore};
+use export::export_particles;
+use field::{compute_fields, FieldCache};
+use flow::{advect_particles, compute_flows, flow_enabled, toggle_flow, OpticalFlow};
+use gesture::{audio_unlocked, prompt_gesture, unlock_audio, AudioUnlocked};
+use gpu_sim::{GpuSim, GpuSimPlugin};
+use gravity::{gravity_enabled, pull_particles, spawn_wells, toggle_gravity, Gravity};
+use hud::{count_tick, setup_hud, toggle_hud, update_hud, Hud};
+use idle::{apply_auto_pause, pause_when_unfocused};
+use interpolate::{blend_frames, interpolation_enabled, toggle_interpolation, Interpolation};
+use job::Job;
+use layering::{cycle_layering, layer_particles, Layering, BASE_Z};
+use layers::{resize_layers, spawn_layers, tint_layers, Layer, LayerSpec, Layers};
+use lifetime::{age_particles, fade_particles, toggle_lifespan, Fade, Lifespan, Lifetime};
+use locale::{tr, tr_with};
+use low_power::{
+    disable_msaa, is_frame_limited, is_low_power, limit_frame_rate, FrameLimit, LowPower,
+};
+use metrics::{metrics_enabled, record_metrics, Metrics, Timed};
+use mouse::{mouse_pressed, push_particles, track_mouse, MouseForce};
+use msaa::toggle_msaa;
+use multicam::{
+    cut_shots, frame_camera, load_shots, multicam_enabled, release_camera, toggle_multicam,
+};
+use music::{AudioClock, Music};
+use palette::{
+    adjust_gamma, cycle_palette, load_colors, toggle_aging, AgeColors, Aging, ColorScheme, Gamma,
+    Palette,
+};
+use panel::{
+    click_seek_bar, panel_visible, press_panel_buttons, setup_panel, toggle_panel, update_panel,
+    Tuning,
+};
+use phase::{breathe, cycle_breathing, Breathing, Phase};
+use playfield::{
+    fit_playfield, fit_window, toggle_fullscreen, toggle_window_fit, Backdrop, WindowFit,
+};
+use playlist::{load_playlist, play_next, Playlist};
+use present::PresentationPlugin;
+use quantize::{
+    apply_quantize, cycle_quantize, resize_post, setup_quantize, Quantize, QuantizeMaterial,
+};
+use reactivity::{
+    follow_bass, pulse_particles, reactivity_enabled, toggle_reactivity, AudioReactivity,
+};
+use reload::{reload_config, ConfigWatcher};
+use render::InstancedRenderPlugin;
+use restart::restart;
+use rewind::{is_rewinding, rewind, Rewind};
+use scene3d::{Scene3dPlugin, ViewMode};
+use seek::seek;
+use sequence::{draw_overlay, load_sequence, run_sequence};
+use shading::{shade_particles, shading_enabled, toggle_shading, DensityShading};
+use sim_rate::{change_sim_rate, SimRate};
+use spawner::{change_particle_count, spawn_particles, ParticleSpawner};
+use spectrum::{analyze_audio, Spectrum};
+use speed::{change_speed, speed_audio, PlaybackSpeed};
+use splash::{hold_playback, setup_splash, splash_visible, update_splash, Loading};
+use spotlight::{move_spotlight, spotlight_enabled, toggle_spotlight, Spotlight};
+use stall::{detect_underrun, is_stalled, setup_stall, show_buffering, Stall};
+use stepping::{is_stepping, show_step, step_frames, tick_step, FrameStep};
+use subtitles::{load_subtitles, show_subtitles};
+use sync::{sync_video, Lag, Lagging};
+use texture::{
+    apply_texture, cycle_texture, load_shapes, ParticleSize, ParticleTextures, ShapeAtlas,
+};
+use threshold::{adapt_threshold, detect_inversion, toggle_inversion, toggle_threshold, Threshold};
+use thumbnails::{make_thumbnails, preview_thumbnail, Thumbnails};
+use timecode::{follow_timecode, TimecodeInput};
+use toast::{setup_toasts, show_toasts, Toasts};
+use trails::{fade_ghosts, leave_trails, spawn_ghosts, toggle_trails, GhostPool};
+use transition::{
+    advance_transition, is_converging, is_scattering, is_transitioning, scatter_particles,
+    show_first_frame, Transition, TransitionSet,
+};
+use video::VideoStream;
+use visualizer::{detect_source, is_video, is_visualizing, visualize_spectrum};
+
+// defaults of the command line options, from the original video
+const FRAMES: usize = 6572;
+const FPS: f64 = 30.0;
+const WIDTH: u32 = 480;
+const HEIGHT: u32 = 360;
+/// Frames of the webcam buffered at most, the latest ones
+const LIVE_FRAMES: usize = 2;
+const PARTICLES: usize = 30000;
+const SOUNDTRACK: &str = "bad_apple.ogg";
+/// Seed of the randomness in the spawn patterns, unless given with `--seed`
+const SPAWN_SEED: u64 = 0x5EED;
+
+#[derive(Resource)]
+pub enum State {
+    Paused,
+    Playing,
+}
+
+/// Camera drawing the scene, as opposed to post-processing passes
+#[derive(Component)]
+pub struct MainCamera;
+
+#[derive(Component)]
+struct Player {
+    buffer: VecDeque<Handle<Image>>,
+    /// Frames being decoded, in order, to go in the buffer once they are ready
+    decoding: VecDeque<Job<(Option<Image>, Duration)>>,
+    play_index: usize,
+    load_index: usize,
+    time: Stopwatch,
+}
+
+impl Player {
+    /// Player with nothing buffered, that has just shown `frame`
+    fn at(frame: usize, fps: f64) -> Self {
+        let mut time = Stopwatch::new();
+        time.set_elapsed(Duration::from_secs_f64(frame as f64 / fps));
+
+        Self {
+            buffer: VecDeque::new(),
+            decoding: VecDeque::new(),
+            play_index: frame,
+            load_index: frame + 1,
+            time,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct Frame {
+    image: Handle<Image>,
+    index: usize,
+}
+
+/// Double-buffered frame, so that every system sees the same one for a whole tick
+#[derive(Resource, Default)]
+struct CurrentFrame {
+    /// Frame displayed and sampled during this tick
+    front: Frame,
+    /// Latest published frame, swapped to the front at the start of the next tick
+    back: Option<Frame>,
+}
+
+impl CurrentFrame {
+    fn publish(&mut self, frame: Frame) {
+        self.back = Some(frame);
+    }
+}
+
+fn swap_frame(
+    mut current: ResMut<CurrentFrame>,
+    mut sprite: Query<&mut Handle<Image>, With<Player>>,
+) {
+    let _audit = alloc_audit::scope(Audited::SwapFrame);
+    if let Some(back) = current.back.take() {
+        *sprite.single_mut() = back.image.clone();
+        current.front = back;
+    }
+}
+
+fn update_sprite(
+    mut player: Query<&mut Player>,
+    mut current: ResMut<CurrentFrame>,
+    config: Res<Config>,
+    speed: Res<PlaybackSpeed>,
+    mut lagging: ResMut<Lagging>,
+    time: Res<Time>,
+) {
+    let mut player = player.single_mut();
+
+    player
+        .time
+        .tick(time.delta().mul_f64(speed.0 * lagging.slowdown()));
+
+    let current_idx = (player.time.elapsed_secs_f64() * config.fps).floor() as usize;
+    let behind = current_idx.saturating_sub(player.play_index);
+    let lagging_now = config.lag == Lag::Slow && behind > 1;
+    if lagging.0 != lagging_now {
+        lagging.0 = lagging_now;
+    }
+
+    if behind > 0 {
+        // land on the due frame, or as close to it as the lag allows and what is buffered gets
+        let skipped = behind.saturating_sub(config.lag.max_behind(config.fps));
+        let skipped = skipped.min(player.buffer.len().saturating_sub(1));
+        player.buffer.drain(..skipped);
+        player.play_index += skipped;
+        if let Some(image) = player.buffer.pop_front() {
+            player.play_index += 1;
+            current.publish(Frame {
+                image,
+                index: player.play_index,
+            });
+        }
+    }
+}
+
+fn load_frames(
+    mut player: Query<&mut Player>,
+    mut budget: ResMut<FrameBudget>,
+    store: Res<FrameStore>,
+    server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    config: Res<Config>,
+    mut stream: Local<Option<VideoStream>>,
+) {
+    let _timer = metrics::time(Timed::LoadFrames);
+    let mut player = player.single_mut();
+
+    // the webcam shows what it sees now, seeking can't go anywhere
+    if let FrameStore::Camera(device) = &*store {
+        let stream =
+            stream.get_or_insert_with(|| VideoStream::capture(device, player.load_index, &config));
+        while let Some(image) = stream.try_next() {
+            player.buffer.push_back(images.add(image));
+            player.load_index += 1;
+        }
+        // drop the frames the player fell behind on rather than lagging behind the camera
+        while player.buffer.len() > LIVE_FRAMES {
+            let late = player.buffer.pop_front().expect("more than kept");
+            images.remove(&late);
+        }
+        return;
+    }
+
+    // the video decodes in order on its own thread
+    if let FrameStore::Video(path) = &*store {
+        // start over from wherever the player seeked to
+        if stream
+            .as_ref()
+            .is_none_or(|stream| stream.next_index() != player.load_index)
+        {
+            *stream = Some(VideoStream::open(path, player.load_index, &config));
+        }
+        let stream = stream.as_mut().expect("just opened");
+        while player.buffer.len() < budget.frames(config.fps) {
+            let Some(image) = stream.try_next() else {
+                break;
+            };
+            budget.record(&image, None);
+            player.buffer.push_back(images.add(image));
+            player.load_index += 1;
+        }
+        return;
+    }
+
+    // take decoded frames in order, even if later ones finished first
+    while let Some(task) = player.decoding.front_mut() {
+        let Some((image, latency)) = task.poll() else {
+            break;
+        };
+        player.decoding.pop_front();
+        let image = match image {
+            Some(image) => {
+                budget.record(&image, Some(latency));
+                image
+            }
+            // keeps the buffer in step with the frame numbers
+            None => placeholder(&player, &images, &config),
+        };
+        player.buffer.push_back(images.add(image));
+    }
+
+    let frames = store.frames().unwrap_or(config.frames);
+    let buffered = budget.frames(config.fps);
+    let in_flight = budget.in_flight(config.fps);
+    while player.load_index < frames
+        && player.buffer.len() + player.decoding.len() < buffered
+        && player.decoding.len() < in_flight
+    {
+        let task = spawn_decode(&store, &server, &config, player.load_index);
+        player.decoding.push_back(task);
+        player.load_index += 1;
+    }
+}
+
+/// Stands in for a frame that couldn't be decoded, the last buffered one again, or a blank
+/// frame when there is none
+fn placeholder(player: &Player, images: &Assets<Image>, config: &Config) -> Image {
+    player
+        .buffer
+        .back()
+        .and_then(|image| images.get(image))
+        .cloned()
+        .unwrap_or_else(|| {
+            let blank = vec![u8::MAX; (config.size.x * config.size.y) as usize];
+            gray_image(&blank, config.size)
+        })
+}
+
+#[derive(Component)]
+struct Particle(usize);
+
+/// Speed kept between ticks by particles attracted to the silhouette, in pixels per tick
+#[derive(Component, Default)]
+struct Velocity(Vec2);
+
+#[allow(clippy::too_many_arguments)]
+fn color_particle(
+    mut particles: Query<(&mut Particle, &mut Sprite)>,
+    mut colors: Local<AgeColors>,
+    current: Res<CurrentFrame>,
+    palette: Res<Palette>,
+    scheme: Res<ColorScheme>,
+    gamma: Res<Gamma>,
+    aging: Res<Aging>,
+    bloom: Res<Bloom>,
+) {
+    let _timer = metrics::time(Timed::ColorParticle);
+    if colors.is_empty()
+        || palette.is_changed()
+        || scheme.is_changed()
+        || gamma.is_changed()
+        || aging.is_changed()
+        || bloom.is_changed()
+    {
+        colors.rebuild(*palette, &scheme, *gamma, aging.0, bloom.boost());
+    }
+
+    for (mut standstill, mut sprite) in &mut particles {
+        // particles stopped on a frame after the shown one when seeking or stepping back
+        // count as stopped on the shown one, aging from there
+        if standstill.0 > current.front.index {
+            standstill.0 = current.front.index;
+        }
+        sprite.color = colors.get(current.front.index - standstill.0);
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn move_particle(
+    mut particles: Query<(
+        Entity,
+        &mut Transform,
+        &mut Velocity,
+        &mut Particle,
+        &Phase,
+        Option<&Layer>,
+    )>,
+    mut sim_rng: ResMut<SimRng>,
+    images: Res<Assets<Image>>,
+    current: Res<CurrentFrame>,
+    playfield: Res<Playfield>,
+    mode: Res<MovementMode>,
+    silhouette: Res<Silhouette>,
+    fields: Res<FieldCache>,
+    (cut, reactivity): (Res<SceneCut>, Res<AudioReactivity>),
+    interpolation: Res<Interpolation>,
+    breathing: Res<Breathing>,
+    (threshold, layers): (Res<Threshold>, Res<Layers>),
+    automation: Res<Automation>,
+    tuning: Res<Tuning>,
+    spotlight: Res<Spotlight>,
+    time: Res<Time>,
+) {
+    let _audit = alloc_audit::scope(Audited::MoveParticle);
+    let _timer = metrics::time(Timed::MoveParticle);
+    if let Some(image) = images.get(&current.front.image) {
+        if image.size().as_uvec2() != playfield.size {
+            return;
+        }
+
+        let frame = interpolation
+            .view(playfield.size)
+            .unwrap_or_else(|| threshold.apply(FrameView::from_image(image)));
+        let jitter = JITTER
+            * cut.jitter_scale()
+            * reactivity.jitter_scale()
+            * automation.jitter
+            * tuning.jitter;
+        let t = time.elapsed_seconds_wrapped();
+        let steering = Steering {
+            mode: *mode,
+            settle: Settle::Fill,
+            centroid: silhouette.centroid,
+            field: fields.get(&current.front.image),
+        };
+
+        sim_rng.advance();
+        let sim_rng = &*sim_rng;
+        particles.par_iter_mut().for_each_mut(
+            |(entity, mut particle, mut velocity, mut standstill, phase, layer)| {
+                // the outer scope only counts the calling thread
+                let _audit = alloc_audit::scope(Audited::MoveParticle);
+                let mut rng = sim_rng.particle(entity.index());
+                let jitter = jitter * phase.jitter_scale(t, breathing.0);
+                let (steering, jitter) = match layers.of(layer) {
+                    Some(spec) => spec.steer(steering, jitter),
+                    None => (steering, jitter),
+                };
+
+                if !spotlight.reveals(particle.translation.truncate()) {
+                    sim::drift(
+                        &mut particle.translation,
+                        &mut velocity.0,
+                        &playfield,
+                        &steering,
+                        jitter,
+                        &mut rng,
+                    );
+                    return;
+                }
+
+                let step = sim::step(
+                    &mut particle.translation,
+                    &mut velocity.0,
+                    &frame,
+                    &playfield,
+                    &steering,
+                    jitter,
+                    &mut rng,
+                );
+                if step == Step::Settled {
+                    standstill.0 = current.front.index;
+                }
+            },
+        );
+    }
+}
+
+pub fn is_playing(state: Res<State>) -> bool {
+    matches!(*state, State::Playing)
+}
+
+pub fn set_state(
+    mut state: ResMut<State>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::Space) {
+        *state = match *state {
+            State::Playing => State::Paused,
+            State::Paused => State::Playing,
+        };
+        toasts.push(match *state {
+            State::Playing => tr("playing"),
+            State::Paused => tr("paused"),
+        });
+    }
+}
+
+/// `mm:ss` position of a frame in the video
+fn timestamp(frame: usize, fps: f64) -> String {
+    let secs = (frame as f64 / fps) as u64;
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+#[derive(Resource, Default)]
+struct MusicPlayer {
+    source: Handle<AudioSource>,
+    sink: Option<Handle<AudioSink>>,
+    /// Position of the playing sink
+    clock: Option<Arc<AudioClock>>,
+}
+
+impl MusicPlayer {
+    /// Stops the soundtrack, to be started again from the player's position
+    fn stop(&mut self, sinks: &Assets<AudioSink>) {
+        self.clock = None;
+        if let Some(sink) = self.sink.take().and_then(|sink| sinks.get(&sink)) {
+            sink.stop();
+        }
+    }
+}
+
+/// Starts the soundtrack at the player's position once it has loaded and playback starts.
+/// Until then it stays armed, not decoding, so that it doesn't get ahead of the stopwatch.
+fn start_audio(
+    mut music_player: ResMut<MusicPlayer>,
+    sources: Res<Assets<AudioSource>>,
+    mut musics: ResMut<Assets<Music>>,
+    audio: Res<Audio<Music>>,
+    sinks: Res<Assets<AudioSink>>,
+    player: Query<&Player>,
+) {
+    if music_player.sink.is_some() {
+        return;
+    }
+
+    if let Some(source) = sources.get(&music_player.source) {
+        let start = player.single().time.elapsed();
+        let clock = Arc::new(AudioClock::new(start));
+        let music = musics.add(Music {
+            source: source.clone(),
+            start,
+            clock: clock.clone(),
+        });
+
+        let handle = audio.play(music);
+        music_player.sink = Some(sinks.get_handle(handle));
+        music_player.clock = Some(clock);
+    }
+}
+
+fn play_audio(
+    music_player: Res<MusicPlayer>,
+    sinks: Res<Assets<AudioSink>>,
+    state: Res<State>,
+    stall: Res<Stall>,
+) {
+    if let Some(sink) = music_player.sink.as_ref().and_then(|sink| sinks.get(sink)) {
+        match (&*state, stall.stalled) {
+            (State::Playing, false) => sink.play(),
+            _ => sink.pause(),
+        }
+    }
+}
+
+/// Components of a particle fading in at `translation`, last settled on `standstill`
+fn particle_bundle(
+    standstill: usize,
+    translation: Vec3,
+    texture: Handle<Image>,
+    rect: Option<Rect>,
+    size: Vec2,
+    rng: &mut impl Rng,
+) -> impl Bundle {
+    (
+        Particle(standstill),
+        ParticleSize(size),
+        Velocity::default(),
+        Lifetime::new(rng),
+        Fade::fade_in(),
+        Phase::new(rng),
+        Crystal::default(),
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.0, 0.0, 0.0, 0.0),
+                custom_size: Some(size),
+                rect,
+                ..default()
+            },
+            texture,
+            transform: Transform::from_translation(translation),
+            ..default()
+        },
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn startup(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    mut music_player: ResMut<MusicPlayer>,
+    save: Res<Autosave>,
+    playfield: Res<Playfield>,
+    low_power: Res<LowPower>,
+    config: Res<Config>,
+    gpu_sim: Option<Res<GpuSim>>,
+    mut toasts: ResMut<Toasts>,
+    mut sim_rng: ResMut<SimRng>,
+    mut spawner: ResMut<ParticleSpawner>,
+) {
+    commands.spawn((
+        MainCamera,
+        Camera2dBundle {
+            projection: OrthographicProjection {
+                scaling_mode: ScalingMode::AutoMin {
+                    min_width: config.size.x as f32,
+                    min_height: config.size.y as f32,
+                },
+                ..default()
+            },
+            ..default()
+        },
+        UiCameraConfig::default(),
+    ));
+
+    commands.spawn((
+        Backdrop,
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::WHITE,
+                custom_size: Some(config.size.as_vec2()),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+
+    // resume where the last session left off, unless it reached the end
+    let frame = if save.frame < config.frames {
+        save.frame
+    } else {
+        0
+    };
+    if frame > 0 {
+        toasts.push(tr_with(
+            "resuming",
+            &[("time", &timestamp(frame, config.fps))],
+        ));
+    }
+
+    commands.spawn((
+        Player::at(frame, config.fps),
+        SpriteBundle {
+            sprite: Sprite {
+                // color: Color::GRAY,
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    ));
+
+    let texture = server.load("particle.png");
+    let count = if gpu_sim.is_some() {
+        // simulated in the compute shader instead
+        0
+    } else if low_power.0 {
+        toasts.push(tr("low-power"));
+        config.particles / 2
+    } else {
+        config.particles
+    };
+    // the same pattern on every launch
+    let mut rng = StdRng::seed_from_u64(config.seed.unwrap_or(SPAWN_SEED));
+    let mut rhythms = sim_rng.serial();
+    for idx in 0..count {
+        let position = save.spawn.position(idx, count, &playfield, &mut rng);
+        commands.spawn(particle_bundle(
+            0,
+            position.extend(BASE_Z),
+            texture.clone(),
+            None,
+            Vec2::new(2.0, 2.0),
+            &mut rhythms,
+        ));
+    }
+    spawner.target = count;
+
+    music_player.source = server.load(config.audio.clone());
+}
+
+/// The whole effect: the video, its soundtrack, the particles and every control.
+/// Expects the `DefaultPlugins` to have been added.
+pub struct BadAppleParticlePlugin {
+    config: Config,
+    movement: MovementMode,
+    /// Overrides the autosaved palette
+    palette: Option<Palette>,
+    reactivity: AudioReactivity,
+    layers: Vec<LayerSpec>,
+}
+
+impl Default for BadAppleParticlePlugin {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+impl BadAppleParticlePlugin {
+    pub fn new(config: Config) -> Self {
+        let file = config.file.clone().unwrap_or_default();
+        Self {
+            config,
+            movement: file.movement.unwrap_or_default(),
+            palette: file.palette,
+            reactivity: file.reactivity.unwrap_or_default(),
+            layers: file.layers.unwrap_or_default(),
+        }
+    }
+
+    /// Plays the PNGs of another folder, relative to the assets folder or absolute
+    pub fn frames(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.set_frames_dir(dir.into());
+        self
+    }
+
+    pub fn particles(mut self, count: usize) -> Self {
+        self.config.particles = count;
+        self
+    }
+
+    /// Colors of settled and moving particles
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.palette = Some(palette);
+        self
+    }
+
+    /// How particles off the silhouette move
+    pub fn movement(mut self, movement: MovementMode) -> Self {
+        self.movement = movement;
+        self
+    }
+}
+
+impl Plugin for BadAppleParticlePlugin {
+    fn build(&self, app: &mut App) {
+        let config = &self.config;
+        locale::select(config.lang.as_deref());
+        let low_power = LowPower::detect(config.low_power);
+        let sim_rate = if low_power.0 {
+            SimRate::slowest()
+        } else {
+            SimRate::default()
+        };
+
+        app.insert_resource(config.clone())
+            .add_plugin(GpuSimPlugin {
+                enabled: config.gpu,
+                particles: config.particles,
+                size: config.size,
+                seed: config.seed.unwrap_or(SPAWN_SEED),
+            })
+            .add_plugin(InstancedRenderPlugin {
+                // the 3D scene draws the particles itself
+                enabled: config.instanced && config.mode == ViewMode::Flat,
+            })
+            .add_plugin(Scene3dPlugin {
+                enabled: config.mode == ViewMode::Space,
+                relief: config.relief,
+            })
+            .add_audio_source::<Music>()
+            .add_startup_system(load_autosave.in_base_set(StartupSet::PreStartup))
+            .add_startup_system(startup)
+            .init_resource::<Toasts>()
+            .add_startup_system(setup_toasts)
+            .add_system(show_toasts)
+            .init_resource::<Hud>()
+            .add_startup_system(setup_hud.in_base_set(StartupSet::PostStartup))
+            .add_system(toggle_hud)
+            .add_system(count_tick.in_schedule(CoreSchedule::FixedUpdate))
+            .add_system(update_hud.after(toggle_hud))
+            .init_resource::<Loading>()
+            .add_startup_system(setup_splash.in_base_set(StartupSet::PostStartup))
+            .add_system(update_splash.run_if(splash_visible))
+            .add_system(
+                hold_playback
+                    .run_if(splash_visible)
+                    .after(set_state)
+                    .after(press_panel_buttons)
+                    .before(update_splash)
+                    .before(play_audio)
+                    .before(update_sprite),
+            )
+            .add_startup_system(load_sequence.in_base_set(StartupSet::PostStartup))
+            .add_startup_system(load_subtitles.in_base_set(StartupSet::PostStartup))
+            .add_system(show_subtitles.after(update_sprite))
+            .add_system(run_sequence.after(update_splash))
+            .add_system(draw_overlay.after(run_sequence))
+            .insert_resource(State::Paused)
+            .add_system(set_state)
+            .add_system(apply_auto_pause)
+            .add_system(pause_when_unfocused.after(set_state))
+            .insert_resource(MusicPlayer::default())
+            .init_resource::<AudioUnlocked>()
+            .add_startup_system(prompt_gesture.after(setup_toasts))
+            .add_system(unlock_audio.run_if(not(audio_unlocked)))
+            .add_system(
+                start_audio
+                    .run_if(is_playing)
+                    .run_if(not(is_stalled))
+                    .run_if(audio_unlocked)
+                    .run_if(not(is_rewinding))
+                    .run_if(not(splash_visible))
+                    .run_if(not(is_transitioning))
+                    .after(unlock_audio),
+            )
+            .add_system(play_audio.after(start_audio))
+            .insert_resource(PlaybackSpeed(config.speed))
+            .add_system(change_speed)
+            .add_system(speed_audio.after(start_audio).after(change_speed))
+            .add_startup_system(detect_source.in_base_set(StartupSet::PreStartup))
+            .add_system(load_frames.run_if(is_video))
+            .add_system(
+                seek.run_if(is_video)
+                    .before(load_frames)
+                    .before(start_audio),
+            )
+            .add_system(
+                restart
+                    .run_if(is_video)
+                    .after(seek)
+                    .before(load_frames)
+                    .before(start_audio),
+            )
+            .init_resource::<FrameStep>()
+            .add_system(
+                step_frames
+                    .run_if(not(is_playing))
+                    .run_if(is_video)
+                    .after(seek)
+                    .before(load_frames)
+                    .before(start_audio),
+            )
+            .add_system(
+                show_step
+                    .run_if(not(is_playing))
+                    .after(step_frames)
+                    .after(load_frames),
+            )
+            .init_resource::<Spectrum>()
+            .add_system(analyze_audio)
+            .init_resource::<CurrentFrame>()
+            .add_system(swap_frame.in_base_set(CoreSet::PreUpdate))
+            .insert_resource(FrameBudget::new(config))
+            .insert_resource(ShownFrames::new(config.history))
+            .add_system(
+                unload_frames
+                    .in_base_set(CoreSet::PreUpdate)
+                    .after(swap_frame),
+            )
+            .insert_resource(Metrics::open(config.metrics.as_deref()))
+            .add_system(
+                record_metrics
+                    .run_if(metrics_enabled)
+                    .in_base_set(CoreSet::PreUpdate)
+                    .before(swap_frame),
+            )
+            .insert_resource(Playfield::new(config.size))
+            .add_system(
+                fit_playfield
+                    .in_base_set(CoreSet::PreUpdate)
+                    .after(swap_frame),
+            )
+            .init_resource::<WindowFit>()
+            .add_system(toggle_window_fit)
+            .add_system(fit_window)
+            .add_system(toggle_fullscreen)
+            .init_resource::<Stall>()
+            .init_resource::<Lagging>()
+            .add_startup_system(setup_stall.in_base_set(StartupSet::PostStartup))
+            .add_system(
+                detect_underrun
+                    .run_if(is_playing)
+                    .run_if(is_video)
+                    .after(load_frames)
+                    .before(update_sprite),
+            )
+            .add_system(show_buffering.after(detect_underrun))
+            .add_system(
+                sync_video
+                    .run_if(is_playing)
+                    .run_if(not(is_stalled))
+                    .run_if(not(resource_exists::<TimecodeInput>()))
+                    .before(update_sprite),
+            )
+            .add_system(
+                follow_timecode
+                    .run_if(resource_exists::<TimecodeInput>())
+                    .run_if(is_video)
+                    .before(update_sprite)
+                    .before(load_frames)
+                    .before(start_audio),
+            )
+            .add_system(
+                update_sprite
+                    .run_if(is_playing)
+                    .run_if(is_video)
+                    .run_if(not(is_rewinding))
+                    .run_if(not(is_stalled))
+                    .run_if(not(is_transitioning)),
+            )
+            .init_resource::<Rewind>()
+            .add_system(
+                rewind
+                    .run_if(is_video)
+                    .after(seek)
+                    .before(update_sprite)
+                    .before(start_audio),
+            )
+            .init_resource::<Playlist>()
+            .add_startup_system(load_playlist)
+            .init_resource::<Transition>()
+            .configure_set(
+                TransitionSet
+                    .run_if(is_playing)
+                    .after(update_sprite)
+                    .after(load_frames)
+                    .before(play_next),
+            )
+            .add_system(advance_transition.in_set(TransitionSet))
+            .add_system(
+                show_first_frame
+                    .run_if(is_converging)
+                    .in_set(TransitionSet)
+                    .after(advance_transition),
+            )
+            .add_system(
+                scatter_particles
+                    .run_if(is_playing)
+                    .run_if(is_scattering)
+                    .after(move_particle)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(
+                play_next
+                    .run_if(is_playing)
+                    .run_if(is_video)
+                    .after(update_sprite),
+            )
+            .add_system(visualize_spectrum.run_if(is_playing).run_if(is_visualizing))
+            .insert_resource(low_power)
+            .insert_resource(FrameLimit::new(config.max_fps, low_power))
+            .add_system(
+                limit_frame_rate
+                    .run_if(is_frame_limited)
+                    .in_base_set(CoreSet::Last),
+            )
+            .insert_resource(FixedTime::new(sim_rate.period()))
+            .insert_resource(sim_rate)
+            .add_system(change_sim_rate)
+            .insert_resource(Threshold::new(config))
+            .add_system(toggle_threshold)
+            .add_system(adapt_threshold.in_schedule(CoreSchedule::FixedUpdate))
+            .add_system(toggle_inversion)
+            .add_system(detect_inversion.after(toggle_inversion))
+            .insert_resource(self.movement)
+            .insert_resource(SimRng::new(
+                config.seed.unwrap_or_else(|| thread_rng().gen()),
+            ))
+            .init_resource::<Silhouette>()
+            .init_resource::<FieldCache>()
+            .add_system(cycle_movement_mode)
+            .add_system(
+                find_centroid
+                    .run_if(is_orbiting.or_else(multicam_enabled))
+                    .after(adapt_threshold)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(compute_fields.run_if(uses_field).after(update_sprite))
+            .init_resource::<SceneCut>()
+            .add_system(detect_cut.in_schedule(CoreSchedule::FixedUpdate))
+            .init_resource::<OpticalFlow>()
+            .add_system(toggle_flow)
+            .add_system(
+                compute_flows
+                    .run_if(flow_enabled)
+                    .run_if(not(resource_exists::<GpuSim>()))
+                    .after(update_sprite),
+            )
+            .add_system(
+                advect_particles
+                    .run_if(is_playing)
+                    .run_if(flow_enabled)
+                    .run_if(not(resource_exists::<GpuSim>()))
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_startup_system(load_shots)
+            .add_system(toggle_multicam)
+            .add_system(
+                cut_shots
+                    .run_if(is_playing)
+                    .run_if(multicam_enabled)
+                    .after(toggle_multicam),
+            )
+            .add_system(frame_camera.run_if(multicam_enabled).after(cut_shots))
+            .add_system(release_camera.after(toggle_multicam).after(automate))
+            .init_resource::<Automation>()
+            .add_startup_system(load_automation)
+            .add_system(automate.before(cut_shots).before(cycle_palette))
+            .add_system(thin_particles.after(automate).after(press_panel_buttons))
+            .init_resource::<ParticleSpawner>()
+            .add_system(change_particle_count.run_if(not(resource_exists::<GpuSim>())))
+            .add_system(
+                spawn_particles
+                    .run_if(not(resource_exists::<GpuSim>()))
+                    .after(change_particle_count)
+                    .after(press_panel_buttons),
+            )
+            .insert_resource(Layers(self.layers.clone()))
+            .add_system(spawn_layers.run_if(not(resource_exists::<GpuSim>())))
+            .add_system(resize_layers.after(apply_texture))
+            .add_system(
+                tint_layers
+                    .run_if(is_playing.or_else(is_stepping))
+                    .after(color_particle)
+                    .before(shade_particles)
+                    .before(fade_particles)
+                    .before(pulse_particles)
+                    .before(layer_particles),
+            )
+            .init_resource::<Interpolation>()
+            .add_system(toggle_interpolation)
+            .add_system(
+                blend_frames
+                    .run_if(is_playing)
+                    .run_if(interpolation_enabled)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(
+                move_particle
+                    .run_if(is_playing.or_else(is_stepping))
+                    .run_if(not(is_scattering))
+                    .after(find_centroid)
+                    .after(detect_cut)
+                    .after(advect_particles)
+                    .after(blend_frames)
+                    .after(adapt_threshold)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(
+                tick_step
+                    .run_if(is_stepping)
+                    .after(move_particle)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .init_resource::<Spotlight>()
+            .add_system(toggle_spotlight)
+            .add_system(move_spotlight.run_if(spotlight_enabled))
+            .insert_resource(MouseForce::new(config.mouse_radius))
+            .add_system(track_mouse)
+            .add_system(
+                push_particles
+                    .run_if(is_playing)
+                    .run_if(mouse_pressed)
+                    .after(move_particle)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .init_resource::<Gravity>()
+            .add_system(toggle_gravity)
+            .add_system(
+                spawn_wells
+                    .run_if(is_playing)
+                    .run_if(gravity_enabled)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(
+                pull_particles
+                    .run_if(is_playing)
+                    .run_if(gravity_enabled)
+                    .after(spawn_wells)
+                    .after(move_particle)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .init_resource::<RegionBudgets>()
+            .add_system(toggle_budgets)
+            .add_system(
+                balance_regions
+                    .run_if(is_playing)
+                    .run_if(budgets_enabled)
+                    .after(move_particle)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(cycle_breathing)
+            .add_system(breathe.run_if(is_playing))
+            .init_resource::<ColorScheme>()
+            .add_startup_system(load_colors)
+            .add_system(cycle_palette)
+            .add_system(adjust_gamma)
+            .init_resource::<Aging>()
+            .add_system(toggle_aging)
+            .add_system(color_particle.run_if(is_playing.or_else(is_stepping)))
+            .init_resource::<DensityShading>()
+            .add_system(toggle_shading)
+            .add_system(
+                shade_particles
+                    .run_if(is_playing)
+                    .run_if(shading_enabled)
+                    .after(color_particle),
+            )
+            .init_resource::<Lifespan>()
+            .add_system(toggle_lifespan)
+            .add_system(age_particles.run_if(is_playing))
+            .add_system(fade_particles.after(color_particle))
+            .insert_resource(self.reactivity)
+            .add_system(toggle_reactivity)
+            .add_system(follow_bass.run_if(reactivity_enabled))
+            .add_system(
+                pulse_particles
+                    .run_if(is_playing.or_else(is_stepping))
+                    .after(follow_bass)
+                    .after(shade_particles)
+                    .after(fade_particles),
+            )
+            .init_resource::<GhostPool>()
+            .add_system(toggle_trails)
+            .add_system(spawn_ghosts.after(toggle_trails))
+            .add_system(
+                leave_trails
+                    .run_if(is_playing)
+                    .after(spawn_ghosts)
+                    .after(fade_particles)
+                    .after(layer_particles),
+            )
+            .add_system(fade_ghosts.after(leave_trails))
+            .add_system(cycle_lattice)
+            .add_system(
+                crystallize
+                    .run_if(is_playing)
+                    .after(cycle_lattice)
+                    .after(apply_texture),
+            )
+            .init_resource::<ParticleTextures>()
+            .init_resource::<ShapeAtlas>()
+            .add_startup_system(load_shapes)
+            .add_system(cycle_texture)
+            .add_system(apply_texture.after(cycle_texture))
+            .init_resource::<Layering>()
+            .add_system(cycle_layering)
+            .add_system(
+                layer_particles
+                    .run_if(is_playing)
+                    .after(color_particle)
+                    .after(shade_particles),
+            )
+            .add_plugin(Material2dPlugin::<QuantizeMaterial>::default())
+            .init_resource::<Quantize>()
+            .add_startup_system(setup_quantize)
+            .add_system(cycle_quantize.run_if(not(is_low_power)))
+            .add_system(apply_quantize.after(cycle_quantize))
+            .add_system(resize_post)
+            .add_startup_system(disable_msaa.run_if(is_low_power))
+            .add_system(toggle_msaa.run_if(not(is_low_power)))
+            .init_resource::<Bloom>()
+            .add_system(cycle_bloom.run_if(not(is_low_power)))
+            .add_system(apply_bloom.after(cycle_bloom))
+            .add_system(export_particles)
+            .add_plugin(CapturePlugin)
+            .add_plugin(PresentationPlugin)
+            .insert_resource(Tuning {
+                jitter: config
+                    .file
+                    .as_ref()
+                    .and_then(|file| file.jitter)
+                    .unwrap_or(1.0),
+                ..default()
+            })
+            .add_startup_system(setup_panel.in_base_set(StartupSet::PostStartup))
+            .add_system(toggle_panel)
+            .add_system(press_panel_buttons.before(cycle_palette))
+            .add_system(
+                click_seek_bar
+                    .run_if(is_video)
+                    .before(load_frames)
+                    .before(start_audio),
+            )
+            .add_system(update_panel.run_if(panel_visible).after(toggle_panel))
+            .init_resource::<Thumbnails>()
+            .add_system(make_thumbnails.run_if(panel_visible).run_if(is_video))
+            .add_system(
+                preview_thumbnail
+                    .run_if(panel_visible)
+                    .run_if(is_video)
+                    .after(make_thumbnails),
+            )
+            .init_resource::<AutosaveTimer>()
+            .add_system(autosave)
+            .add_plugin(AllocAuditPlugin);
+
+        // browsers have no files to watch
+        if let (Some(file), false) = (&config.file, cfg!(target_arch = "wasm32")) {
+            match ConfigWatcher::new(file.clone()) {
+                Ok(watcher) => {
+                    app.insert_resource(watcher).add_system(reload_config);
+                }
+                Err(e) => warn!("couldn't watch the settings for changes: {e}"),
+            }
+        }
+
+        if let Some(device) = &config.timecode {
+            app.insert_resource(TimecodeInput::listen(device));
+        }
+
+        if let Some(palette) = self.palette {
+            // after the autosave is loaded
+            app.add_startup_system(move |mut commands: Commands| commands.insert_resource(palette));
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, time::Stopwatch};
+use rand::prelude::*;
+
+use crate::{
+    locale::{tr, tr_with},
+    sim::{Playfield, SimRng},
+    toast::Toasts,
+    CurrentFrame, Particle,
+};
+
+/// Time taken to fade a particle in or out
+const FADE: Duration = Duration::from_millis(500);
+/// Average lifespan when recycling is turned on, in seconds
+const LIFESPAN: f32 = 30.0;
+
+/// Average number of seconds a particle lives before it is recycled somewhere else,
+/// `None` to keep particles forever
+#[derive(Resource, Default)]
+pub struct Lifespan(pub Option<f32>);
+
+#[derive(Component)]
+pub struct Lifetime {
+    age: Stopwatch,
+    /// Multiplier on the [`Lifespan`], so particles don't all die at once
+    span: f32,
+}
+
+impl Lifetime {
+    pub fn new(rng: &mut impl Rng) -> Self {
+        Self {
+            age: Stopwatch::new(),
+            span: rng.gen_range(0.5..1.5),
+        }
+    }
+}
+
+/// Alpha transition of a particle that just spawned or is about to be recycled
+#[derive(Component)]
+pub enum Fade {
+    In(Timer),
+    Out(Timer),
+}
+
+impl Fade {
+    pub fn fade_in() -> Self {
+        Fade::In(Timer::new(FADE, TimerMode::Once))
+    }
+
+    pub fn fade_out() -> Self {
+        Fade::Out(Timer::new(FADE, TimerMode::Once))
+    }
+}
+
+pub fn toggle_lifespan(
+    mut lifespan: ResMut<Lifespan>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::L) {
+        lifespan.0 = match lifespan.0 {
+            Some(_) => None,
+            None => Some(LIFESPAN),
+        };
+        toasts.push(match lifespan.0 {
+            Some(span) => tr_with("lifespan", &[("span", &span)]),
+            None => tr("lifespan-off"),
+        });
+    }
+}
+
+pub fn age_particles(
+    mut commands: Commands,
+    mut particles: Query<(Entity, &mut Lifetime), Without<Fade>>,
+    lifespan: Res<Lifespan>,
+    time: Res<Time>,
+) {
+    for (entity, mut lifetime) in &mut particles {
+        lifetime.age.tick(time.delta());
+
+        if let Some(span) = lifespan.0 {
+            if lifetime.age.elapsed_secs() > span * lifetime.span {
+                commands.entity(entity).insert(Fade::fade_out());
+            }
+        }
+    }
+}
+
+pub fn fade_particles(
+    mut commands: Commands,
+    mut particles: Query<(
+        Entity,
+        &mut Fade,
+        &mut Sprite,
+        &mut Transform,
+        &mut Particle,
+        &mut Lifetime,
+    )>,
+    time: Res<Time>,
+    playfield: Res<Playfield>,
+    current: Res<CurrentFrame>,
+    mut sim_rng: ResMut<SimRng>,
+) {
+    let mut rng = sim_rng.serial();
+
+    for (entity, mut fade, mut sprite, mut transform, mut particle, mut lifetime) in &mut particles
+    {
+        match &mut *fade {
+            Fade::In(timer) => {
+                timer.tick(time.delta());
+                sprite.color.set_a(timer.percent());
+
+                if timer.finished() {
+                    commands.entity(entity).remove::<Fade>();
+                }
+            }
+            Fade::Out(timer) => {
+                timer.tick(time.delta());
+                sprite.color.set_a(timer.percent_left());
+
+                if timer.finished() {
+                    let pos = playfield.random_position(&mut rng);
+                    transform.translation = pos.extend(transform.translation.z);
+                    particle.0 = current.front.index;
+                    *lifetime = Lifetime::new(&mut rng);
+                    *fade = Fade::fade_in();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Text shown on screen, looked up by key in the bundled translation of the language picked
+//! with `--lang`, or the system's otherwise, falling back to English.
+//!
+//! Translations are in the Fluent format, of which only what the strings need is read:
+//! `key = text` messages, `#` comments and `{ $name }` placeables.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use bevy::prelude::*;
+
+/// Languages bundled with the app, the first being the fallback
+const LANGUAGES: &[(&str, &str)] = &[
+    ("en", include_str!("../assets/locales/en.ftl")),
+    ("fr", include_str!("../assets/locales/fr.ftl")),
+];
+
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+
+struct Locale {
+    /// Code of the language picked, such as `fr`
+    lang: &'static str,
+    messages: HashMap<&'static str, &'static str>,
+    fallback: HashMap<&'static str, &'static str>,
+}
+
+impl Locale {
+    fn new(lang: &'static str, source: &'static str) -> Self {
+        Self {
+            lang,
+            messages: parse(source),
+            fallback: parse(LANGUAGES[0].1),
+        }
+    }
+
+    fn message(&self, key: &str) -> Option<&'static str> {
+        self.messages
+            .get(key)
+            .or_else(|| self.fallback.get(key))
+            .copied()
+    }
+}
+
+/// Messages of a Fluent file, by key
+fn parse(source: &'static str) -> HashMap<&'static str, &'static str> {
+    source
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            let (key, text) = line.split_once('=')?;
+            Some((key.trim(), text.trim()))
+        })
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+/// Fills in the `{ $name }` placeables of a message
+fn fill(message: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut text = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        text.push_str(&rest[..start]);
+        let name = rest[start + 1..end].trim().trim_start_matches('$');
+        match args.iter().find(|(arg, _)| *arg == name) {
+            Some((_, value)) => text.push_str(&value.to_string()),
+            None => text.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+    text
+}
+
+/// Language of the system, from the usual environment variables such as `LANG=fr_FR.UTF-8`
+fn system_lang() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// Picks the language to show text in, `lang` when given or the system's.
+/// Only the first pick counts, for the apps made again on restart and by the tests.
+pub fn select(lang: Option<&str>) {
+    let wanted = lang.map(str::to_string).or_else(system_lang);
+    LOCALE.get_or_init(|| {
+        let code = wanted.as_deref().map(|wanted| {
+            wanted
+                .split(['_', '-', '.', '@'])
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase()
+        });
+        let (lang, source) = match code {
+            Some(code) => LANGUAGES
+                .iter()
+                .find(|(lang, _)| *lang == code)
+                .copied()
+                .unwrap_or_else(|| {
+                    // the system's is often one without a translation, such as C
+                    if lang.is_some() {
+                        warn!("no {code} translation, showing text in English");
+                    }
+                    LANGUAGES[0]
+                }),
+            None => LANGUAGES[0],
+        };
+        Locale::new(lang, source)
+    });
+}
+
+fn locale() -> &'static Locale {
+    LOCALE.get_or_init(|| Locale::new(LANGUAGES[0].0, LANGUAGES[0].1))
+}
+
+/// Text of the message `key`, or the key itself when no language has it
+pub fn tr(key: &str) -> String {
+    tr_with(key, &[])
+}
+
+/// Text of the message `key`, with its placeables filled in from `args`
+pub fn tr_with(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    match locale().message(key) {
+        Some(message) => fill(message, args),
+        None => {
+            debug!("no text for {key}");
+            key.to_string()
+        }
+    }
+}
+
+/// `path` for the language picked, like `subtitles.fr.srt` for `subtitles.srt`, when there
+/// is such a file
+pub fn localized(path: &Path) -> Option<PathBuf> {
+    let stem = path.file_stem()?.to_str()?;
+    let lang = locale().lang;
+    let name = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => format!("{stem}.{lang}.{ext}"),
+        None => format!("{stem}.{lang}"),
+    };
+    Some(path.with_file_name(name)).filter(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_placeables() {
+        let message = "Frame { $frame } at {$time}, { $missing }";
+        assert_eq!(
+            fill(message, &[("frame", &12), ("time", &"0:01")]),
+            "Frame 12 at 0:01, { $missing }"
+        );
+    }
+
+    #[test]
+    fn skips_comments() {
+        let messages = parse("# a comment = not a message\n\nplaying = Playing\n");
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages["playing"], "Playing");
+    }
+
+    #[test]
+    fn translations_are_complete() {
+        let english = parse(LANGUAGES[0].1);
+        for (lang, source) in &LANGUAGES[1..] {
+            let messages = parse(source);
+            for key in english.keys() {
+                assert!(messages.contains_key(key), "{lang} has no {key}");
+            }
+            for key in messages.keys() {
+                assert!(english.contains_key(key), "{lang} has an unknown {key}");
+            }
+        }
+    }
+}
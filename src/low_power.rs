@@ -0,0 +1,80 @@
+use std::{thread, time::Duration};
+
+use bevy::{prelude::*, utils::Instant};
+
+/// Highest rendering frame rate in low-power mode
+const LOW_POWER_FPS: f64 = 30.0;
+
+/// Trades smoothness for battery life: half the particles, no anti-aliasing, bloom or
+/// output palette, a slower simulation and rendering capped at 30fps
+#[derive(Resource, Clone, Copy)]
+pub struct LowPower(pub bool);
+
+impl LowPower {
+    /// As `forced` with `--low-power`, `--no-low-power` or the config file, otherwise on when
+    /// running on battery
+    pub fn detect(forced: Option<bool>) -> Self {
+        Self(forced.unwrap_or_else(on_battery))
+    }
+}
+
+pub fn is_low_power(low_power: Res<LowPower>) -> bool {
+    low_power.0
+}
+
+/// Turns off the anti-aliasing loaded from the autosave. Bloom and output palettes start
+/// off, and can't be picked in low-power mode.
+pub fn disable_msaa(mut commands: Commands) {
+    commands.insert_resource(Msaa::Off);
+}
+
+/// Whether a laptop battery is discharging, as reported by the kernel
+#[cfg(target_os = "linux")]
+fn on_battery() -> bool {
+    let Ok(supplies) = std::fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+
+    supplies.flatten().any(|supply| {
+        let read = |file| std::fs::read_to_string(supply.path().join(file)).unwrap_or_default();
+        read("type").trim() == "Battery" && read("status").trim() == "Discharging"
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn on_battery() -> bool {
+    false
+}
+
+/// Shortest time between two rendered frames, `None` when uncapped
+#[derive(Resource, Clone, Copy)]
+pub struct FrameLimit(Option<Duration>);
+
+impl FrameLimit {
+    /// Caps rendering at `max_fps`, or lower in low-power mode
+    pub fn new(max_fps: Option<f64>, low_power: LowPower) -> Self {
+        let fps = max_fps
+            .into_iter()
+            .chain(low_power.0.then_some(LOW_POWER_FPS))
+            .reduce(f64::min);
+        Self(fps.map(|fps| Duration::from_secs_f64(1.0 / fps)))
+    }
+}
+
+pub fn is_frame_limited(limit: Res<FrameLimit>) -> bool {
+    limit.0.is_some()
+}
+
+/// Sleeps off what is left of the frame time, at the very end of the frame
+pub fn limit_frame_rate(mut last: Local<Option<Instant>>, limit: Res<FrameLimit>) {
+    let Some(frame_time) = limit.0 else {
+        return;
+    };
+    if let Some(last) = *last {
+        let elapsed = last.elapsed();
+        if elapsed < frame_time {
+            thread::sleep(frame_time - elapsed);
+        }
+    }
+    *last = Some(Instant::now());
+}
@@ -0,0 +1,180 @@
+//! Appends a CSV row of simulation metrics for every frame played, to plot how
+//! parameter changes affect how well the particles converge
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use bevy::{prelude::*, utils::Instant};
+
+use crate::{
+    sim::{FrameView, Playfield},
+    threshold::Threshold,
+    CurrentFrame, Particle,
+};
+
+/// Systems whose run time goes in the metrics
+#[derive(Clone, Copy, Debug)]
+pub enum Timed {
+    MoveParticle,
+    ColorParticle,
+    LoadFrames,
+}
+
+impl Timed {
+    const ALL: [Timed; 3] = [Timed::MoveParticle, Timed::ColorParticle, Timed::LoadFrames];
+
+    fn name(self) -> &'static str {
+        match self {
+            Timed::MoveParticle => "move_particle",
+            Timed::ColorParticle => "color_particle",
+            Timed::LoadFrames => "load_frames",
+        }
+    }
+}
+
+/// Nanoseconds spent in every timed system since the last row
+static NANOS: [AtomicU64; Timed::ALL.len()] =
+    [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+/// Adds the time until it is dropped to a system
+#[must_use]
+pub struct Timer {
+    system: Timed,
+    start: Instant,
+}
+
+pub fn time(system: Timed) -> Timer {
+    Timer {
+        system,
+        start: Instant::now(),
+    }
+}
+
+/// Time spent in a system since the last call
+pub fn take(system: Timed) -> Duration {
+    Duration::from_nanos(NANOS[system as usize].swap(0, Ordering::Relaxed))
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        let nanos = self.start.elapsed().as_nanos() as u64;
+        NANOS[self.system as usize].fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+/// CSV file the rows go to, `None` without `--metrics`
+#[derive(Resource)]
+pub struct Metrics {
+    file: Option<File>,
+    /// Pixels with a particle on them, kept around between frames
+    covered: Vec<bool>,
+}
+
+impl Metrics {
+    /// Opens the file for appending, writing the header if it is new
+    pub fn open(path: Option<&Path>) -> Self {
+        let file = path.and_then(|path| {
+            let result = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| {
+                    if file.metadata()?.len() == 0 {
+                        let timings: Vec<_> = Timed::ALL
+                            .iter()
+                            .map(|system| format!("{}_ms", system.name()))
+                            .collect();
+                        writeln!(file, "frame,moving,coverage,{}", timings.join(","))?;
+                    }
+                    Ok(file)
+                });
+            match result {
+                Ok(file) => Some(file),
+                Err(e) => {
+                    warn!("couldn't open {}: {e}", path.display());
+                    None
+                }
+            }
+        });
+
+        Self {
+            file,
+            covered: Vec::new(),
+        }
+    }
+}
+
+pub fn metrics_enabled(metrics: Res<Metrics>) -> bool {
+    metrics.file.is_some()
+}
+
+/// Writes a row about the displayed frame right before the next one replaces it,
+/// once the particles have had all of its duration to settle
+pub fn record_metrics(
+    mut metrics: ResMut<Metrics>,
+    particles: Query<(&Transform, &Particle)>,
+    current: Res<CurrentFrame>,
+    images: Res<Assets<Image>>,
+    playfield: Res<Playfield>,
+    threshold: Res<Threshold>,
+) {
+    let index = current.front.index;
+    if current.back.is_none() || index == 0 {
+        return;
+    }
+    let Some(image) = images.get(&current.front.image) else {
+        return;
+    };
+    if image.size().as_uvec2() != playfield.size {
+        return;
+    }
+    let frame = threshold.apply(FrameView::from_image(image));
+
+    let mut moving = 0;
+    let metrics = &mut *metrics;
+    metrics.covered.clear();
+    metrics
+        .covered
+        .resize((playfield.size.x * playfield.size.y) as usize, false);
+    for (transform, standstill) in &particles {
+        if standstill.0 != index {
+            moving += 1;
+        }
+        metrics.covered[playfield.pixel_index(transform.translation.truncate())] = true;
+    }
+
+    // share of the silhouette that has a particle on it
+    let (dark, covered) = metrics.covered.iter().enumerate().fold(
+        (0usize, 0usize),
+        |(dark, covered), (idx, &is_covered)| {
+            if frame.is_dark(idx) {
+                (dark + 1, covered + is_covered as usize)
+            } else {
+                (dark, covered)
+            }
+        },
+    );
+    let coverage = if dark == 0 {
+        100.0
+    } else {
+        covered as f32 / dark as f32 * 100.0
+    };
+
+    let timings: Vec<_> = Timed::ALL
+        .iter()
+        .map(|&system| format!("{:.3}", take(system).as_secs_f64() * 1e3))
+        .collect();
+
+    let Some(file) = &mut metrics.file else {
+        return;
+    };
+    if let Err(e) = writeln!(file, "{index},{moving},{coverage:.2},{}", timings.join(",")) {
+        warn!("couldn't write the metrics, stopping: {e}");
+        metrics.file = None;
+    }
+}
@@ -0,0 +1,158 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{sim::Playfield, MainCamera, Particle};
+
+/// Distance a particle right under the cursor is pushed or pulled each tick
+const STRENGTH: f32 = 4.0;
+
+/// Buttons held and where the cursor was, as seen on a frame
+#[derive(Clone, Copy, PartialEq, Default)]
+struct Hold {
+    /// World position of the cursor while a button is held
+    center: Option<Vec2>,
+    /// 1 to attract, -1 to repel
+    sign: f32,
+}
+
+/// Particles pulled in by the left mouse button and pushed away by the right one,
+/// on top of their own movement
+#[derive(Resource)]
+pub struct MouseForce {
+    /// Reach of the cursor, in pixels
+    pub radius: f32,
+    /// Holds with the time they were first seen, until the simulation is past them.
+    /// Each tick applies those over the time it stands for, so that a slow simulation
+    /// follows where the cursor went between its ticks rather than jumping after it.
+    holds: VecDeque<(Duration, Hold)>,
+}
+
+impl MouseForce {
+    pub fn new(radius: f32) -> Self {
+        Self {
+            radius,
+            holds: VecDeque::new(),
+        }
+    }
+
+    /// Displacement of a particle at `pos` for a whole tick of `hold`, fading out towards
+    /// the radius
+    fn push(&self, hold: &Hold, pos: Vec2) -> Vec2 {
+        let Some(center) = hold.center else {
+            return Vec2::ZERO;
+        };
+        let offset = center - pos;
+        let distance = offset.length();
+        if distance >= self.radius || distance < 1.0 {
+            return Vec2::ZERO;
+        }
+
+        let falloff = 1.0 - distance / self.radius;
+        // attracted particles stop on the cursor instead of overshooting it
+        let step = (STRENGTH * falloff).min(distance);
+        offset / distance * step * hold.sign
+    }
+
+    /// Holds with a button down between `start` and `end`, with the share of that time they
+    /// last. The last one seen lasts until it changes.
+    fn spans(&self, start: Duration, end: Duration) -> Vec<(Hold, f32)> {
+        let span = end.saturating_sub(start).as_secs_f32();
+        if span == 0.0 {
+            return Vec::new();
+        }
+        let untils = self.holds.iter().skip(1).map(|&(from, _)| from);
+        self.holds
+            .iter()
+            .zip(untils.map(Some).chain([None]))
+            .filter_map(|(&(from, hold), until)| {
+                let until = until.unwrap_or(end).min(end);
+                let overlap = until.saturating_sub(from.max(start));
+                (hold.center.is_some() && !overlap.is_zero())
+                    .then(|| (hold, overlap.as_secs_f32() / span))
+            })
+            .collect()
+    }
+}
+
+pub fn mouse_pressed(force: Res<MouseForce>) -> bool {
+    force.holds.iter().any(|(_, hold)| hold.center.is_some())
+}
+
+/// Time the simulation has caught up to, behind the frame by what is left to tick
+fn simulated(time: &Time, fixed: &FixedTime) -> Duration {
+    time.elapsed().saturating_sub(fixed.accumulated())
+}
+
+pub fn track_mouse(
+    mut force: ResMut<MouseForce>,
+    mouse: Res<Input<MouseButton>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    time: Res<Time>,
+    fixed: Res<FixedTime>,
+) {
+    // the ticks have all run for this frame, the last hold before where they stopped still
+    // lasts into the next one
+    let simulated = simulated(&time, &fixed);
+    while force
+        .holds
+        .get(1)
+        .is_some_and(|&(from, _)| from <= simulated)
+    {
+        force.holds.pop_front();
+    }
+
+    let sign = match (
+        mouse.pressed(MouseButton::Left),
+        mouse.pressed(MouseButton::Right),
+    ) {
+        (true, false) => 1.0,
+        (false, true) => -1.0,
+        _ => 0.0,
+    };
+    let hold = match sign {
+        0.0 => Hold::default(),
+        sign => {
+            let (camera, transform) = camera.single();
+            let center = window
+                .single()
+                .cursor_position()
+                .and_then(|cursor| camera.viewport_to_world(transform, cursor))
+                .map(|ray| ray.origin.truncate());
+            Hold { center, sign }
+        }
+    };
+    if force.holds.back().map(|(_, last)| last) != Some(&hold) {
+        force.holds.push_back((time.elapsed(), hold));
+    }
+}
+
+/// Moves the particles around the cursor, settled ones included, which find their way
+/// back to the silhouette once let go
+pub fn push_particles(
+    mut particles: Query<&mut Transform, With<Particle>>,
+    force: Res<MouseForce>,
+    playfield: Res<Playfield>,
+    time: Res<Time>,
+    fixed: Res<FixedTime>,
+) {
+    // the tick stands for the period up to the time simulated once it is done
+    let end = simulated(&time, &fixed);
+    let spans = force.spans(end.saturating_sub(fixed.period), end);
+    if spans.is_empty() {
+        return;
+    }
+
+    for mut transform in &mut particles {
+        let pos = transform.translation.truncate();
+        let push: Vec2 = spans
+            .iter()
+            .map(|(hold, share)| force.push(hold, pos) * *share)
+            .sum();
+        if push != Vec2::ZERO {
+            transform.translation += push.extend(0.0);
+            playfield.wrap(&mut transform.translation);
+        }
+    }
+}
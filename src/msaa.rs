@@ -0,0 +1,35 @@
+use bevy::prelude::*;
+
+use crate::{
+    locale::{tr, tr_with},
+    toast::Toasts,
+};
+
+/// Anti-aliasing from a number of samples, as stored in the autosave
+pub fn from_samples(samples: u32) -> Msaa {
+    match samples {
+        1 => Msaa::Off,
+        2 => Msaa::Sample2,
+        8 => Msaa::Sample8,
+        _ => Msaa::Sample4,
+    }
+}
+
+/// Switches between no multisampling and 4x, the only levels every GPU supports.
+/// 2x and 8x can still be picked by editing the autosave.
+pub fn toggle_msaa(
+    mut msaa: ResMut<Msaa>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::A) {
+        *msaa = match *msaa {
+            Msaa::Off => Msaa::Sample4,
+            _ => Msaa::Off,
+        };
+        toasts.push(match *msaa {
+            Msaa::Off => tr("msaa-off"),
+            msaa => tr_with("msaa", &[("samples", &msaa.samples())]),
+        });
+    }
+}
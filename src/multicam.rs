@@ -0,0 +1,230 @@
+use std::{fs, time::Duration};
+
+use bevy::prelude::*;
+use rand::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    behavior::Silhouette,
+    cut::SceneCut,
+    locale::{tr, tr_with},
+    sim::Playfield,
+    toast::Toasts,
+    MainCamera,
+};
+
+const PATH: &str = "shots.ron";
+/// Shortest a shot can last before a scene cut switches to the next one, in seconds
+const MIN_SHOT: f32 = 1.5;
+/// Fraction of the distance to the centroid a close-up covers each second
+const FOLLOW: f32 = 4.0;
+
+/// How a shot frames the playfield
+#[derive(Deserialize, Clone, Debug)]
+pub enum Framing {
+    /// The whole frame
+    Full,
+    /// Follows the centroid of the silhouette
+    CloseUp { zoom: f32 },
+    /// Slides across the frame over the length of the shot,
+    /// positions going from -1 to 1 across the frame
+    Pan {
+        from: (f32, f32),
+        to: (f32, f32),
+        zoom: f32,
+    },
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Shot {
+    pub name: String,
+    pub framing: Framing,
+}
+
+#[derive(Deserialize)]
+struct ShotList {
+    interval: f32,
+    shots: Vec<Shot>,
+}
+
+impl Default for ShotList {
+    fn default() -> Self {
+        Self {
+            interval: 6.0,
+            shots: vec![
+                Shot {
+                    name: "Full".to_string(),
+                    framing: Framing::Full,
+                },
+                Shot {
+                    name: "Close-up".to_string(),
+                    framing: Framing::CloseUp { zoom: 2.0 },
+                },
+            ],
+        }
+    }
+}
+
+impl ShotList {
+    fn read() -> Self {
+        let list = fs::read_to_string(PATH)
+            .map_err(|e| e.to_string())
+            .and_then(|text| ron::from_str::<ShotList>(&text).map_err(|e| e.to_string()));
+
+        match list {
+            Ok(list) if !list.shots.is_empty() && list.interval > 0.0 => list,
+            Ok(_) => {
+                warn!("{PATH} has no shots, using the default ones");
+                default()
+            }
+            Err(e) => {
+                warn!("couldn't load {PATH}: {e}");
+                default()
+            }
+        }
+    }
+}
+
+/// Virtual camera operator, cutting between framings of the playfield
+#[derive(Resource)]
+pub struct Multicam {
+    pub enabled: bool,
+    shots: Vec<Shot>,
+    active: usize,
+    /// Time spent on the active shot, finishes when it is time to cut
+    timer: Timer,
+    /// Whether the multicam was on before a shot got pinned, no cuts happen while pinned
+    pinned: Option<bool>,
+}
+
+impl Multicam {
+    /// Holds the camera on the shot with this name, returns false if there is none
+    pub fn pin(&mut self, name: &str) -> bool {
+        let Some(idx) = self.shots.iter().position(|shot| shot.name == name) else {
+            return false;
+        };
+        self.pinned.get_or_insert(self.enabled);
+        self.enabled = true;
+        self.active = idx;
+        self.timer.reset();
+        true
+    }
+
+    pub fn unpin(&mut self) {
+        if let Some(enabled) = self.pinned.take() {
+            self.enabled = enabled;
+            self.timer.reset();
+        }
+    }
+}
+
+pub fn load_shots(mut commands: Commands) {
+    let ShotList { interval, shots } = ShotList::read();
+
+    commands.insert_resource(Multicam {
+        enabled: false,
+        shots,
+        active: 0,
+        timer: Timer::new(Duration::from_secs_f32(interval), TimerMode::Once),
+        pinned: None,
+    });
+}
+
+pub fn multicam_enabled(multicam: Res<Multicam>) -> bool {
+    multicam.enabled
+}
+
+pub fn toggle_multicam(
+    mut multicam: ResMut<Multicam>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if !keyboard.just_released(KeyCode::V) {
+        return;
+    }
+
+    // taking over from a pinned shot
+    multicam.pinned = None;
+    multicam.enabled = !multicam.enabled;
+    if multicam.enabled {
+        multicam.timer.reset();
+        let shot = &multicam.shots[multicam.active];
+        toasts.push(tr_with("multicam", &[("shot", &shot.name)]));
+    } else {
+        toasts.push(tr("multicam-off"));
+    }
+}
+
+/// Goes back to showing the whole frame once the multicam turns off
+pub fn release_camera(
+    multicam: Res<Multicam>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    if !multicam.is_changed() || multicam.enabled {
+        return;
+    }
+
+    let (mut transform, mut projection) = camera.single_mut();
+    transform.translation.x = 0.0;
+    transform.translation.y = 0.0;
+    projection.scale = 1.0;
+}
+
+/// Switches shots on a timer, and on scene cuts in the video
+pub fn cut_shots(mut multicam: ResMut<Multicam>, cut: Res<SceneCut>, time: Res<Time>) {
+    multicam.timer.tick(time.delta());
+    if multicam.pinned.is_some() {
+        return;
+    }
+
+    let scene_cut = cut.just_cut() && multicam.timer.elapsed_secs() > MIN_SHOT;
+    if !multicam.timer.finished() && !scene_cut {
+        return;
+    }
+
+    // never cut to the same shot
+    let count = multicam.shots.len();
+    if count > 1 {
+        let offset = thread_rng().gen_range(1..count);
+        multicam.active = (multicam.active + offset) % count;
+    }
+    multicam.timer.reset();
+    debug!("cut to {}", multicam.shots[multicam.active].name);
+}
+
+pub fn frame_camera(
+    multicam: Res<Multicam>,
+    silhouette: Res<Silhouette>,
+    playfield: Res<Playfield>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    time: Res<Time>,
+) {
+    let (mut transform, mut projection) = camera.single_mut();
+    let half_size = playfield.half_size();
+
+    let (target, zoom) = match multicam.shots[multicam.active].framing {
+        Framing::Full => (Vec2::ZERO, 1.0),
+        Framing::CloseUp { zoom } => (silhouette.centroid.unwrap_or(Vec2::ZERO), zoom),
+        Framing::Pan { from, to, zoom } => {
+            let t = multicam.timer.percent();
+            (Vec2::from(from).lerp(Vec2::from(to), t) * half_size, zoom)
+        }
+    };
+    let zoom = zoom.max(1.0);
+
+    // keep the view inside the frame
+    let bound = half_size * (1.0 - 1.0 / zoom);
+    let target = target.clamp(-bound, bound);
+
+    // the timer was just reset by a cut
+    let position = if multicam.timer.elapsed().is_zero() {
+        target
+    } else {
+        let position = transform.translation.truncate();
+        // ease towards moving targets
+        position.lerp(target, (FOLLOW * time.delta_seconds()).min(1.0))
+    };
+
+    transform.translation = position.extend(transform.translation.z);
+    projection.scale = 1.0 / zoom;
+}
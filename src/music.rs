@@ -0,0 +1,102 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bevy::{
+    audio::{Decodable, Sample, Source},
+    prelude::*,
+    reflect::TypeUuid,
+};
+
+/// Soundtrack that starts `start` into the track, so audio can resume where playback left off
+#[derive(TypeUuid)]
+#[uuid = "e617ed54-a8ea-4552-9b0c-872f262fa07b"]
+pub struct Music {
+    pub source: AudioSource,
+    pub start: Duration,
+    pub clock: Arc<AudioClock>,
+}
+
+impl Decodable for Music {
+    type DecoderItem = <AudioSource as Decodable>::DecoderItem;
+    type Decoder = Box<dyn Source<Item = Self::DecoderItem> + Send>;
+
+    fn decoder(&self) -> Self::Decoder {
+        let decoder = self.source.decoder().skip_duration(self.start);
+        let rate = decoder.sample_rate() * decoder.channels() as u32;
+        self.clock.rate.store(rate, Ordering::Relaxed);
+        Box::new(Counted {
+            inner: decoder,
+            clock: self.clock.clone(),
+        })
+    }
+}
+
+/// How far the audio output has gotten through the soundtrack, counted in samples
+#[derive(Default)]
+pub struct AudioClock {
+    start: Duration,
+    samples: AtomicU64,
+    /// Samples per second over all channels, 0 until the decoder starts
+    rate: AtomicU32,
+}
+
+impl AudioClock {
+    pub fn new(start: Duration) -> Self {
+        Self { start, ..default() }
+    }
+
+    /// Position in the track of the last sample played, once playback has started
+    pub fn position(&self) -> Option<Duration> {
+        let rate = self.rate.load(Ordering::Relaxed);
+        let samples = self.samples.load(Ordering::Relaxed);
+        if rate == 0 || samples == 0 {
+            return None;
+        }
+        Some(self.start + Duration::from_secs_f64(samples as f64 / rate as f64))
+    }
+}
+
+/// Counts the samples pulled by the audio output
+struct Counted<S> {
+    inner: S,
+    clock: Arc<AudioClock>,
+}
+
+impl<S: Source> Iterator for Counted<S>
+where
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.inner.next()?;
+        self.clock.samples.fetch_add(1, Ordering::Relaxed);
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for Counted<S>
+where
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
@@ -0,0 +1,188 @@
+//! Run-length encoded archive of the video's frames, written by the `pack` binary.
+//!
+//! The archive holds the grayscale pixels of every frame one after the other, each as
+//! `(run, value)` byte pairs. Its sidecar holds the dimensions and where each frame starts.
+
+use serde::{Deserialize, Serialize};
+
+/// Archive file, in the assets folder
+pub const ARCHIVE: &str = "frames.pack";
+/// Sidecar file, in the assets folder
+pub const METADATA: &str = "frames.ron";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Metadata {
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+    /// Frame `i` spans `offsets[i]..offsets[i + 1]` in the archive
+    pub offsets: Vec<u64>,
+}
+
+impl Metadata {
+    pub fn frames(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    fn pixels(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+}
+
+/// Appends the run-length encoding of a frame to `out`
+pub fn encode(pixels: &[u8], out: &mut Vec<u8>) {
+    let mut pixels = pixels.iter().copied().peekable();
+    while let Some(value) = pixels.next() {
+        let mut run = 1u8;
+        while run < u8::MAX && pixels.next_if_eq(&value).is_some() {
+            run += 1;
+        }
+        out.extend([run, value]);
+    }
+}
+
+/// Decodes a run-length encoded frame of `len` pixels
+pub fn decode(data: &[u8], len: usize) -> Result<Vec<u8>, String> {
+    if !data.len().is_multiple_of(2) {
+        return Err("truncated run".to_string());
+    }
+
+    let mut pixels = Vec::with_capacity(len);
+    for pair in data.chunks_exact(2) {
+        let (run, value) = (pair[0] as usize, pair[1]);
+        pixels.resize(pixels.len() + run, value);
+    }
+
+    if pixels.len() != len {
+        return Err(format!("expected {len} pixels, got {}", pixels.len()));
+    }
+    Ok(pixels)
+}
+
+/// An archive loaded in memory
+pub struct Pack {
+    pub metadata: Metadata,
+    data: Vec<u8>,
+}
+
+impl Pack {
+    pub fn new(metadata: Metadata, data: Vec<u8>) -> Result<Self, String> {
+        let sorted = metadata.offsets.windows(2).all(|w| w[0] <= w[1]);
+        let end = metadata.offsets.last().copied().unwrap_or(0);
+        if !sorted || end > data.len() as u64 {
+            return Err("frame offsets don't match the archive".to_string());
+        }
+
+        Ok(Self { metadata, data })
+    }
+
+    /// Grayscale pixels of a frame, row major from the top left
+    pub fn frame(&self, idx: usize) -> Result<Vec<u8>, String> {
+        let offsets = &self.metadata.offsets;
+        let (Some(&start), Some(&end)) = (offsets.get(idx), offsets.get(idx + 1)) else {
+            return Err(format!("no frame {idx}"));
+        };
+
+        decode(
+            &self.data[start as usize..end as usize],
+            self.metadata.pixels(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(offsets: Vec<u64>) -> Metadata {
+        Metadata {
+            width: 4,
+            height: 2,
+            fps: 30.0,
+            offsets,
+        }
+    }
+
+    /// Archive of the frames of `metadata(...)`'s size
+    fn archive(frames: &[[u8; 8]]) -> (Vec<u64>, Vec<u8>) {
+        let mut data = Vec::new();
+        let mut offsets = vec![0];
+        for frame in frames {
+            encode(frame, &mut data);
+            offsets.push(data.len() as u64);
+        }
+        (offsets, data)
+    }
+
+    #[test]
+    fn round_trip() {
+        let pixels: Vec<u8> = [0, 0, 0, 255, 255, 7, 0, 0].repeat(3);
+        let mut data = Vec::new();
+        encode(&pixels, &mut data);
+        assert_eq!(decode(&data, pixels.len()).unwrap(), pixels);
+    }
+
+    #[test]
+    fn long_runs_split() {
+        let pixels = vec![9; 600];
+        let mut data = Vec::new();
+        encode(&pixels, &mut data);
+        assert_eq!(data, [255, 9, 255, 9, 90, 9]);
+        assert_eq!(decode(&data, 600).unwrap(), pixels);
+    }
+
+    #[test]
+    fn empty_frame() {
+        let mut data = Vec::new();
+        encode(&[], &mut data);
+        assert!(data.is_empty());
+        assert_eq!(decode(&data, 0).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn decode_rejects_bad_runs() {
+        assert!(decode(&[3, 0, 2], 3).is_err());
+        assert!(decode(&[3, 0], 4).is_err());
+        assert!(decode(&[3, 0, 2, 1], 4).is_err());
+    }
+
+    #[test]
+    fn reads_frames() {
+        let frames = [[0; 8], [0, 0, 255, 255, 0, 0, 255, 255]];
+        let (offsets, data) = archive(&frames);
+        let pack = Pack::new(metadata(offsets), data).unwrap();
+
+        assert_eq!(pack.metadata.frames(), 2);
+        assert_eq!(pack.frame(0).unwrap(), frames[0]);
+        assert_eq!(pack.frame(1).unwrap(), frames[1]);
+        assert!(pack.frame(2).is_err());
+    }
+
+    #[test]
+    fn rejects_unsorted_offsets() {
+        let (_, data) = archive(&[[0; 8], [255; 8]]);
+        assert!(Pack::new(metadata(vec![0, 2, 1]), data).is_err());
+    }
+
+    #[test]
+    fn rejects_offsets_past_the_archive() {
+        let (mut offsets, data) = archive(&[[0; 8]]);
+        *offsets.last_mut().unwrap() += 1;
+        assert!(Pack::new(metadata(offsets), data).is_err());
+    }
+
+    #[test]
+    fn frame_of_the_wrong_size() {
+        // a 4x2 frame's worth of pixels read as 4x3
+        let (offsets, data) = archive(&[[0; 8]]);
+        let pack = Pack::new(
+            Metadata {
+                height: 3,
+                ..metadata(offsets)
+            },
+            data,
+        )
+        .unwrap();
+        assert!(pack.frame(0).is_err());
+    }
+}
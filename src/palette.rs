@@ -0,0 +1,299 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locale::{tr, tr_with},
+    texture::ParticleTexture,
+    toast::Toasts,
+};
+
+/// Gradient used to color particles by how long they've been moving
+#[derive(Resource, Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Palette {
+    /// Every particle stays black
+    #[default]
+    Off,
+    /// Black to red
+    Classic,
+    /// Black to orange, avoids the red/green axis
+    Deuteranopia,
+    /// Black to blue, reds look too dark with protanopia
+    Protanopia,
+    /// Black to vermillion, avoids the blue/yellow axis
+    Tritanopia,
+    /// Hard black to magenta switch
+    HighContrast,
+    /// Black to light gray
+    Grayscale,
+    /// Black through red and orange to yellow
+    Fire,
+    /// Perceptually uniform purple through teal to yellow
+    Viridis,
+    /// Two-color gradient picked in `colors.ron`
+    Custom,
+}
+
+const FIRE: [[u8; 3]; 4] = [
+    [0x00, 0x00, 0x00],
+    [0xB0, 0x10, 0x00],
+    [0xFF, 0x80, 0x00],
+    [0xFF, 0xE0, 0x40],
+];
+
+const VIRIDIS: [[u8; 3]; 5] = [
+    [0x44, 0x01, 0x54],
+    [0x3B, 0x52, 0x8B],
+    [0x21, 0x91, 0x8C],
+    [0x5E, 0xC9, 0x62],
+    [0xFD, 0xE7, 0x25],
+];
+
+/// Color at `t` along evenly spaced stops
+fn gradient(stops: &[[u8; 3]], t: f32) -> Color {
+    let pos = t * (stops.len() - 1) as f32;
+    let idx = (pos as usize).min(stops.len() - 2);
+    let frac = pos - idx as f32;
+    let [a, b] = [stops[idx], stops[idx + 1]]
+        .map(|[r, g, b]| Vec3::new(r as f32, g as f32, b as f32) / 255.0);
+    let c = a.lerp(b, frac);
+    Color::rgb(c.x, c.y, c.z)
+}
+
+impl Palette {
+    const ALL: [Palette; 10] = [
+        Palette::Off,
+        Palette::Classic,
+        Palette::Deuteranopia,
+        Palette::Protanopia,
+        Palette::Tritanopia,
+        Palette::HighContrast,
+        Palette::Grayscale,
+        Palette::Fire,
+        Palette::Viridis,
+        Palette::Custom,
+    ];
+
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&p| p == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    pub fn previous(self) -> Self {
+        let idx = Self::ALL.iter().position(|&p| p == self).unwrap_or(0);
+        Self::ALL[(idx + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    /// Color at `t` in `[0, 1]`, where 0 is a settled particle
+    pub fn color(self, t: f32, scheme: &ColorScheme) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let moving = match self {
+            Palette::Off => return Color::BLACK,
+            Palette::Classic => Color::rgb(1.0, 0.0, 0.0),
+            Palette::Deuteranopia => Color::rgb_u8(0xE6, 0x9F, 0x00),
+            Palette::Protanopia => Color::rgb_u8(0x00, 0x72, 0xB2),
+            Palette::Tritanopia => Color::rgb_u8(0xD5, 0x5E, 0x00),
+            Palette::HighContrast => {
+                return if t < 0.5 {
+                    Color::BLACK
+                } else {
+                    Color::rgb(1.0, 0.0, 1.0)
+                }
+            }
+            Palette::Grayscale => Color::rgb(0.75, 0.75, 0.75),
+            Palette::Fire => return gradient(&FIRE, t),
+            Palette::Viridis => return gradient(&VIRIDIS, t),
+            Palette::Custom => {
+                let [settled, moving] = [scheme.settled, scheme.moving].map(Vec3::from);
+                let c = settled.lerp(moving, t);
+                return Color::rgb(c.x, c.y, c.z);
+            }
+        };
+
+        Color::rgb(moving.r() * t, moving.g() * t, moving.b() * t)
+    }
+}
+
+pub const COLORS_FILE: &str = "colors.ron";
+
+/// How particles age through the palette, and the ends of the custom gradient, from `colors.ron`
+#[derive(Resource, Clone, PartialEq, Debug, Deserialize)]
+#[serde(default)]
+pub struct ColorScheme {
+    /// Frames a moving particle takes to get about two thirds of the way along the palette
+    pub decay: f32,
+    /// RGB of settled particles in the custom palette, from 0 to 1
+    pub settled: [f32; 3],
+    /// RGB the custom palette fades to as particles keep moving
+    pub moving: [f32; 3],
+    /// Shape the particles are drawn with, switched to whenever the file changes
+    pub texture: Option<ParticleTexture>,
+}
+
+impl Default for ColorScheme {
+    fn default() -> Self {
+        Self {
+            decay: 12.0,
+            settled: [0.0, 0.0, 0.0],
+            moving: [0.0, 0.8, 0.6],
+            texture: None,
+        }
+    }
+}
+
+impl ColorScheme {
+    /// Position along the palette of a particle moving for `frames` frames
+    pub fn age(&self, frames: usize) -> f32 {
+        // negative exponential for color transition
+        1.0 - (-(frames as f32) / self.decay.max(f32::EPSILON)).exp()
+    }
+}
+
+/// Whether particles change color the longer they move, toggled with `D`
+#[derive(Resource)]
+pub struct Aging(pub bool);
+
+impl Default for Aging {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+pub fn toggle_aging(
+    mut aging: ResMut<Aging>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::D) {
+        aging.0 = !aging.0;
+        toasts.push(if aging.0 {
+            tr("aging-on")
+        } else {
+            tr("aging-off")
+        });
+    }
+}
+
+/// Colors by frames spent moving, so coloring tens of thousands of particles is a lookup
+/// instead of an exponential and a gamma curve each
+#[derive(Default)]
+pub struct AgeColors(Vec<Color>);
+
+impl AgeColors {
+    /// Past this many decays particles are within a thousandth of the moving color
+    const SPAN: f32 = 7.0;
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// `boost` multiplies the colors of the particles that moved the longest, above 1 to
+    /// make them brighter than white for the bloom
+    pub fn rebuild(
+        &mut self,
+        palette: Palette,
+        scheme: &ColorScheme,
+        gamma: Gamma,
+        aging: bool,
+        boost: f32,
+    ) {
+        let len = if aging {
+            (scheme.decay.max(0.0) * Self::SPAN).ceil() as usize + 1
+        } else {
+            1
+        };
+        self.0.clear();
+        self.0.extend((0..len).map(|frames| {
+            let t = scheme.age(frames);
+            let [r, g, b, a] = gamma.apply(palette.color(t, scheme)).as_rgba_f32();
+            let boost = 1.0 + (boost - 1.0) * t;
+            Color::rgba(r * boost, g * boost, b * boost, a)
+        }));
+    }
+
+    /// Color of a particle moving for `frames` frames
+    pub fn get(&self, frames: usize) -> Color {
+        self.0[frames.min(self.0.len() - 1)]
+    }
+}
+
+impl ColorScheme {
+    /// Reads `colors.ron`, `None` when it can't be read or parsed
+    pub fn read() -> Option<Self> {
+        let text = match fs::read_to_string(COLORS_FILE) {
+            Ok(text) => text,
+            Err(e) => {
+                info!("using the default colors, couldn't read {COLORS_FILE}: {e}");
+                return None;
+            }
+        };
+
+        ron::from_str(&text)
+            .map_err(|e| warn!("couldn't parse {COLORS_FILE}: {e}"))
+            .ok()
+    }
+}
+
+pub fn load_colors(mut scheme: ResMut<ColorScheme>) {
+    if let Some(loaded) = ColorScheme::read() {
+        *scheme = loaded;
+    }
+}
+
+/// Exponent on the particle colors, above 1 to deepen gradients that look washed out
+/// on wide-gamut displays and projectors, which stretch sRGB colors
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+pub struct Gamma(pub f32);
+
+impl Default for Gamma {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+impl Gamma {
+    const STEP: f32 = 0.1;
+    const MIN: f32 = 0.5;
+    const MAX: f32 = 2.5;
+
+    pub fn apply(self, color: Color) -> Color {
+        if self.0 == 1.0 {
+            return color;
+        }
+        let [r, g, b, a] = color.as_rgba_f32();
+        Color::rgba(r.powf(self.0), g.powf(self.0), b.powf(self.0), a)
+    }
+}
+
+pub fn adjust_gamma(
+    mut gamma: ResMut<Gamma>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    let step = if keyboard.just_released(KeyCode::Equals) {
+        Gamma::STEP
+    } else if keyboard.just_released(KeyCode::Minus) {
+        -Gamma::STEP
+    } else {
+        return;
+    };
+
+    // rounded so repeated steps don't drift
+    gamma.0 = ((gamma.0 + step).clamp(Gamma::MIN, Gamma::MAX) * 10.0).round() / 10.0;
+    toasts.push(tr_with("gamma", &[("gamma", &format!("{:.1}", gamma.0))]));
+}
+
+pub fn cycle_palette(
+    mut palette: ResMut<Palette>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::C) {
+        *palette = palette.next();
+        toasts.push(tr_with(
+            "palette",
+            &[("palette", &format!("{:?}", *palette))],
+        ));
+    }
+}
@@ -0,0 +1,375 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    config::Config, decode::FrameStore, locale::tr, palette::Palette, spawner::ParticleSpawner,
+    spectrum::Spectrum, speed::PlaybackSpeed, timestamp, toast::UiFont, MusicPlayer, Player, State,
+};
+
+/// Step of the particle fraction buttons
+const PARTICLES_STEP: f32 = 0.1;
+/// Factor applied by the jitter buttons
+const JITTER_STEP: f32 = 1.25;
+const MIN_JITTER: f32 = 0.1;
+const MAX_JITTER: f32 = 4.0;
+const BUTTON: Color = Color::rgba(1.0, 1.0, 1.0, 0.15);
+const BUTTON_HOVERED: Color = Color::rgba(1.0, 1.0, 1.0, 0.3);
+/// Seconds a seek with `Shift` held can move to land on an onset of the soundtrack
+const SNAP_SECS: f64 = 0.25;
+
+/// Settings picked in the panel, on top of the automated ones
+#[derive(Resource)]
+pub struct Tuning {
+    /// Fraction of the particles shown, from 0 to 1
+    pub particles: f32,
+    /// Multiplier on the jitter of moving particles
+    pub jitter: f32,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            particles: 1.0,
+            jitter: 1.0,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Panel;
+
+/// What a panel button does when clicked
+#[derive(Component, Clone, Copy)]
+pub enum PanelButton {
+    TogglePlay,
+    Slower,
+    Faster,
+    FewerParticles,
+    MoreParticles,
+    RemoveParticles,
+    AddParticles,
+    LessJitter,
+    MoreJitter,
+    PreviousPalette,
+    NextPalette,
+}
+
+/// Value shown in the panel
+#[derive(Component, Clone, Copy)]
+pub enum PanelLabel {
+    Play,
+    Position,
+    Speed,
+    Particles,
+    Count,
+    Jitter,
+    Palette,
+}
+
+/// Clickable timeline, seeking to where it was clicked
+#[derive(Component)]
+pub struct SeekBar;
+
+#[derive(Component)]
+pub struct SeekFill;
+
+/// Thumbnail of the hovered spot of the timeline, under it
+#[derive(Component)]
+pub struct SeekPreview;
+
+pub fn setup_panel(mut commands: Commands, font: Res<UiFont>) {
+    let style = TextStyle {
+        font: font.0.clone(),
+        font_size: 14.0,
+        color: Color::WHITE,
+    };
+    let row = || NodeBundle {
+        style: Style {
+            align_items: AlignItems::Center,
+            gap: Size::width(Val::Px(8.0)),
+            ..default()
+        },
+        ..default()
+    };
+    let button = |parent: &mut ChildBuilder, action, text: &str| {
+        parent
+            .spawn((
+                action,
+                ButtonBundle {
+                    style: Style {
+                        padding: UiRect::new(
+                            Val::Px(6.0),
+                            Val::Px(6.0),
+                            Val::Px(2.0),
+                            Val::Px(2.0),
+                        ),
+                        ..default()
+                    },
+                    background_color: BUTTON.into(),
+                    ..default()
+                },
+            ))
+            .with_children(|button| {
+                button.spawn(TextBundle::from_section(text, style.clone()));
+            });
+    };
+    // a setting with buttons to lower and raise it on each side
+    let stepper = |parent: &mut ChildBuilder, name: &str, label, buttons: [PanelButton; 2]| {
+        parent.spawn(row()).with_children(|row| {
+            row.spawn(TextBundle::from_section(name, style.clone()));
+            button(row, buttons[0], "<");
+            row.spawn((label, TextBundle::from_section("", style.clone())));
+            button(row, buttons[1], ">");
+        });
+    };
+
+    commands
+        .spawn((
+            Panel,
+            NodeBundle {
+                style: Style {
+                    display: Display::None,
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        right: Val::Px(8.0),
+                        top: Val::Px(8.0),
+                        ..default()
+                    },
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(8.0)),
+                    gap: Size::height(Val::Px(6.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+                ..default()
+            },
+        ))
+        .with_children(|panel| {
+            panel.spawn(row()).with_children(|row| {
+                button(row, PanelButton::TogglePlay, &tr("panel-play-pause"));
+                row.spawn((
+                    PanelLabel::Play,
+                    TextBundle::from_section("", style.clone()),
+                ));
+            });
+            panel.spawn(row()).with_children(|row| {
+                row.spawn((
+                    SeekBar,
+                    ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(200.0), Val::Px(8.0)),
+                            ..default()
+                        },
+                        background_color: Color::DARK_GRAY.into(),
+                        ..default()
+                    },
+                ))
+                .with_children(|bar| {
+                    bar.spawn((
+                        SeekFill,
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            background_color: Color::WHITE.into(),
+                            ..default()
+                        },
+                    ));
+                    bar.spawn((
+                        SeekPreview,
+                        ImageBundle {
+                            style: Style {
+                                display: Display::None,
+                                position_type: PositionType::Absolute,
+                                position: UiRect {
+                                    top: Val::Px(12.0),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            ..default()
+                        },
+                    ));
+                });
+                row.spawn((
+                    PanelLabel::Position,
+                    TextBundle::from_section("", style.clone()),
+                ));
+            });
+            stepper(
+                panel,
+                &tr("panel-speed"),
+                PanelLabel::Speed,
+                [PanelButton::Slower, PanelButton::Faster],
+            );
+            stepper(
+                panel,
+                &tr("panel-particles"),
+                PanelLabel::Particles,
+                [PanelButton::FewerParticles, PanelButton::MoreParticles],
+            );
+            stepper(
+                panel,
+                &tr("panel-count"),
+                PanelLabel::Count,
+                [PanelButton::RemoveParticles, PanelButton::AddParticles],
+            );
+            stepper(
+                panel,
+                &tr("panel-jitter"),
+                PanelLabel::Jitter,
+                [PanelButton::LessJitter, PanelButton::MoreJitter],
+            );
+            stepper(
+                panel,
+                &tr("panel-palette"),
+                PanelLabel::Palette,
+                [PanelButton::PreviousPalette, PanelButton::NextPalette],
+            );
+        });
+}
+
+pub fn toggle_panel(mut panel: Query<&mut Style, With<Panel>>, keyboard: Res<Input<KeyCode>>) {
+    if keyboard.just_released(KeyCode::Tab) {
+        let mut style = panel.single_mut();
+        style.display = match style.display {
+            Display::None => Display::Flex,
+            Display::Flex => Display::None,
+        };
+    }
+}
+
+pub fn panel_visible(panel: Query<&Style, With<Panel>>) -> bool {
+    panel.single().display != Display::None
+}
+
+#[allow(clippy::type_complexity)]
+pub fn press_panel_buttons(
+    mut buttons: Query<
+        (&PanelButton, &Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, Without<SeekBar>),
+    >,
+    mut state: ResMut<State>,
+    mut speed: ResMut<PlaybackSpeed>,
+    mut tuning: ResMut<Tuning>,
+    mut spawner: ResMut<ParticleSpawner>,
+    mut palette: ResMut<Palette>,
+) {
+    for (action, interaction, mut color) in &mut buttons {
+        *color = match interaction {
+            Interaction::None => BUTTON,
+            _ => BUTTON_HOVERED,
+        }
+        .into();
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match action {
+            PanelButton::TogglePlay => {
+                *state = match *state {
+                    State::Playing => State::Paused,
+                    State::Paused => State::Playing,
+                }
+            }
+            PanelButton::Slower => *speed = speed.slower(),
+            PanelButton::Faster => *speed = speed.faster(),
+            PanelButton::FewerParticles => {
+                tuning.particles = (tuning.particles - PARTICLES_STEP).max(PARTICLES_STEP)
+            }
+            PanelButton::MoreParticles => {
+                tuning.particles = (tuning.particles + PARTICLES_STEP).min(1.0)
+            }
+            PanelButton::RemoveParticles => spawner.fewer(),
+            PanelButton::AddParticles => spawner.more(),
+            PanelButton::LessJitter => {
+                tuning.jitter = (tuning.jitter / JITTER_STEP).max(MIN_JITTER)
+            }
+            PanelButton::MoreJitter => {
+                tuning.jitter = (tuning.jitter * JITTER_STEP).min(MAX_JITTER)
+            }
+            PanelButton::PreviousPalette => *palette = palette.previous(),
+            PanelButton::NextPalette => *palette = palette.next(),
+        }
+    }
+}
+
+/// Where the cursor is along the timeline, from 0 at its start to 1 at its end
+pub fn bar_fraction(node: &Node, transform: &GlobalTransform, cursor: Vec2) -> f32 {
+    // the bar's transform is at its center
+    let left = transform.translation().x - node.size().x / 2.0;
+    ((cursor.x - left) / node.size().x).clamp(0.0, 1.0)
+}
+
+/// Seeks to the clicked spot of the timeline, or with `Shift` held to the closest onset of
+/// the soundtrack near it, for loops and chapters to start on the music
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn click_seek_bar(
+    bar: Query<(&Interaction, &Node, &GlobalTransform), (With<SeekBar>, Changed<Interaction>)>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut player: Query<&mut Player>,
+    mut music_player: ResMut<MusicPlayer>,
+    sinks: Res<Assets<AudioSink>>,
+    store: Option<Res<FrameStore>>,
+    spectrum: Res<Spectrum>,
+    config: Res<Config>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    let Ok((Interaction::Clicked, node, transform)) = bar.get_single() else {
+        return;
+    };
+    let (Some(cursor), Some(store)) = (window.single().cursor_position(), store) else {
+        return;
+    };
+
+    let fraction = bar_fraction(node, transform, cursor);
+    let frames = store.frames().unwrap_or(config.frames);
+    let mut frame = ((frames as f32 * fraction) as usize).min(frames.saturating_sub(1));
+    if keyboard.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+        let reach = (SNAP_SECS * config.fps).round() as usize;
+        frame = spectrum.snap(frame, reach).min(frames.saturating_sub(1));
+    }
+
+    *player.single_mut() = Player::at(frame, config.fps);
+    music_player.stop(&sinks);
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_panel(
+    mut labels: Query<(&PanelLabel, &mut Text)>,
+    mut fill: Query<&mut Style, With<SeekFill>>,
+    player: Query<&Player>,
+    store: Option<Res<FrameStore>>,
+    state: Res<State>,
+    speed: Res<PlaybackSpeed>,
+    tuning: Res<Tuning>,
+    spawner: Res<ParticleSpawner>,
+    palette: Res<Palette>,
+    config: Res<Config>,
+) {
+    let player = player.single();
+    let frames = store
+        .and_then(|store| store.frames())
+        .unwrap_or(config.frames)
+        .max(1);
+    fill.single_mut().size.width = Val::Percent(player.play_index as f32 / frames as f32 * 100.0);
+
+    for (label, mut text) in &mut labels {
+        text.sections[0].value = match label {
+            PanelLabel::Play => match *state {
+                State::Playing => tr("playing"),
+                State::Paused => tr("paused"),
+            },
+            PanelLabel::Position => format!(
+                "{} / {}",
+                timestamp(player.play_index, config.fps),
+                timestamp(frames, config.fps)
+            ),
+            PanelLabel::Speed => format!("{}x", speed.0),
+            PanelLabel::Particles => format!("{:.0}%", tuning.particles * 100.0),
+            PanelLabel::Count => spawner.target.to_string(),
+            PanelLabel::Jitter => format!("{:.2}x", tuning.jitter),
+            PanelLabel::Palette => format!("{:?}", *palette),
+        };
+    }
+}
@@ -0,0 +1,80 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::{
+    locale::{tr, tr_with},
+    sim::JITTER,
+    toast::Toasts,
+};
+
+/// Amplitudes to pick from, in pixels
+const LEVELS: [f32; 4] = [0.0, 0.5, 1.0, 2.0];
+
+/// How far particles sway around their position over time, in pixels. Settled particles
+/// breathe in place, and wanderers jitter more or less depending on their phase.
+#[derive(Resource, Default)]
+pub struct Breathing(pub f32);
+
+/// Each particle's own rhythm, so they don't all move in sync
+#[derive(Component)]
+pub struct Phase {
+    phase: f32,
+    /// Radians per second
+    frequency: f32,
+    direction: Vec2,
+    /// Sway applied on the last tick, taken back before applying the next one
+    offset: Vec2,
+}
+
+impl Phase {
+    pub fn new(rng: &mut impl Rng) -> Self {
+        Self {
+            phase: rng.gen_range(0.0..TAU),
+            frequency: rng.gen_range(0.5..1.5) * TAU,
+            direction: Vec2::from_angle(rng.gen_range(0.0..TAU)),
+            offset: Vec2::ZERO,
+        }
+    }
+
+    fn wave(&self, time: f32) -> f32 {
+        (self.phase + self.frequency * time).sin()
+    }
+
+    /// Multiplier on the jitter of a wandering particle
+    pub fn jitter_scale(&self, time: f32, amplitude: f32) -> f32 {
+        1.0 + self.wave(time) * amplitude / JITTER
+    }
+}
+
+pub fn cycle_breathing(
+    mut breathing: ResMut<Breathing>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::P) {
+        let next = LEVELS.iter().position(|&level| level > breathing.0);
+        breathing.0 = next.map_or(LEVELS[0], |i| LEVELS[i]);
+        toasts.push(if breathing.0 > 0.0 {
+            tr_with("sway", &[("px", &breathing.0)])
+        } else {
+            tr("sway-off")
+        });
+    }
+}
+
+/// Sways particles sinusoidally around where they are, without drifting away from it
+pub fn breathe(
+    mut particles: Query<(&mut Transform, &mut Phase)>,
+    breathing: Res<Breathing>,
+    time: Res<Time>,
+) {
+    let t = time.elapsed_seconds_wrapped();
+
+    for (mut transform, mut phase) in &mut particles {
+        let offset = phase.direction * breathing.0 * phase.wave(t);
+        transform.translation += (offset - phase.offset).extend(0.0);
+        phase.offset = offset;
+    }
+}
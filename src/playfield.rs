@@ -0,0 +1,128 @@
+use bevy::{
+    prelude::*,
+    render::camera::ScalingMode,
+    window::{PrimaryWindow, WindowMode},
+    winit::WinitWindows,
+};
+
+use crate::{
+    locale::{tr, tr_with},
+    sim::Playfield,
+    toast::Toasts,
+    CurrentFrame, MainCamera, Particle,
+};
+
+/// Largest part of the monitor a resized window may cover
+const MAX_MONITOR_FRACTION: f32 = 0.9;
+
+/// White area behind the particles, the clear color shows as letterboxing around it
+#[derive(Component)]
+pub struct Backdrop;
+
+/// Resizes the playfield when a frame with new dimensions comes in, keeping the
+/// particles at the same relative positions and the whole frame in view
+pub fn fit_playfield(
+    current: Res<CurrentFrame>,
+    images: Res<Assets<Image>>,
+    mut playfield: ResMut<Playfield>,
+    mut particles: Query<&mut Transform, With<Particle>>,
+    mut backdrop: Query<&mut Sprite, With<Backdrop>>,
+    mut projection: Query<&mut OrthographicProjection, With<MainCamera>>,
+    mut toasts: ResMut<Toasts>,
+) {
+    let Some(image) = images.get(&current.front.image) else {
+        return;
+    };
+    let size = image.size().as_uvec2();
+    if size == playfield.size {
+        return;
+    }
+
+    let scale = size.as_vec2() / playfield.size.as_vec2();
+    for mut particle in &mut particles {
+        particle.translation =
+            (particle.translation.truncate() * scale).extend(particle.translation.z);
+    }
+
+    playfield.size = size;
+    backdrop.single_mut().custom_size = Some(size.as_vec2());
+    projection.single_mut().scaling_mode = ScalingMode::AutoMin {
+        min_width: size.x as f32,
+        min_height: size.y as f32,
+    };
+
+    toasts.push(tr_with(
+        "playfield-resized",
+        &[("width", &size.x), ("height", &size.y)],
+    ));
+}
+
+/// Whether the window follows the aspect ratio of the source, instead of letterboxing it
+#[derive(Resource)]
+pub struct WindowFit(pub bool);
+
+impl Default for WindowFit {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+pub fn toggle_window_fit(
+    mut fit: ResMut<WindowFit>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::W) {
+        fit.0 = !fit.0;
+        toasts.push(if fit.0 { tr("fit-on") } else { tr("fit-off") });
+    }
+}
+
+/// Switches between the window and borderless fullscreen, where the camera letterboxes
+/// or pillarboxes the playfield to the monitor like in a window of that shape
+pub fn toggle_fullscreen(
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if !keyboard.just_released(KeyCode::F11) {
+        return;
+    }
+    let mut window = window.single_mut();
+    window.mode = match window.mode {
+        WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+        _ => WindowMode::Windowed,
+    };
+}
+
+/// Reshapes the window to the playfield's aspect ratio when it changes, keeping about
+/// the same height and staying within the monitor. Fullscreen keeps the monitor's shape.
+pub fn fit_window(
+    playfield: Res<Playfield>,
+    fit: Res<WindowFit>,
+    mut window: Query<(Entity, &mut Window), With<PrimaryWindow>>,
+    winit_windows: NonSend<WinitWindows>,
+) {
+    if !playfield.is_changed() || playfield.is_added() || !fit.0 {
+        return;
+    }
+    let (entity, mut window) = window.single_mut();
+    if window.mode != WindowMode::Windowed {
+        return;
+    }
+
+    let aspect = playfield.size.x as f32 / playfield.size.y as f32;
+    let mut size = Vec2::new(window.height() * aspect, window.height());
+
+    let monitor = winit_windows
+        .get_window(entity)
+        .and_then(|window| window.current_monitor());
+    if let Some(monitor) = monitor {
+        let monitor_size = monitor.size();
+        let bounds = Vec2::new(monitor_size.width as f32, monitor_size.height as f32)
+            / monitor.scale_factor() as f32
+            * MAX_MONITOR_FRACTION;
+        size *= (bounds / size).min_element().min(1.0);
+    }
+
+    window.resolution.set(size.x.round(), size.y.round());
+}
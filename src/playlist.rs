@@ -0,0 +1,157 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    config::Config,
+    decode::FrameStore,
+    locale::{tr, tr_with},
+    threshold::Threshold,
+    toast::Toasts,
+    transition::Transition,
+    MusicPlayer, Player, State, SOUNDTRACK,
+};
+
+const PATH: &str = "playlist.ron";
+
+/// What happens once the last frame has played
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum AtEnd {
+    /// Rewind and pause, to play again with `Space`
+    #[default]
+    Stop,
+    /// Play the same video again
+    Loop,
+    /// Play the next entry of the playlist, going back to the first one after the last,
+    /// with the particles dissolving from one to the other
+    Next,
+}
+
+/// A video to play after the one given on the command line
+#[derive(Deserialize, Clone, Debug)]
+struct Entry {
+    /// Folder of its PNG frames, relative to the assets folder or absolute
+    frames: PathBuf,
+    /// Soundtrack in the assets folder
+    audio: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Script {
+    at_end: AtEnd,
+    entries: Vec<Entry>,
+}
+
+impl Script {
+    fn read() -> Self {
+        let text = match fs::read_to_string(PATH) {
+            Ok(text) => text,
+            Err(e) => {
+                info!("stopping at the end, couldn't read {PATH}: {e}");
+                return Self::default();
+            }
+        };
+
+        ron::from_str(&text).unwrap_or_else(|e| {
+            warn!("couldn't parse {PATH}: {e}");
+            Self::default()
+        })
+    }
+}
+
+/// Videos played one after the other, from `playlist.ron`
+#[derive(Resource, Default)]
+pub struct Playlist {
+    at_end: AtEnd,
+    entries: Vec<Entry>,
+    /// Entry playing, 0 being the video given on the command line
+    current: usize,
+    /// Frames of the video given on the command line, to go back to
+    original: Option<(FrameStore, usize)>,
+}
+
+pub fn load_playlist(
+    mut playlist: ResMut<Playlist>,
+    config: Res<Config>,
+    store: Option<Res<FrameStore>>,
+) {
+    let Script { at_end, entries } = Script::read();
+    playlist.at_end = at_end;
+    playlist.entries = entries;
+    playlist.entries.insert(
+        0,
+        Entry {
+            frames: config.frames_dir.clone(),
+            audio: SOUNDTRACK.to_string(),
+        },
+    );
+    playlist.original = store.map(|store| (store.clone(), config.frames));
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn play_next(
+    mut commands: Commands,
+    mut playlist: ResMut<Playlist>,
+    mut transition: ResMut<Transition>,
+    mut player: Query<&mut Player>,
+    mut music_player: ResMut<MusicPlayer>,
+    mut config: ResMut<Config>,
+    mut state: ResMut<State>,
+    mut toasts: ResMut<Toasts>,
+    mut threshold: ResMut<Threshold>,
+    store: Res<FrameStore>,
+    sinks: Res<Assets<AudioSink>>,
+    server: Res<AssetServer>,
+) {
+    let mut player = player.single_mut();
+    let frames = store.frames().unwrap_or(config.frames);
+    if player.play_index + 1 < frames || !player.buffer.is_empty() {
+        // seeking away from the end calls the dissolve off
+        if matches!(*transition, Transition::Scatter(_)) {
+            *transition = Transition::Idle;
+        }
+        return;
+    }
+
+    match playlist.at_end {
+        AtEnd::Stop => {
+            *state = State::Paused;
+            toasts.push(tr("the-end"));
+        }
+        AtEnd::Loop => {}
+        AtEnd::Next => {
+            if !transition.scattered() {
+                if matches!(*transition, Transition::Idle) {
+                    *transition = Transition::scatter();
+                }
+                return;
+            }
+            *transition = Transition::converge();
+
+            playlist.current = (playlist.current + 1) % playlist.entries.len();
+            let entry = playlist.entries[playlist.current].clone();
+            match (playlist.current, &playlist.original) {
+                (0, Some((store, frames))) => {
+                    commands.insert_resource(store.clone());
+                    config.frames_dir = entry.frames.clone();
+                    config.frames = *frames;
+                }
+                _ => {
+                    commands.insert_resource(FrameStore::Png);
+                    config.set_frames_dir(entry.frames.clone());
+                }
+            }
+            music_player.source = server.load(entry.audio.as_str());
+            threshold.forget_inversion();
+            toasts.push(tr_with(
+                "playing-entry",
+                &[("entry", &entry.frames.display())],
+            ));
+        }
+    }
+
+    *player = Player::at(0, config.fps);
+    music_player.stop(&sinks);
+}
@@ -0,0 +1,122 @@
+//! How rendered frames reach the window, falling back to vsync where the display can't
+//! present them the way asked for
+
+use std::sync::{
+    mpsc::{self, Receiver, Sender},
+    Mutex,
+};
+
+use bevy::{
+    prelude::*,
+    render::{
+        renderer::{RenderAdapter, RenderInstance},
+        view::{ExtractedWindows, NonSendMarker, WindowSystem},
+        RenderApp, RenderSet,
+    },
+    utils::HashMap,
+    window::PresentMode,
+};
+use serde::Deserialize;
+
+/// How rendered frames are presented to the window
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub enum Presentation {
+    /// Waiting on the display's refresh, without tearing
+    Vsync,
+    /// Rendering as fast as possible and showing the latest frame on each refresh, where supported
+    Mailbox,
+    /// Showing frames as they are done, tearing but with the least latency
+    Immediate,
+}
+
+impl Presentation {
+    pub fn present_mode(self) -> PresentMode {
+        match self {
+            Presentation::Vsync => PresentMode::AutoVsync,
+            Presentation::Mailbox => PresentMode::Mailbox,
+            // falls back to mailbox, then vsync, where it isn't supported
+            Presentation::Immediate => PresentMode::AutoNoVsync,
+        }
+    }
+}
+
+/// Falls back to vsync when the window's surface can't present in the mode asked for,
+/// which would fail configuring it
+pub struct PresentationPlugin;
+
+/// Windows presenting in a mode their surface doesn't support, sent from the render world
+#[derive(Resource)]
+struct Unsupported(Mutex<Receiver<Entity>>);
+
+#[derive(Resource)]
+struct ReportUnsupported(Sender<Entity>);
+
+impl Plugin for PresentationPlugin {
+    fn build(&self, app: &mut App) {
+        let (report, unsupported) = mpsc::channel();
+        app.insert_resource(Unsupported(Mutex::new(unsupported)))
+            .add_system(fall_back_to_vsync);
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .insert_resource(ReportUnsupported(report))
+            .add_system(
+                check_present_mode
+                    .in_set(RenderSet::Prepare)
+                    .before(WindowSystem::Prepare),
+            );
+    }
+}
+
+/// Presents with vsync the windows whose mode isn't supported, before their surface is
+/// configured with it, and reports them for the main world to do the same
+fn check_present_mode(
+    // creating surfaces must happen on the main thread on some platforms
+    _marker: NonSend<NonSendMarker>,
+    mut windows: ResMut<ExtractedWindows>,
+    instance: Res<RenderInstance>,
+    adapter: Res<RenderAdapter>,
+    report: Res<ReportUnsupported>,
+    mut supported: Local<HashMap<Entity, Vec<wgpu::PresentMode>>>,
+) {
+    for window in windows.windows.values_mut() {
+        let wanted = match window.present_mode {
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+            // picked among the supported ones
+            PresentMode::AutoVsync | PresentMode::AutoNoVsync => continue,
+        };
+        let modes = supported.entry(window.entity).or_insert_with(|| {
+            // a surface of its own, dropped before the renderer makes the one it presents with
+            match unsafe { instance.create_surface(&window.handle.get_handle()) } {
+                Ok(surface) => surface.get_capabilities(&adapter).present_modes,
+                Err(e) => {
+                    warn!("couldn't check the present modes of the window: {e}");
+                    Vec::new()
+                }
+            }
+        });
+        if !modes.contains(&wanted) {
+            window.present_mode = PresentMode::AutoVsync;
+            let _ = report.0.send(window.entity);
+        }
+    }
+}
+
+fn fall_back_to_vsync(unsupported: Res<Unsupported>, mut windows: Query<&mut Window>) {
+    for entity in unsupported.0.lock().unwrap().try_iter() {
+        let Ok(mut window) = windows.get_mut(entity) else {
+            continue;
+        };
+        if window.present_mode != PresentMode::AutoVsync {
+            warn!(
+                "the display can't present in {:?} mode, falling back to vsync",
+                window.present_mode
+            );
+            window.present_mode = PresentMode::AutoVsync;
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        camera::RenderTarget,
+        render_resource::{
+            AsBindGroup, Extent3d, ShaderRef, TextureDescriptor, TextureDimension, TextureFormat,
+            TextureUsages,
+        },
+        texture::BevyDefault,
+        view::RenderLayers,
+    },
+    sprite::{Material2d, MaterialMesh2dBundle, Mesh2dHandle},
+    window::{PrimaryWindow, WindowRef, WindowResized},
+};
+
+use crate::{locale::tr_with, toast::Toasts, MainCamera};
+
+/// Render layer only seen by the post-processing camera
+const POST_LAYER: u8 = RenderLayers::TOTAL_LAYERS as u8 - 1;
+
+/// Limited palette the final output gets snapped to
+#[derive(Resource, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Quantize {
+    #[default]
+    Off,
+    /// The four greens of the original Game Boy screen
+    GameBoy,
+    /// Black, cyan, magenta and white, CGA palette 1
+    Cga,
+}
+
+impl Quantize {
+    pub fn next(self) -> Self {
+        match self {
+            Quantize::Off => Quantize::GameBoy,
+            Quantize::GameBoy => Quantize::Cga,
+            Quantize::Cga => Quantize::Off,
+        }
+    }
+
+    fn colors(self) -> [Color; 4] {
+        match self {
+            Quantize::Off => [Color::BLACK; 4],
+            Quantize::GameBoy => [
+                Color::hex("0F380F").unwrap(),
+                Color::hex("306230").unwrap(),
+                Color::hex("8BAC0F").unwrap(),
+                Color::hex("9BBC0F").unwrap(),
+            ],
+            Quantize::Cga => [
+                Color::BLACK,
+                Color::hex("55FFFF").unwrap(),
+                Color::hex("FF55FF").unwrap(),
+                Color::WHITE,
+            ],
+        }
+    }
+}
+
+/// Draws the rendered scene with every pixel snapped to the nearest palette color
+#[derive(AsBindGroup, TypeUuid, Clone)]
+#[uuid = "35232242-70c6-4006-8bcd-347b31ecbcb5"]
+pub struct QuantizeMaterial {
+    #[texture(0)]
+    #[sampler(1)]
+    source: Handle<Image>,
+    /// Palette in linear space, to match the sampled texture
+    #[uniform(2)]
+    colors: [Vec4; 4],
+}
+
+impl Material2d for QuantizeMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/quantize.wgsl".into()
+    }
+}
+
+#[derive(Resource)]
+pub struct PostProcess {
+    /// Image the main camera renders into while quantizing
+    target: Handle<Image>,
+    material: Handle<QuantizeMaterial>,
+}
+
+#[derive(Component)]
+pub struct PostCamera;
+
+#[derive(Component)]
+pub struct PostQuad;
+
+fn window_size(window: &Window) -> UVec2 {
+    UVec2::new(
+        window.resolution.physical_width().max(1),
+        window.resolution.physical_height().max(1),
+    )
+}
+
+/// Quad covering the whole window, in the post camera's logical pixels
+fn quad_mesh(window: &Window) -> Mesh {
+    shape::Quad::new(Vec2::new(window.width(), window.height())).into()
+}
+
+fn target_image(size: UVec2) -> Image {
+    let size = Extent3d {
+        width: size.x,
+        height: size.y,
+        ..default()
+    };
+
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: None,
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::bevy_default(),
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+    image
+}
+
+pub fn setup_quantize(
+    mut commands: Commands,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<QuantizeMaterial>>,
+) {
+    let window = window.single();
+    let target = images.add(target_image(window_size(window)));
+    let material = materials.add(QuantizeMaterial {
+        source: target.clone(),
+        colors: [Vec4::ZERO; 4],
+    });
+
+    let layer = RenderLayers::layer(POST_LAYER);
+    commands.spawn((
+        PostQuad,
+        MaterialMesh2dBundle {
+            mesh: meshes.add(quad_mesh(window)).into(),
+            material: material.clone(),
+            ..default()
+        },
+        layer,
+    ));
+    commands.spawn((
+        PostCamera,
+        Camera2dBundle {
+            camera: Camera {
+                order: 1,
+                is_active: false,
+                ..default()
+            },
+            ..default()
+        },
+        UiCameraConfig { show_ui: false },
+        layer,
+    ));
+
+    commands.insert_resource(PostProcess { target, material });
+}
+
+pub fn cycle_quantize(
+    mut quantize: ResMut<Quantize>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::Q) {
+        *quantize = quantize.next();
+        toasts.push(tr_with(
+            "output-palette",
+            &[("palette", &format!("{:?}", *quantize))],
+        ));
+    }
+}
+
+/// Routes the main camera through the post pass while a palette is selected
+pub fn apply_quantize(
+    quantize: Res<Quantize>,
+    post: Res<PostProcess>,
+    mut materials: ResMut<Assets<QuantizeMaterial>>,
+    mut main_camera: Query<(&mut Camera, &mut UiCameraConfig), With<MainCamera>>,
+    mut post_camera: Query<(&mut Camera, &mut UiCameraConfig), Without<MainCamera>>,
+) {
+    if !quantize.is_changed() {
+        return;
+    }
+    let enabled = *quantize != Quantize::Off;
+
+    if let Some(material) = materials.get_mut(&post.material) {
+        material.colors = quantize
+            .colors()
+            .map(|color| Vec4::from(color.as_linear_rgba_f32()));
+    }
+
+    let (mut camera, mut ui) = main_camera.single_mut();
+    camera.target = if enabled {
+        RenderTarget::Image(post.target.clone())
+    } else {
+        RenderTarget::Window(WindowRef::Primary)
+    };
+    // keep the toasts out of the quantized image
+    ui.show_ui = !enabled;
+
+    let (mut camera, mut ui) = post_camera.single_mut();
+    camera.is_active = enabled;
+    ui.show_ui = enabled;
+}
+
+/// Keeps the post pass at the window's resolution
+pub fn resize_post(
+    mut resized: EventReader<WindowResized>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    post: Res<PostProcess>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut quad: Query<&mut Mesh2dHandle, With<PostQuad>>,
+) {
+    if resized.iter().last().is_none() {
+        return;
+    }
+    let window = window.single();
+    let size = window_size(window);
+
+    if let Some(image) = images.get_mut(&post.target) {
+        image.resize(Extent3d {
+            width: size.x,
+            height: size.y,
+            ..default()
+        });
+    }
+    quad.single_mut().0 = meshes.add(quad_mesh(window));
+}
@@ -0,0 +1,116 @@
+//! Particles pulsing with the bass of the soundtrack, from its precomputed spectrum
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{locale::tr, spectrum::Spectrum, toast::Toasts, CurrentFrame, Particle};
+
+/// Share of the bass level left after a second without a louder beat
+const RELEASE: f32 = 0.05;
+
+/// How strongly particles react to the bass when toggled on, each amount being how much
+/// they change at the loudest bass. Read from `reactivity` in `config.ron`.
+#[derive(Resource, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct AudioReactivity {
+    #[serde(skip)]
+    pub enabled: bool,
+    /// Extra jitter of moving particles, 1 doubling it
+    pub jitter: f32,
+    /// Extra size, 0.5 making particles half again as large
+    pub size: f32,
+    /// How far particles lighten towards white
+    pub brightness: f32,
+    /// Bass energy from 0 to 1, jumping up on beats and falling off after them
+    #[serde(skip)]
+    level: f32,
+}
+
+impl Default for AudioReactivity {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            jitter: 1.0,
+            size: 0.5,
+            brightness: 0.3,
+            level: 0.0,
+        }
+    }
+}
+
+impl AudioReactivity {
+    /// Takes the amounts of `settings`, staying on or off
+    pub fn set(&mut self, settings: AudioReactivity) {
+        *self = Self {
+            enabled: self.enabled,
+            level: self.level,
+            ..settings
+        };
+    }
+
+    /// How much the bass currently weighs in, 0 when turned off
+    fn level(&self) -> f32 {
+        if self.enabled {
+            self.level
+        } else {
+            0.0
+        }
+    }
+
+    /// Multiplier on the jitter of moving particles
+    pub fn jitter_scale(&self) -> f32 {
+        1.0 + self.jitter * self.level()
+    }
+}
+
+pub fn reactivity_enabled(reactivity: Res<AudioReactivity>) -> bool {
+    reactivity.enabled
+}
+
+pub fn toggle_reactivity(
+    mut reactivity: ResMut<AudioReactivity>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::J) {
+        reactivity.enabled = !reactivity.enabled;
+        toasts.push(if reactivity.enabled {
+            tr("reactivity-on")
+        } else {
+            tr("reactivity-off")
+        });
+    }
+}
+
+/// Follows the bass of the shown frame, holding on to beats for a moment
+pub fn follow_bass(
+    mut reactivity: ResMut<AudioReactivity>,
+    spectrum: Res<Spectrum>,
+    current: Res<CurrentFrame>,
+    time: Res<Time>,
+) {
+    let bass = spectrum.bass(current.front.index).unwrap_or(0.0);
+    let released = reactivity.level * RELEASE.powf(time.delta_seconds());
+    reactivity.level = bass.max(released);
+}
+
+/// Grows and lightens the particles with the bass, over the colors they were just given
+pub fn pulse_particles(
+    mut particles: Query<(&mut Transform, &mut Sprite), With<Particle>>,
+    reactivity: Res<AudioReactivity>,
+) {
+    // back to their size once, when turned off
+    if !reactivity.enabled && !reactivity.is_changed() {
+        return;
+    }
+
+    let level = reactivity.level();
+    let scale = Vec3::splat(1.0 + reactivity.size * level);
+    let lighten = reactivity.brightness * level;
+    for (mut transform, mut sprite) in &mut particles {
+        transform.scale = scale;
+        let [r, g, b, a] = sprite.color.as_rgba_f32();
+        let [r, g, b] = [r, g, b].map(|c| c + (1.0 - c) * lighten);
+        sprite.color = Color::rgba(r, g, b, a);
+    }
+}
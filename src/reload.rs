@@ -0,0 +1,157 @@
+//! Watches `config.ron` and `colors.ron`, applying the settings that can change while playing.
+//! The others, like the frame rate or the window size, wait for the next launch.
+
+use std::{
+    path::Path,
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+};
+
+use bevy::prelude::*;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{
+    config::{ConfigFile, CONFIG_FILE},
+    locale::tr_with,
+    mouse::MouseForce,
+    palette::{ColorScheme, Palette, COLORS_FILE},
+    panel::Tuning,
+    reactivity::AudioReactivity,
+    sim::MovementMode,
+    speed::PlaybackSpeed,
+    toast::Toasts,
+};
+
+#[derive(Resource)]
+pub struct ConfigWatcher {
+    // stops watching when dropped
+    _watcher: Mutex<RecommendedWatcher>,
+    events: Mutex<Receiver<notify::Result<Event>>>,
+    /// Settings last read from `config.ron`, to apply only the ones that changed
+    applied: ConfigFile,
+}
+
+impl ConfigWatcher {
+    /// Watches the working directory, where the files are, so they can also be created later
+    pub fn new(applied: ConfigFile) -> notify::Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(Path::new("."), RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            _watcher: Mutex::new(watcher),
+            events: Mutex::new(events),
+            applied,
+        })
+    }
+
+    /// Whether each file was written to since the last call
+    fn changed(&self) -> (bool, bool) {
+        let (mut config, mut colors) = (false, false);
+        for event in self.events.lock().unwrap().try_iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("stopped watching the settings: {e}");
+                    continue;
+                }
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                continue;
+            }
+
+            for path in &event.paths {
+                let name = path.file_name().and_then(|name| name.to_str());
+                config |= name == Some(CONFIG_FILE);
+                colors |= name == Some(COLORS_FILE);
+            }
+        }
+        (config, colors)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn reload_config(
+    mut watcher: ResMut<ConfigWatcher>,
+    mut speed: ResMut<PlaybackSpeed>,
+    mut palette: ResMut<Palette>,
+    mut movement: ResMut<MovementMode>,
+    mut tuning: ResMut<Tuning>,
+    mut force: ResMut<MouseForce>,
+    mut scheme: ResMut<ColorScheme>,
+    mut reactivity: ResMut<AudioReactivity>,
+    mut toasts: ResMut<Toasts>,
+) {
+    let (config, colors) = watcher.changed();
+
+    if colors {
+        if let Some(loaded) = ColorScheme::read() {
+            if *scheme != loaded {
+                *scheme = loaded;
+                toasts.push(tr_with("reloaded", &[("file", &COLORS_FILE)]));
+            }
+        }
+    }
+
+    // a file that doesn't parse is likely being edited, keep the current settings
+    let Some(file) = config.then(ConfigFile::read).flatten() else {
+        return;
+    };
+    let old = std::mem::replace(&mut watcher.applied, file.clone());
+    if file == old {
+        return;
+    }
+
+    if let Some(value) = file.speed.filter(|_| file.speed != old.speed) {
+        *speed = PlaybackSpeed(value);
+    }
+    if let Some(value) = file.palette.filter(|_| file.palette != old.palette) {
+        *palette = value;
+    }
+    if let Some(value) = file.movement.filter(|_| file.movement != old.movement) {
+        *movement = value;
+    }
+    if let Some(value) = file.jitter.filter(|_| file.jitter != old.jitter) {
+        tuning.jitter = value;
+    }
+    if let Some(value) = file
+        .mouse_radius
+        .filter(|_| file.mouse_radius != old.mouse_radius)
+    {
+        force.radius = value;
+    }
+    if let Some(value) = file
+        .reactivity
+        .filter(|_| file.reactivity != old.reactivity)
+    {
+        reactivity.set(value);
+    }
+
+    let restart: Vec<_> = [
+        ("frames", file.frames != old.frames),
+        ("audio", file.audio != old.audio),
+        ("fps", file.fps != old.fps),
+        ("particles", file.particles != old.particles),
+        ("layers", file.layers != old.layers),
+        ("buffer_mb", file.buffer_mb != old.buffer_mb),
+        ("history", file.history != old.history),
+        ("size", file.size != old.size),
+        ("present_mode", file.present_mode != old.present_mode),
+        ("max_fps", file.max_fps != old.max_fps),
+        ("low_power", file.low_power != old.low_power),
+    ]
+    .into_iter()
+    .filter_map(|(key, changed)| changed.then_some(key))
+    .collect();
+
+    if restart.is_empty() {
+        toasts.push(tr_with("reloaded", &[("file", &CONFIG_FILE)]));
+    } else {
+        toasts.push(tr_with(
+            "reloaded-restart",
+            &[("file", &CONFIG_FILE), ("keys", &restart.join(", "))],
+        ));
+    }
+}
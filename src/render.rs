@@ -0,0 +1,479 @@
+//! Draws the CPU particles as instances of a single quad, in one draw call for each texture,
+//! instead of the sprite renderer building the vertices of every particle each frame.
+//!
+//! Particles stay sprites in the main world, so every effect applies to them as before: they
+//! are only taken out of the sprites extracted to the render world and drawn from there.
+//! Once a spot of the playfield is covered several times over, the particles landing on it
+//! aren't drawn at all, keeping the drawing cost to what can be seen when there are many more
+//! particles than pixels. They are still simulated.
+
+use std::ops::Range;
+
+use crate::Particle;
+use bevy::{
+    asset::HandleId,
+    core_pipeline::core_2d::Transparent2d,
+    ecs::system::{
+        lifetimeless::{Read, SRes},
+        SystemParamItem,
+    },
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_phase::{
+            AddRenderCommand, DrawFunctions, PhaseItem, RenderCommand, RenderCommandResult,
+            RenderPhase, SetItemPipeline, TrackedRenderPass,
+        },
+        render_resource::*,
+        renderer::{RenderDevice, RenderQueue},
+        texture::BevyDefault,
+        view::{ExtractedView, ViewTarget, ViewUniform, ViewUniformOffset, ViewUniforms},
+        Extract, ExtractSchedule, RenderApp, RenderSet,
+    },
+    sprite::{ExtractedSprites, SpriteSystem},
+    utils::{FloatOrd, HashMap},
+};
+use instance::Instance;
+
+const SHADER: &str = "shaders/instanced.wgsl";
+/// Side of the squares the particles are binned into to tell how covered a spot is, in pixels
+const CELL_SIZE: f32 = 4.0;
+/// Times over a cell is covered before the particles on it are no longer drawn,
+/// more of them blending on top can't be told apart
+const SATURATION: f32 = 3.0;
+
+/// Draws the particles instanced when `enabled`, as sprites otherwise
+pub struct InstancedRenderPlugin {
+    pub enabled: bool,
+}
+
+impl Plugin for InstancedRenderPlugin {
+    fn build(&self, app: &mut App) {
+        if !self.enabled {
+            return;
+        }
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            warn!("no renderer, drawing the particles as sprites");
+            return;
+        };
+
+        render_app
+            .init_resource::<ParticleInstances>()
+            .init_resource::<InstancePipeline>()
+            .init_resource::<SpecializedRenderPipelines<InstancePipeline>>()
+            .add_render_command::<Transparent2d, DrawParticles>()
+            .add_system(
+                extract_particles
+                    .in_schedule(ExtractSchedule)
+                    .after(SpriteSystem::ExtractSprites),
+            )
+            .add_system(prepare_instances.in_set(RenderSet::Prepare))
+            .add_system(queue_instances.in_set(RenderSet::Queue));
+    }
+}
+
+mod instance {
+    // the derive generates layout checks that are never called
+    #![allow(dead_code)]
+
+    use bytemuck::{Pod, Zeroable};
+
+    /// A particle's quad in world space
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    #[repr(C)]
+    pub struct Instance {
+        /// Sides of the quad, scaled to the sprite's size
+        pub axis_x: [f32; 3],
+        pub axis_y: [f32; 3],
+        pub center: [f32; 3],
+        /// Linear RGBA
+        pub color: [f32; 4],
+        /// Corners of the part of the texture drawn, from 0 to 1, top left then bottom right
+        pub uv_rect: [f32; 4],
+    }
+}
+
+/// Particles sharing a texture
+struct Batch {
+    image: HandleId,
+    instances: Vec<Instance>,
+    /// Where the instances are in the buffer
+    range: Range<u32>,
+    bind_group: Option<BindGroup>,
+}
+
+#[derive(Resource)]
+struct ParticleInstances {
+    /// Kept across frames to reuse their allocations
+    batches: Vec<Batch>,
+    buffer: BufferVec<Instance>,
+    view_bind_group: Option<BindGroup>,
+    /// Whether the entity of each index is drawn here, to take it out of the sprites
+    drawn: Vec<bool>,
+    /// Area of the cells covered by the particles drawn so far, weighted by their opacity
+    coverage: HashMap<IVec2, f32>,
+}
+
+impl Default for ParticleInstances {
+    fn default() -> Self {
+        Self {
+            batches: Vec::new(),
+            buffer: BufferVec::new(BufferUsages::VERTEX),
+            view_bind_group: None,
+            drawn: Vec::new(),
+            coverage: HashMap::default(),
+        }
+    }
+}
+
+/// Render world entity drawing the batch at this index
+#[derive(Component)]
+struct ParticleBatch(usize);
+
+#[allow(clippy::type_complexity)]
+fn extract_particles(
+    mut commands: Commands,
+    mut instances: ResMut<ParticleInstances>,
+    mut sprites: ResMut<ExtractedSprites>,
+    particles: Extract<
+        Query<
+            (
+                Entity,
+                &ComputedVisibility,
+                &Sprite,
+                &GlobalTransform,
+                &Handle<Image>,
+            ),
+            With<Particle>,
+        >,
+    >,
+    images: Extract<Res<Assets<Image>>>,
+) {
+    let instances = &mut *instances;
+    for batch in &mut instances.batches {
+        batch.instances.clear();
+    }
+    instances.drawn.clear();
+    instances.coverage.clear();
+
+    for (entity, visibility, sprite, transform, image) in &particles {
+        // sized after their texture otherwise, which isn't known until it is prepared
+        let Some(size) = sprite.custom_size.or(sprite.rect.map(|rect| rect.size())) else {
+            continue;
+        };
+        if !visibility.is_visible() || sprite.flip_x || sprite.flip_y {
+            continue;
+        }
+        // cells of an atlas, in pixels of the whole texture
+        let uv_rect = match sprite.rect {
+            None => Rect::new(0.0, 0.0, 1.0, 1.0),
+            Some(rect) => {
+                let Some(texture) = images.get(image) else {
+                    continue;
+                };
+                let texture = texture.size();
+                Rect::from_corners(rect.min / texture, rect.max / texture)
+            }
+        };
+
+        let idx = entity.index() as usize;
+        if instances.drawn.len() <= idx {
+            instances.drawn.resize(idx + 1, false);
+        }
+        instances.drawn[idx] = true;
+
+        let affine = transform.affine();
+        let axis_x = affine.matrix3.x_axis * size.x;
+        let axis_y = affine.matrix3.y_axis * size.y;
+        let anchor = sprite.anchor.as_vec();
+        let center = affine.translation - axis_x * anchor.x - axis_y * anchor.y;
+
+        // left out of the sprites too, it wouldn't show
+        let cell = (center.truncate() / CELL_SIZE).floor().as_ivec2();
+        let covered = instances.coverage.entry(cell).or_default();
+        if *covered >= SATURATION * CELL_SIZE * CELL_SIZE {
+            continue;
+        }
+        *covered += axis_x.truncate().perp_dot(axis_y.truncate()).abs() * sprite.color.a();
+
+        let instance = Instance {
+            axis_x: axis_x.to_array(),
+            axis_y: axis_y.to_array(),
+            center: center.to_array(),
+            color: sprite.color.as_linear_rgba_f32(),
+            uv_rect: [uv_rect.min.x, uv_rect.min.y, uv_rect.max.x, uv_rect.max.y],
+        };
+
+        // there are only ever a few textures
+        let batch = match instances
+            .batches
+            .iter_mut()
+            .position(|batch| batch.image == image.id())
+        {
+            Some(idx) => &mut instances.batches[idx],
+            None => {
+                instances.batches.push(Batch {
+                    image: image.id(),
+                    instances: Vec::new(),
+                    range: 0..0,
+                    bind_group: None,
+                });
+                instances.batches.last_mut().expect("just pushed")
+            }
+        };
+        batch.instances.push(instance);
+    }
+
+    instances
+        .batches
+        .retain(|batch| !batch.instances.is_empty());
+    for (idx, batch) in instances.batches.iter_mut().enumerate() {
+        // back to front for blending, stable since most particles share a depth
+        batch
+            .instances
+            .sort_by(|a, b| a.center[2].total_cmp(&b.center[2]));
+        commands.spawn(ParticleBatch(idx));
+    }
+
+    let drawn = &instances.drawn;
+    sprites
+        .sprites
+        .retain(|sprite| !drawn.get(sprite.entity.index() as usize).unwrap_or(&false));
+}
+
+fn prepare_instances(
+    mut instances: ResMut<ParticleInstances>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    let ParticleInstances {
+        batches, buffer, ..
+    } = &mut *instances;
+
+    buffer.clear();
+    for batch in batches {
+        let start = buffer.len() as u32;
+        for &instance in &batch.instances {
+            buffer.push(instance);
+        }
+        batch.range = start..buffer.len() as u32;
+    }
+    buffer.write_buffer(&device, &queue);
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct InstancePipelineKey {
+    samples: u32,
+    hdr: bool,
+}
+
+#[derive(Resource)]
+struct InstancePipeline {
+    view_layout: BindGroupLayout,
+    texture_layout: BindGroupLayout,
+    shader: Handle<Shader>,
+}
+
+impl FromWorld for InstancePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+
+        let view_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("particle_view_layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: Some(ViewUniform::min_size()),
+                },
+                count: None,
+            }],
+        });
+        let texture_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("particle_texture_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        Self {
+            view_layout,
+            texture_layout,
+            shader: world.resource::<AssetServer>().load(SHADER),
+        }
+    }
+}
+
+impl SpecializedRenderPipeline for InstancePipeline {
+    type Key = InstancePipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let instance_layout = VertexBufferLayout::from_vertex_formats(
+            VertexStepMode::Instance,
+            [
+                VertexFormat::Float32x3,
+                VertexFormat::Float32x3,
+                VertexFormat::Float32x3,
+                VertexFormat::Float32x4,
+                VertexFormat::Float32x4,
+            ],
+        );
+        let format = if key.hdr {
+            ViewTarget::TEXTURE_FORMAT_HDR
+        } else {
+            TextureFormat::bevy_default()
+        };
+
+        RenderPipelineDescriptor {
+            label: Some("particle_pipeline".into()),
+            layout: vec![self.view_layout.clone(), self.texture_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            vertex: VertexState {
+                shader: self.shader.clone(),
+                shader_defs: Vec::new(),
+                entry_point: "vertex".into(),
+                buffers: vec![instance_layout],
+            },
+            fragment: Some(FragmentState {
+                shader: self.shader.clone(),
+                shader_defs: Vec::new(),
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: key.samples,
+                ..default()
+            },
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn queue_instances(
+    mut instances: ResMut<ParticleInstances>,
+    pipeline: Res<InstancePipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<InstancePipeline>>,
+    cache: Res<PipelineCache>,
+    draw_functions: Res<DrawFunctions<Transparent2d>>,
+    view_uniforms: Res<ViewUniforms>,
+    images: Res<RenderAssets<Image>>,
+    device: Res<RenderDevice>,
+    msaa: Res<Msaa>,
+    batches: Query<(Entity, &ParticleBatch)>,
+    mut views: Query<(&mut RenderPhase<Transparent2d>, &ExtractedView)>,
+) {
+    let Some(view_binding) = view_uniforms.uniforms.binding() else {
+        return;
+    };
+    let instances = &mut *instances;
+    instances.view_bind_group = Some(device.create_bind_group(&BindGroupDescriptor {
+        label: Some("particle_view_bind_group"),
+        layout: &pipeline.view_layout,
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: view_binding,
+        }],
+    }));
+
+    for batch in &mut instances.batches {
+        // the texture may still be loading
+        batch.bind_group = images.get(&Handle::weak(batch.image)).map(|image| {
+            device.create_bind_group(&BindGroupDescriptor {
+                label: Some("particle_texture_bind_group"),
+                layout: &pipeline.texture_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(&image.texture_view),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::Sampler(&image.sampler),
+                    },
+                ],
+            })
+        });
+    }
+
+    let draw_function = draw_functions.read().id::<DrawParticles>();
+    for (mut phase, view) in &mut views {
+        let key = InstancePipelineKey {
+            samples: msaa.samples(),
+            hdr: view.hdr,
+        };
+        let pipeline = pipelines.specialize(&cache, &pipeline, key);
+
+        for (entity, batch) in &batches {
+            let batch = &instances.batches[batch.0];
+            if batch.bind_group.is_none() {
+                continue;
+            }
+            phase.add(Transparent2d {
+                // behind anything drawn over its most distant particles
+                sort_key: FloatOrd(batch.instances[0].center[2]),
+                entity,
+                pipeline,
+                draw_function,
+                batch_range: None,
+            });
+        }
+    }
+}
+
+type DrawParticles = (SetItemPipeline, DrawParticleBatch);
+
+struct DrawParticleBatch;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawParticleBatch {
+    type Param = SRes<ParticleInstances>;
+    type ViewWorldQuery = Read<ViewUniformOffset>;
+    type ItemWorldQuery = Read<ParticleBatch>;
+
+    fn render<'w>(
+        _item: &P,
+        view: &'w ViewUniformOffset,
+        batch: &'w ParticleBatch,
+        instances: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let instances = instances.into_inner();
+        let batch = &instances.batches[batch.0];
+        let (Some(view_bind_group), Some(bind_group), Some(buffer)) = (
+            &instances.view_bind_group,
+            &batch.bind_group,
+            instances.buffer.buffer(),
+        ) else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(0, view_bind_group, &[view.offset]);
+        pass.set_bind_group(1, bind_group, &[]);
+        pass.set_vertex_buffer(0, buffer.slice(..));
+        // two triangles, with their corners from the vertex index
+        pass.draw(0..6, batch.range.clone());
+        RenderCommandResult::Success
+    }
+}
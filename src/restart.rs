@@ -0,0 +1,68 @@
+//! Starts the video over with `Home`, as if relaunched: the player, the soundtrack and the
+//! particles go back to where they were at launch.
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    autosave::Autosave,
+    config::Config,
+    lifetime::{Fade, Lifetime},
+    locale::tr,
+    phase::Phase,
+    sim::{Playfield, SimRng},
+    toast::Toasts,
+    MusicPlayer, Particle, Player, Velocity, SPAWN_SEED,
+};
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn restart(
+    mut commands: Commands,
+    mut player: Query<&mut Player>,
+    mut particles: Query<(
+        Entity,
+        &mut Transform,
+        &mut Velocity,
+        &mut Particle,
+        &mut Lifetime,
+        &mut Phase,
+    )>,
+    mut music_player: ResMut<MusicPlayer>,
+    sinks: Res<Assets<AudioSink>>,
+    mut sim_rng: ResMut<SimRng>,
+    save: Res<Autosave>,
+    playfield: Res<Playfield>,
+    config: Res<Config>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if !keyboard.just_pressed(KeyCode::Home) {
+        return;
+    }
+
+    *player.single_mut() = Player::at(0, config.fps);
+    music_player.stop(&sinks);
+
+    // respawned in the order they were spawned, to draw the same pattern
+    let mut particles: Vec<_> = particles.iter_mut().collect();
+    particles.sort_unstable_by_key(|(entity, ..)| *entity);
+    let count = particles.len();
+    let mut rng = StdRng::seed_from_u64(config.seed.unwrap_or(SPAWN_SEED));
+    sim_rng.restart();
+    let mut rhythms = sim_rng.serial();
+    for (idx, (entity, mut transform, mut velocity, mut standstill, mut lifetime, mut phase)) in
+        particles.into_iter().enumerate()
+    {
+        transform.translation = save
+            .spawn
+            .position(idx, count, &playfield, &mut rng)
+            .extend(transform.translation.z);
+        velocity.0 = Vec2::ZERO;
+        standstill.0 = 0;
+        *lifetime = Lifetime::new(&mut rhythms);
+        *phase = Phase::new(&mut rhythms);
+        commands.entity(entity).insert(Fade::fade_in());
+    }
+
+    toasts.push(tr("restarting"));
+}
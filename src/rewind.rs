@@ -0,0 +1,83 @@
+//! Plays the video backwards while `R` is held, through the frames kept after they were
+//! shown. The soundtrack is muted meanwhile, and starts again from where the rewind stopped.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    buffer::ShownFrames, config::Config, locale::tr, speed::PlaybackSpeed, toast::Toasts,
+    CurrentFrame, MusicPlayer, Player,
+};
+
+#[derive(Resource, Default)]
+pub struct Rewind {
+    active: bool,
+    /// Seconds of video rewound since the last frame went back
+    elapsed: f64,
+    /// Whether the history ran out during this rewind
+    exhausted: bool,
+}
+
+pub fn is_rewinding(rewind: Res<Rewind>) -> bool {
+    rewind.active
+}
+
+/// Steps back a frame for every frame's worth of time `R` is held, at the playback speed.
+/// The frames rewound over go back in front of the player's buffer, to play them again.
+#[allow(clippy::too_many_arguments)]
+pub fn rewind(
+    mut rewind: ResMut<Rewind>,
+    mut shown: ResMut<ShownFrames>,
+    mut player: Query<&mut Player>,
+    mut current: ResMut<CurrentFrame>,
+    mut music_player: ResMut<MusicPlayer>,
+    sinks: Res<Assets<AudioSink>>,
+    mut toasts: ResMut<Toasts>,
+    speed: Res<PlaybackSpeed>,
+    config: Res<Config>,
+    time: Res<Time>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    let held = keyboard.pressed(KeyCode::R);
+    if held != rewind.active {
+        *rewind = Rewind {
+            active: held,
+            ..default()
+        };
+        if held {
+            music_player.stop(&sinks);
+            toasts.push(tr("rewinding"));
+        }
+    }
+    if !held {
+        return;
+    }
+
+    // at most a frame back per update, as every frame needs showing once
+    let frame_secs = 1.0 / config.fps;
+    rewind.elapsed = (rewind.elapsed + time.delta_seconds_f64() * speed.0).min(frame_secs);
+    let mut player = player.single_mut();
+    if rewind.elapsed < frame_secs
+        || current.back.is_some()
+        || current.front.index != player.play_index
+    {
+        return;
+    }
+
+    let Some(frame) = shown.rewind() else {
+        if !rewind.exhausted {
+            rewind.exhausted = true;
+            toasts.push(tr("rewind-limit"));
+        }
+        return;
+    };
+    rewind.elapsed = 0.0;
+
+    player.buffer.push_front(current.front.image.clone());
+    player.play_index = frame.index;
+    player
+        .time
+        .set_elapsed(Duration::from_secs_f64(frame.index as f64 / config.fps));
+    current.publish(frame);
+}
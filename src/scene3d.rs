@@ -0,0 +1,259 @@
+//! `--mode 3d`: the particles float in front of the video's plane, seen from a camera slowly
+//! circling around it. They are simulated as in 2D, moving ones also wandering in depth,
+//! and drawn as one mesh of quads facing the camera that is rebuilt every frame.
+//! With a relief, they also stand out of the plane by how dark the video is under them.
+//! The 2D camera keeps drawing the interface on top, and recordings and screenshots are taken
+//! from a second 3D camera following the orbiting one.
+
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::*,
+    render::{mesh::PrimitiveTopology, view::RenderLayers},
+};
+use rand::Rng;
+
+use crate::{
+    capture::CaptureCamera,
+    playfield::Backdrop,
+    sim::{FrameSampler, FrameView, Playfield, SimRng},
+    threshold::Threshold,
+    CurrentFrame, MainCamera, Particle,
+};
+
+/// Render layer no camera sees, for the sprites drawn in 3D instead
+const HIDDEN_LAYER: u8 = RenderLayers::TOTAL_LAYERS as u8 - 2;
+/// Radians per second the camera goes around the plane
+const ORBIT_SPEED: f32 = 0.1;
+/// Angle between the camera's path and the plane's normal
+const ORBIT_TILT: f32 = 0.35;
+/// Furthest a particle wanders from the plane, in pixels
+const MAX_DEPTH: f32 = 60.0;
+/// Depth moving particles jitter by every tick, in pixels
+const DEPTH_JITTER: f32 = 2.0;
+/// Share of their depth settled particles keep every tick, easing back onto the plane
+const SETTLE: f32 = 0.8;
+
+/// How the particles are drawn
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum ViewMode {
+    /// Sprites seen from the front
+    #[default]
+    Flat,
+    /// Quads in front of the plane, seen from an orbiting camera
+    Space,
+}
+
+/// Draws the particles in 3D when `enabled`
+pub struct Scene3dPlugin {
+    pub enabled: bool,
+    /// Height of the relief, in pixels, when there is one
+    pub relief: Option<f32>,
+}
+
+impl Plugin for Scene3dPlugin {
+    fn build(&self, app: &mut App) {
+        if !self.enabled {
+            return;
+        }
+
+        if let Some(height) = self.relief {
+            app.insert_resource(Relief(height));
+        }
+        app.add_startup_system(setup_scene.in_base_set(StartupSet::PostStartup))
+            .add_system(hide_sprites)
+            .add_system(jitter_depth.in_schedule(CoreSchedule::FixedUpdate))
+            .add_system(orbit_camera)
+            .add_system(follow_orbit.after(orbit_camera))
+            .add_system(build_cloud.after(orbit_camera));
+    }
+}
+
+/// Distance off the plane, towards the camera
+#[derive(Component, Default)]
+pub struct Depth(f32);
+
+/// How far the silhouette stands out of the plane, in pixels
+#[derive(Resource)]
+struct Relief(f32);
+
+impl Relief {
+    /// Depth particles settle at over a pixel of `luminance`, the silhouette's color standing out
+    fn depth(&self, luminance: f32, inverted: bool) -> f32 {
+        let darkness = if inverted { luminance } else { 1.0 - luminance };
+        darkness * self.0
+    }
+}
+
+#[derive(Component)]
+struct OrbitCamera;
+
+/// Mesh all the particles are drawn in
+#[derive(Component)]
+struct Cloud;
+
+fn setup_scene(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut main_camera: Query<&mut Camera2d, With<MainCamera>>,
+    playfield: Res<Playfield>,
+) {
+    // the interface goes over the 3D scene
+    main_camera.single_mut().clear_color = ClearColorConfig::None;
+    commands.spawn((
+        OrbitCamera,
+        Camera3dBundle {
+            camera: Camera {
+                order: -1,
+                ..default()
+            },
+            ..default()
+        },
+    ));
+
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(shape::Quad::new(playfield.size.as_vec2()).into()),
+        material: materials.add(StandardMaterial {
+            base_color: Color::WHITE,
+            unlit: true,
+            cull_mode: None,
+            ..default()
+        }),
+        ..default()
+    });
+    commands.spawn((
+        Cloud,
+        PbrBundle {
+            mesh: meshes.add(Mesh::new(PrimitiveTopology::TriangleList)),
+            material: materials.add(StandardMaterial {
+                base_color: Color::WHITE,
+                unlit: true,
+                cull_mode: None,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+            ..default()
+        },
+    ));
+}
+
+/// Moves the backdrop and new particles out of the 2D camera's sight
+#[allow(clippy::type_complexity)]
+fn hide_sprites(
+    mut commands: Commands,
+    sprites: Query<Entity, Or<(Added<Particle>, Added<Backdrop>)>>,
+) {
+    for entity in &sprites {
+        commands
+            .entity(entity)
+            .insert((RenderLayers::layer(HIDDEN_LAYER), Depth::default()));
+    }
+}
+
+/// Moving particles wander in depth, settled ones ease back onto the plane or the relief
+fn jitter_depth(
+    mut particles: Query<(&Transform, &Particle, &mut Depth)>,
+    mut sim_rng: ResMut<SimRng>,
+    current: Res<CurrentFrame>,
+    relief: Option<Res<Relief>>,
+    images: Res<Assets<Image>>,
+    threshold: Res<Threshold>,
+    playfield: Res<Playfield>,
+) {
+    let sampler = relief
+        .as_ref()
+        .and_then(|_| images.get(&current.front.image))
+        .filter(|image| image.size().as_uvec2() == playfield.size)
+        .map(|image| FrameSampler::new(FrameView::from_image(image), *playfield));
+    let surface = |pos: Vec2| match (&relief, &sampler) {
+        (Some(relief), Some(sampler)) => {
+            relief.depth(sampler.luminance_at(pos), threshold.inverted)
+        }
+        _ => 0.0,
+    };
+
+    let mut rng = sim_rng.serial();
+    for (transform, standstill, mut depth) in &mut particles {
+        let surface = surface(transform.translation.truncate());
+        depth.0 = if standstill.0 == current.front.index {
+            surface + (depth.0 - surface) * SETTLE
+        } else {
+            let jitter = rng.gen_range(-DEPTH_JITTER..DEPTH_JITTER);
+            (depth.0 + jitter).clamp(surface - MAX_DEPTH, surface + MAX_DEPTH)
+        };
+    }
+}
+
+/// Circles around the plane's normal, far enough to see the whole playfield
+fn orbit_camera(
+    mut camera: Query<(&mut Transform, &Projection), With<OrbitCamera>>,
+    playfield: Res<Playfield>,
+    time: Res<Time>,
+) {
+    let (mut transform, projection) = camera.single_mut();
+    let Projection::Perspective(perspective) = projection else {
+        return;
+    };
+    let half = playfield.half_size();
+    let fit = half.y.max(half.x / perspective.aspect_ratio);
+    let distance = fit / (perspective.fov / 2.0).tan() * 1.1;
+
+    let angle = time.elapsed_seconds_wrapped() * ORBIT_SPEED;
+    let offset = Vec2::from_angle(angle) * ORBIT_TILT.sin();
+    let position = Vec3::new(offset.x, offset.y, ORBIT_TILT.cos()) * distance;
+    *transform = Transform::from_translation(position).looking_at(Vec3::ZERO, Vec3::Y);
+}
+
+/// Records from where the orbiting camera is
+fn follow_orbit(
+    mut capture: Query<&mut Transform, (With<CaptureCamera>, Without<OrbitCamera>)>,
+    camera: Query<&Transform, With<OrbitCamera>>,
+) {
+    let Ok(mut transform) = capture.get_single_mut() else {
+        return;
+    };
+    *transform = *camera.single();
+}
+
+/// Rebuilds the quads of the visible particles, facing the camera
+#[allow(clippy::type_complexity)]
+fn build_cloud(
+    particles: Query<(&Transform, &Sprite, &Depth, &ComputedVisibility), With<Particle>>,
+    camera: Query<&Transform, With<OrbitCamera>>,
+    cloud: Query<&Handle<Mesh>, With<Cloud>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let Some(mesh) = meshes.get_mut(cloud.single()) else {
+        return;
+    };
+    let camera = camera.single();
+    let (right, up) = (camera.right(), camera.up());
+    let normal = camera.back().to_array();
+
+    let count = particles.iter().len() * 6;
+    let mut positions = Vec::with_capacity(count);
+    let mut colors = Vec::with_capacity(count);
+    for (transform, sprite, depth, visibility) in &particles {
+        if !visibility.is_visible_in_hierarchy() {
+            continue;
+        }
+        let size = sprite.custom_size.unwrap_or(Vec2::ONE) * transform.scale.truncate() / 2.0;
+        let center = transform.translation.truncate().extend(depth.0);
+        let (x, y) = (right * size.x, up * size.y);
+        let corners = [
+            center - x - y,
+            center + x - y,
+            center + x + y,
+            center - x - y,
+            center + x + y,
+            center - x + y,
+        ];
+        positions.extend(corners.map(|corner| corner.to_array()));
+        colors.extend([sprite.color.as_linear_rgba_f32(); 6]);
+    }
+
+    let normals = vec![normal; positions.len()];
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+}
@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+
+use crate::{
+    config::Config, decode::FrameStore, locale::tr_with, timestamp, toast::Toasts, MusicPlayer,
+    Player,
+};
+
+/// Seconds skipped by the left and right arrows
+const STEP: f64 = 5.0;
+/// Seconds skipped by the up and down arrows
+const LONG_STEP: f64 = 30.0;
+
+/// Jumps through the video with the arrow keys, dropping the buffered frames and
+/// restarting the soundtrack at the new position
+pub fn seek(
+    mut player: Query<&mut Player>,
+    mut music_player: ResMut<MusicPlayer>,
+    sinks: Res<Assets<AudioSink>>,
+    store: Res<FrameStore>,
+    config: Res<Config>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    let secs = [
+        (KeyCode::Left, -STEP),
+        (KeyCode::Right, STEP),
+        (KeyCode::Down, -LONG_STEP),
+        (KeyCode::Up, LONG_STEP),
+    ]
+    .into_iter()
+    .filter(|(key, _)| keyboard.just_pressed(*key))
+    .map(|(_, secs)| secs)
+    .sum::<f64>();
+    if secs == 0.0 {
+        return;
+    }
+
+    let mut player = player.single_mut();
+    let frames = store.frames().unwrap_or(config.frames);
+    let offset = (secs * config.fps) as isize;
+    let frame = player
+        .play_index
+        .saturating_add_signed(offset)
+        .min(frames.saturating_sub(1));
+    *player = Player::at(frame, config.fps);
+    toasts.push(tr_with(
+        "seeking",
+        &[("time", &timestamp(frame, config.fps))],
+    ));
+
+    music_player.stop(&sinks);
+}
@@ -0,0 +1,229 @@
+use std::fs;
+
+use bevy::{prelude::*, time::Stopwatch};
+use serde::Deserialize;
+
+use crate::{config::Config, palette::Palette, splash::Loading, toast::UiFont, Player, State};
+
+const PATH: &str = "sequence.ron";
+/// Time a line of text takes to fade in and out
+const TEXT_FADE_SECS: f32 = 0.5;
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum Action {
+    /// Shows a line of text in the middle of the window
+    Text {
+        text: String,
+        secs: f32,
+    },
+    /// Fades the picture to black at 1, or back at 0
+    Fade {
+        to: f32,
+        secs: f32,
+    },
+    Play,
+    Pause,
+    Palette(Palette),
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Cue {
+    /// Seconds since the start of the sequence
+    at: f32,
+    action: Action,
+}
+
+#[derive(Deserialize, Default)]
+struct Script {
+    #[serde(default)]
+    intro: Vec<Cue>,
+    #[serde(default)]
+    credits: Vec<Cue>,
+}
+
+impl Script {
+    fn read() -> Self {
+        let text = match fs::read_to_string(PATH) {
+            Ok(text) => text,
+            Err(e) => {
+                info!("no sequence to play, couldn't read {PATH}: {e}");
+                return default();
+            }
+        };
+
+        ron::from_str(&text).unwrap_or_else(|e| {
+            warn!("couldn't parse {PATH}: {e}");
+            default()
+        })
+    }
+}
+
+struct Running {
+    cues: Vec<Cue>,
+    /// Cues before this one have fired
+    next: usize,
+    time: Stopwatch,
+}
+
+/// Plays the intro and credits sequences
+#[derive(Resource, Default)]
+pub struct Sequencer {
+    intro: Option<Vec<Cue>>,
+    credits: Option<Vec<Cue>>,
+    running: Option<Running>,
+    fade: Fade,
+    text: Option<Timer>,
+}
+
+/// Darkness of the overlay, going from `from` to `to` over the timer
+#[derive(Default)]
+struct Fade {
+    from: f32,
+    to: f32,
+    timer: Timer,
+}
+
+impl Fade {
+    fn darkness(&self) -> f32 {
+        // instant fades have a zero duration
+        let t = if self.timer.duration().is_zero() {
+            1.0
+        } else {
+            self.timer.percent()
+        };
+        self.from + (self.to - self.from) * t
+    }
+}
+
+#[derive(Component)]
+pub struct Overlay;
+
+#[derive(Component)]
+pub struct OverlayText;
+
+pub fn load_sequence(mut commands: Commands, font: Res<UiFont>) {
+    let Script {
+        mut intro,
+        mut credits,
+    } = Script::read();
+    for cues in [&mut intro, &mut credits] {
+        cues.sort_by(|a, b| a.at.total_cmp(&b.at));
+    }
+
+    commands.insert_resource(Sequencer {
+        intro: Some(intro),
+        credits: Some(credits),
+        ..default()
+    });
+
+    commands
+        .spawn((
+            Overlay,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::all(Val::Percent(100.0)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::NONE.into(),
+                ..default()
+            },
+        ))
+        .with_children(|overlay| {
+            overlay.spawn((
+                OverlayText,
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 24.0,
+                        color: Color::NONE,
+                    },
+                ),
+            ));
+        });
+}
+
+/// Starts the sequences at the right time and fires their cues
+#[allow(clippy::too_many_arguments)]
+pub fn run_sequence(
+    mut sequencer: ResMut<Sequencer>,
+    mut state: ResMut<State>,
+    mut palette: ResMut<Palette>,
+    mut text: Query<&mut Text, With<OverlayText>>,
+    loading: Res<Loading>,
+    player: Query<&Player>,
+    config: Res<Config>,
+    time: Res<Time>,
+) {
+    if !loading.done {
+        return;
+    }
+
+    let at_end = player.single().play_index + 1 >= config.frames;
+    let starting = if at_end {
+        sequencer.credits.take()
+    } else {
+        sequencer.intro.take()
+    };
+    if let Some(cues) = starting {
+        sequencer.running = Some(Running {
+            cues,
+            next: 0,
+            time: Stopwatch::new(),
+        });
+    }
+
+    let sequencer = &mut *sequencer;
+    let Some(running) = &mut sequencer.running else {
+        return;
+    };
+    running.time.tick(time.delta());
+
+    while let Some(cue) = running.cues.get(running.next) {
+        if cue.at > running.time.elapsed_secs() {
+            break;
+        }
+        running.next += 1;
+
+        match &cue.action {
+            Action::Text { text: line, secs } => {
+                text.single_mut().sections[0].value = line.clone();
+                sequencer.text = Some(Timer::from_seconds(*secs, TimerMode::Once));
+            }
+            Action::Fade { to, secs } => {
+                sequencer.fade = Fade {
+                    from: sequencer.fade.darkness(),
+                    to: *to,
+                    timer: Timer::from_seconds(*secs, TimerMode::Once),
+                };
+            }
+            Action::Play => *state = State::Playing,
+            Action::Pause => *state = State::Paused,
+            Action::Palette(new) => *palette = *new,
+        }
+    }
+}
+
+pub fn draw_overlay(
+    mut sequencer: ResMut<Sequencer>,
+    mut overlay: Query<&mut BackgroundColor, With<Overlay>>,
+    mut text: Query<&mut Text, With<OverlayText>>,
+    time: Res<Time>,
+) {
+    sequencer.fade.timer.tick(time.delta());
+    overlay.single_mut().0 = Color::rgba(0.0, 0.0, 0.0, sequencer.fade.darkness());
+
+    let alpha = match &mut sequencer.text {
+        Some(timer) => {
+            timer.tick(time.delta());
+            let fade_in = timer.elapsed_secs() / TEXT_FADE_SECS;
+            let fade_out = timer.remaining_secs() / TEXT_FADE_SECS;
+            fade_in.min(fade_out).min(1.0)
+        }
+        None => 0.0,
+    };
+    text.single_mut().sections[0].style.color = Color::rgba(1.0, 1.0, 1.0, alpha);
+}
@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+
+use crate::{
+    locale::tr,
+    sim::{Grid, Playfield},
+    toast::Toasts,
+    CurrentFrame, Particle,
+};
+
+/// Side of the density map cells, in pixels
+const CELL: u32 = 4;
+/// Box blur passes over the density map
+const BLUR_PASSES: usize = 2;
+/// How far the sparsest particles get lightened towards white
+const STRENGTH: f32 = 0.35;
+
+/// Soft pseudo-3D shading, lightening particles on the sparse edges of the silhouette
+/// so the crowded middle looks darker
+#[derive(Resource, Default)]
+pub struct DensityShading {
+    pub enabled: bool,
+    /// Blurred count of settled particles per cell, normalized to `[0, 1]`
+    density: Vec<f32>,
+    scratch: Vec<f32>,
+}
+
+pub fn shading_enabled(shading: Res<DensityShading>) -> bool {
+    shading.enabled
+}
+
+pub fn toggle_shading(
+    mut shading: ResMut<DensityShading>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::O) {
+        shading.enabled = !shading.enabled;
+        toasts.push(if shading.enabled {
+            tr("shading-on")
+        } else {
+            tr("shading-off")
+        });
+    }
+}
+
+/// 3x3 box blur of `map` into `out`
+fn blur(map: &[f32], out: &mut [f32], grid: &Grid) {
+    let (cols, rows) = (grid.cols as i32, grid.rows as i32);
+
+    for y in 0..rows {
+        for x in 0..cols {
+            let mut sum = 0.0;
+            let mut count = 0.0;
+            for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+                let (nx, ny) = (x + dx, y + dy);
+                if (0..cols).contains(&nx) && (0..rows).contains(&ny) {
+                    sum += map[(ny * cols + nx) as usize];
+                    count += 1.0;
+                }
+            }
+            out[(y * cols + x) as usize] = sum / count;
+        }
+    }
+}
+
+pub fn shade_particles(
+    mut shading: ResMut<DensityShading>,
+    mut particles: Query<(&Particle, &Transform, &mut Sprite)>,
+    current: Res<CurrentFrame>,
+    playfield: Res<Playfield>,
+) {
+    let grid = Grid::new(&playfield, CELL);
+    let shading = &mut *shading;
+
+    shading.density.clear();
+    shading.density.resize(grid.len(), 0.0);
+    shading.scratch.resize(grid.len(), 0.0);
+
+    for (standstill, transform, _) in &particles {
+        if standstill.0 == current.front.index {
+            shading.density[grid.cell_of(transform.translation.truncate(), &playfield)] += 1.0;
+        }
+    }
+
+    for _ in 0..BLUR_PASSES {
+        blur(&shading.density, &mut shading.scratch, &grid);
+        std::mem::swap(&mut shading.density, &mut shading.scratch);
+    }
+
+    let max = shading.density.iter().copied().fold(0.0, f32::max);
+    if max <= 0.0 {
+        return;
+    }
+
+    for (_, transform, mut sprite) in &mut particles {
+        let density = shading.density[grid.cell_of(transform.translation.truncate(), &playfield)];
+        let light = STRENGTH * (1.0 - density / max);
+
+        let [r, g, b, a] = sprite.color.as_rgba_f32();
+        let lighten = |c: f32| c + (1.0 - c) * light;
+        sprite.color = Color::rgba(lighten(r), lighten(g), lighten(b), a);
+    }
+}
@@ -0,0 +1,1095 @@
+//! The particle simulation itself, independent from rendering and the Bevy app,
+//! so it can run without a window.
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Pixels brighter than this are background, particles keep moving on them,
+/// unless a frame picks its own threshold
+pub const THRESHOLD: u8 = 128;
+
+/// Ordered dithering thresholds of a 4x4 tile, in sixteenths of the brightness range
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Largest distance a jittering particle moves on each axis per tick
+pub const JITTER: f32 = 5.0;
+
+/// Angle an orbiting particle travels around the centroid each tick
+const ORBIT_SPEED: f32 = 0.05;
+/// Fraction of its distance to the centroid an orbiting particle keeps each tick
+const ORBIT_DECAY: f32 = 0.995;
+
+/// Speed an attracted particle gains towards the nearest dark pixel each tick,
+/// at the full [`JITTER`] amplitude
+const ATTRACT_ACCEL: f32 = 0.6;
+/// Fraction of its velocity an attracted particle keeps each tick
+const ATTRACT_DAMPING: f32 = 0.9;
+/// Fastest an attracted particle moves, in pixels per tick
+const ATTRACT_MAX_SPEED: f32 = 6.0;
+
+/// Distance a full strength [`Well`] pulls a particle right next to it each tick
+const WELL_PULL: f32 = 3.0;
+/// Distance at which the pull of a [`Well`] is halved
+const WELL_FALLOFF: f32 = 100.0;
+
+/// Radius of the [`SpawnPattern::Ring`], as a fraction of the smaller half size
+const RING_RADIUS: f32 = 0.8;
+/// Angle between two consecutive particles of the [`SpawnPattern::Spiral`]
+const GOLDEN_ANGLE: f32 = 2.399_963;
+/// Width of the band [`SpawnPattern::Edges`] spawns particles in, in pixels
+const EDGE_DEPTH: f32 = 4.0;
+
+/// Area the particles live in, matching the dimensions of the source frames
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct Playfield {
+    pub size: UVec2,
+}
+
+impl Playfield {
+    pub fn new(size: UVec2) -> Self {
+        Self { size }
+    }
+
+    pub fn half_size(&self) -> Vec2 {
+        self.size.as_vec2() / 2.0
+    }
+
+    /// Index of the pixel under `pos` in a frame of this playfield's size
+    pub fn pixel_index(&self, pos: Vec2) -> usize {
+        let pos = (pos + self.half_size()).as_uvec2();
+        let x = pos.x.min(self.size.x - 1);
+        let y = (self.size.y - 1).saturating_sub(pos.y);
+
+        (y * self.size.x + x) as usize
+    }
+
+    /// World position of the center of a pixel, the inverse of [`Playfield::pixel_index`]
+    pub fn position(&self, pixel: Vec2) -> Vec2 {
+        let y = (self.size.y - 1) as f32 - pixel.y;
+        Vec2::new(pixel.x, y) - self.half_size() + 0.5
+    }
+
+    /// Random position on the pixel grid of the playfield
+    pub fn random_position(&self, rng: &mut impl Rng) -> Vec2 {
+        let half = self.size.as_ivec2() / 2;
+        IVec2::new(
+            rng.gen_range(-half.x..half.x),
+            rng.gen_range(-half.y..half.y),
+        )
+        .as_vec2()
+    }
+
+    /// Brings a position that left the playfield back in from the other side
+    pub fn wrap(&self, pos: &mut Vec3) {
+        let (half, size) = (self.half_size(), self.size.as_vec2());
+
+        if pos.x < -half.x {
+            pos.x += size.x;
+        } else if pos.x >= half.x {
+            pos.x -= size.x;
+        }
+        if pos.y < -half.y {
+            pos.y += size.y;
+        } else if pos.y >= half.y {
+            pos.y -= size.y;
+        }
+    }
+}
+
+/// Where the particles start out, before the first frame pulls them in
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum SpawnPattern {
+    /// Uniformly random over the playfield
+    #[default]
+    Random,
+    /// Evenly spaced rows and columns
+    Grid,
+    /// A circle around the center
+    Ring,
+    /// Sunflower spiral, each particle a golden angle further around the center
+    Spiral,
+    /// A thin band along the borders
+    Edges,
+}
+
+impl SpawnPattern {
+    /// Position of the `idx`th of `count` particles, on the pixel grid
+    pub fn position(
+        self,
+        idx: usize,
+        count: usize,
+        playfield: &Playfield,
+        rng: &mut impl Rng,
+    ) -> Vec2 {
+        let half = playfield.half_size();
+        // fraction of the way through the particles
+        let t = (idx as f32 + 0.5) / count.max(1) as f32;
+
+        let pos = match self {
+            SpawnPattern::Random => return playfield.random_position(rng),
+            SpawnPattern::Grid => {
+                let size = playfield.size.as_vec2();
+                let cols = (count as f32 * size.x / size.y).sqrt().ceil().max(1.0) as usize;
+                let rows = count.div_ceil(cols).max(1);
+                let cell = size / Vec2::new(cols as f32, rows as f32);
+                let (col, row) = (idx % cols, idx / cols);
+                (Vec2::new(col as f32, row as f32) + 0.5) * cell - half
+            }
+            SpawnPattern::Ring => {
+                let radius = RING_RADIUS * half.min_element() + rng.gen_range(-2.0..=2.0);
+                Vec2::from_angle(t * TAU) * radius
+            }
+            SpawnPattern::Spiral => {
+                // the square root spreads particles evenly over the area of the disc
+                let radius = t.sqrt() * half.min_element();
+                Vec2::from_angle(idx as f32 * GOLDEN_ANGLE) * radius
+            }
+            SpawnPattern::Edges => {
+                let size = playfield.size.as_vec2();
+                let along = t * 2.0 * (size.x + size.y);
+                let depth = rng.gen_range(0.0..EDGE_DEPTH);
+                let on_edge = if along < size.x {
+                    Vec2::new(along, depth)
+                } else if along < size.x + size.y {
+                    Vec2::new(size.x - depth, along - size.x)
+                } else if along < 2.0 * size.x + size.y {
+                    Vec2::new(2.0 * size.x + size.y - along, size.y - depth)
+                } else {
+                    Vec2::new(depth, 2.0 * (size.x + size.y) - along)
+                };
+                on_edge - half
+            }
+        };
+
+        pos.round().clamp(-half, half - 1.0)
+    }
+}
+
+/// Coarse grid of square cells over the playfield, for per-region statistics
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Grid {
+    /// Side of a cell, in pixels
+    pub cell: u32,
+    pub cols: u32,
+    pub rows: u32,
+}
+
+impl Grid {
+    pub fn new(playfield: &Playfield, cell: u32) -> Self {
+        Self {
+            cell,
+            cols: playfield.size.x.div_ceil(cell),
+            rows: playfield.size.y.div_ceil(cell),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        (self.cols * self.rows) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Cell containing a pixel, given in frame coordinates
+    pub fn cell_of_pixel(&self, pixel: UVec2) -> usize {
+        let cell = pixel / self.cell;
+        (cell.y * self.cols + cell.x) as usize
+    }
+
+    /// Cell under a world position
+    pub fn cell_of(&self, pos: Vec2, playfield: &Playfield) -> usize {
+        let idx = playfield.pixel_index(pos) as u32;
+        self.cell_of_pixel(UVec2::new(idx % playfield.size.x, idx / playfield.size.x))
+    }
+
+    /// Random pixel inside a cell, in frame coordinates
+    pub fn random_pixel(&self, cell: usize, playfield: &Playfield, rng: &mut impl Rng) -> UVec2 {
+        let origin = UVec2::new(cell as u32 % self.cols, cell as u32 / self.cols) * self.cell;
+        let end = (origin + self.cell).min(playfield.size);
+        UVec2::new(
+            rng.gen_range(origin.x..end.x),
+            rng.gen_range(origin.y..end.y),
+        )
+    }
+}
+
+/// Borrowed pixels of a frame, row major from the top left
+#[derive(Clone, Copy)]
+pub struct FrameView<'a> {
+    data: &'a [u8],
+    size: UVec2,
+    /// Bytes per pixel, only the first one is sampled unless `luma` is set
+    block_size: usize,
+    /// Brightest value that counts as dark
+    threshold: u8,
+    /// Whether the silhouette is light on a dark background
+    inverted: bool,
+    /// Weighs the red, green and blue channels into a brightness instead of reading red
+    luma: bool,
+    /// Dithers gray pixels instead of cutting them off at the threshold
+    dithered: bool,
+}
+
+impl<'a> FrameView<'a> {
+    pub fn new(data: &'a [u8], size: UVec2, block_size: usize) -> Self {
+        assert!(data.len() >= (size.x * size.y) as usize * block_size);
+        Self {
+            data,
+            size,
+            block_size,
+            threshold: THRESHOLD,
+            inverted: false,
+            luma: false,
+            dithered: false,
+        }
+    }
+
+    pub fn with_threshold(self, threshold: u8) -> Self {
+        Self { threshold, ..self }
+    }
+
+    /// Makes the light pixels the ones particles settle on
+    pub fn inverted(self, inverted: bool) -> Self {
+        Self { inverted, ..self }
+    }
+
+    /// Reads color frames by their luma rather than by their red channel
+    pub fn with_luma(self, luma: bool) -> Self {
+        Self { luma, ..self }
+    }
+
+    /// Makes gray pixels dark in an ordered pattern as dense as they are dark,
+    /// ignoring the threshold
+    pub fn dithered(self, dithered: bool) -> Self {
+        Self { dithered, ..self }
+    }
+
+    pub fn from_image(image: &'a Image) -> Self {
+        let block_size = image.texture_descriptor.format.describe().block_size;
+        Self::new(&image.data, image.size().as_uvec2(), block_size as usize)
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub fn value(&self, idx: usize) -> u8 {
+        let start = idx * self.block_size;
+        self.brightness(&self.data[start..start + self.block_size])
+    }
+
+    fn brightness(&self, pixel: &[u8]) -> u8 {
+        match pixel {
+            // Rec. 709 weights, out of 256
+            &[r, g, b, ..] if self.luma => {
+                ((54 * r as u32 + 183 * g as u32 + 19 * b as u32) >> 8) as u8
+            }
+            _ => pixel[0],
+        }
+    }
+
+    /// Whether the pixel is part of the silhouette, dark unless the view is inverted
+    pub fn is_dark(&self, idx: usize) -> bool {
+        let value = self.value(idx);
+        let dark = if self.dithered {
+            let (x, y) = (idx % self.size.x as usize, idx / self.size.x as usize);
+            // centered in its sixteenth, so black is always dark and white never is
+            let level = BAYER[y % 4][x % 4] as u32 * 16 + 8;
+            (value as u32) < level
+        } else {
+            value <= self.threshold
+        };
+        dark != self.inverted
+    }
+
+    /// Whether the pixel is part of the silhouette and next to one that isn't,
+    /// or to the border of the frame
+    pub fn is_edge(&self, idx: usize) -> bool {
+        if !self.is_dark(idx) {
+            return false;
+        }
+        let (width, height) = (self.size.x as usize, self.size.y as usize);
+        let (x, y) = (idx % width, idx / width);
+        x == 0
+            || y == 0
+            || x + 1 == width
+            || y + 1 == height
+            || [idx - 1, idx + 1, idx - width, idx + width]
+                .into_iter()
+                .any(|neighbor| !self.is_dark(neighbor))
+    }
+
+    fn pixels(&self) -> impl Iterator<Item = u8> + '_ {
+        let len = (self.size.x * self.size.y) as usize;
+        self.data
+            .chunks_exact(self.block_size)
+            .take(len)
+            .map(|pixel| self.brightness(pixel))
+    }
+
+    /// Threshold that best splits this frame's pixels into a dark and a light class,
+    /// using Otsu's method. Frames of a single color keep the default [`THRESHOLD`].
+    pub fn otsu_threshold(&self) -> u8 {
+        let mut histogram = [0u32; 256];
+        for value in self.pixels() {
+            histogram[value as usize] += 1;
+        }
+
+        let total: f64 = histogram.iter().map(|&count| count as f64).sum();
+        let sum: f64 = histogram
+            .iter()
+            .enumerate()
+            .map(|(value, &count)| value as f64 * count as f64)
+            .sum();
+
+        let (mut dark_weight, mut dark_sum) = (0.0, 0.0);
+        let (mut best, mut best_variance) = (THRESHOLD, 0.0);
+        for (value, &count) in histogram.iter().enumerate() {
+            dark_weight += count as f64;
+            dark_sum += value as f64 * count as f64;
+
+            let light_weight = total - dark_weight;
+            if dark_weight == 0.0 || light_weight == 0.0 {
+                continue;
+            }
+
+            let mean_difference = dark_sum / dark_weight - (sum - dark_sum) / light_weight;
+            let variance = dark_weight * light_weight * mean_difference * mean_difference;
+            if variance > best_variance {
+                best = value as u8;
+                best_variance = variance;
+            }
+        }
+        best
+    }
+
+    /// Center of mass of the dark pixels, in world space
+    pub fn centroid(&self, playfield: &Playfield) -> Option<Vec2> {
+        let width = self.size.x as usize;
+
+        let mut sum = Vec2::ZERO;
+        let mut count = 0;
+        let len = (self.size.x * self.size.y) as usize;
+        for idx in (0..len).filter(|&idx| self.is_dark(idx)) {
+            sum += Vec2::new((idx % width) as f32, (idx / width) as f32);
+            count += 1;
+        }
+
+        (count > 0).then(|| playfield.position(sum / count as f32))
+    }
+
+    /// Fraction of the pixels darker than the threshold, regardless of inversion
+    pub fn dark_fraction(&self) -> f32 {
+        let len = (self.size.x * self.size.y) as f32;
+        let dark = self
+            .pixels()
+            .filter(|&value| value <= self.threshold)
+            .count();
+        dark as f32 / len.max(1.0)
+    }
+
+    /// Number of dark pixels in each cell of the grid
+    pub fn dark_per_cell(&self, grid: &Grid) -> Vec<u32> {
+        let mut counts = vec![0; grid.len()];
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                if self.is_dark((y * self.size.x + x) as usize) {
+                    counts[grid.cell_of_pixel(UVec2::new(x, y))] += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    /// Fraction of pixels that went from dark to light or the other way around
+    pub fn difference(&self, other: &FrameView) -> f32 {
+        assert_eq!(self.size, other.size);
+
+        let len = (self.size.x * self.size.y) as usize;
+        let changed = (0..len)
+            .filter(|&idx| self.is_dark(idx) != other.is_dark(idx))
+            .count();
+
+        changed as f32 / len as f32
+    }
+
+    /// Mixes the silhouette of this frame towards the one of `other` by `t` in `[0, 1]`,
+    /// writing one byte per pixel to `out`: black where both are dark, white where neither
+    /// is, and gray in between, for a dithered view to settle on more of the pixels going
+    /// dark the further along `t` is
+    pub fn blend(&self, other: &FrameView, t: f32, out: &mut Vec<u8>) {
+        assert_eq!(self.size, other.size);
+
+        let lightness = |frame: &FrameView, idx| if frame.is_dark(idx) { 0.0 } else { 255.0 };
+        let len = (self.size.x * self.size.y) as usize;
+        out.clear();
+        out.extend((0..len).map(|idx| {
+            let (from, to) = (lightness(self, idx), lightness(other, idx));
+            (from + (to - from) * t).round() as u8
+        }));
+    }
+}
+
+/// Reads a frame in world space, for driving other effects from the same pixels
+/// the particles follow
+#[derive(Clone, Copy)]
+pub struct FrameSampler<'a> {
+    frame: FrameView<'a>,
+    playfield: Playfield,
+}
+
+impl<'a> FrameSampler<'a> {
+    pub fn new(frame: FrameView<'a>, playfield: Playfield) -> Self {
+        assert_eq!(frame.size(), playfield.size);
+        Self { frame, playfield }
+    }
+
+    pub fn frame(&self) -> FrameView<'a> {
+        self.frame
+    }
+
+    /// Brightness of the pixel under `pos`, from 0 for black to 1 for white.
+    /// Positions outside the playfield read the closest pixel on its edge.
+    pub fn luminance_at(&self, pos: Vec2) -> f32 {
+        self.frame.value(self.playfield.pixel_index(pos)) as f32 / u8::MAX as f32
+    }
+
+    /// Whether particles settle on the pixel under `pos`
+    pub fn is_dark_at(&self, pos: Vec2) -> bool {
+        self.frame.is_dark(self.playfield.pixel_index(pos))
+    }
+}
+
+/// How particles that aren't on the silhouette move
+#[derive(Resource, Clone, Copy, Default, PartialEq, Eq, Debug, Deserialize)]
+pub enum MovementMode {
+    /// Random walk until they land on a black pixel
+    #[default]
+    Jitter,
+    /// Spiral in around the centroid of the silhouette
+    Orbit,
+    /// Accelerate towards the nearest black pixel, slowed down by damping
+    Attract,
+    /// Slide down the distance field to the edge of the silhouette
+    Flow,
+    /// Seek the edge of the silhouette from either side, settling only along it
+    Outline,
+}
+
+/// Nearest dark pixel of every pixel of a frame, propagated in two passes over the frame
+/// like a chamfer distance transform, so it is close to but not always the exact nearest
+pub struct NearestDark {
+    size: UVec2,
+    /// Index of the nearest dark pixel, `u32::MAX` when the frame has none
+    nearest: Vec<u32>,
+}
+
+impl NearestDark {
+    pub fn new(frame: &FrameView) -> Self {
+        let size = frame.size();
+        let len = (size.x * size.y) as usize;
+        let mut nearest: Vec<u32> = (0..len as u32)
+            .map(|idx| {
+                if frame.is_dark(idx as usize) {
+                    idx
+                } else {
+                    u32::MAX
+                }
+            })
+            .collect();
+
+        let distance = |from: u32, to: u32| {
+            let (from, to) = (
+                UVec2::new(from % size.x, from / size.x).as_ivec2(),
+                UVec2::new(to % size.x, to / size.x).as_ivec2(),
+            );
+            let offset = from - to;
+            offset.dot(offset)
+        };
+        let mut relax = |x: u32, y: u32, neighbors: [(i32, i32); 4]| {
+            let idx = y * size.x + x;
+            for (dx, dy) in neighbors {
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                if nx < 0 || ny < 0 || nx >= size.x as i32 || ny >= size.y as i32 {
+                    continue;
+                }
+                let candidate = nearest[(ny as u32 * size.x + nx as u32) as usize];
+                let current = nearest[idx as usize];
+                if candidate != u32::MAX
+                    && (current == u32::MAX || distance(idx, candidate) < distance(idx, current))
+                {
+                    nearest[idx as usize] = candidate;
+                }
+            }
+        };
+
+        // neighbors already visited by each pass
+        for y in 0..size.y {
+            for x in 0..size.x {
+                relax(x, y, [(-1, 0), (-1, -1), (0, -1), (1, -1)]);
+            }
+        }
+        for y in (0..size.y).rev() {
+            for x in (0..size.x).rev() {
+                relax(x, y, [(1, 0), (1, 1), (0, 1), (-1, 1)]);
+            }
+        }
+
+        Self { size, nearest }
+    }
+
+    fn pixel(&self, idx: u32) -> UVec2 {
+        UVec2::new(idx % self.size.x, idx / self.size.x)
+    }
+
+    /// World position of the dark pixel nearest to `pos`
+    pub fn target(&self, pos: Vec2, playfield: &Playfield) -> Option<Vec2> {
+        if playfield.size != self.size {
+            return None;
+        }
+        let nearest = self.nearest[playfield.pixel_index(pos)];
+        if nearest == u32::MAX {
+            return None;
+        }
+        Some(playfield.position(self.pixel(nearest).as_vec2()))
+    }
+
+    /// Distance in pixels from pixel `idx` to the nearest dark one
+    fn distance(&self, idx: usize) -> f32 {
+        match self.nearest[idx] {
+            u32::MAX => f32::INFINITY,
+            nearest => {
+                let pixel = self.pixel(idx as u32).as_vec2();
+                pixel.distance(self.pixel(nearest).as_vec2())
+            }
+        }
+    }
+}
+
+/// Signed distance from every pixel of a frame to the edge of its silhouette,
+/// positive on the background and negative on the silhouette
+pub struct DistanceField {
+    nearest: NearestDark,
+    distance: Vec<f32>,
+    /// Whether each pixel is on the edge of the silhouette, see [`FrameView::is_edge`]
+    edges: Vec<bool>,
+}
+
+impl DistanceField {
+    pub fn new(frame: &FrameView) -> Self {
+        let nearest = NearestDark::new(frame);
+        let light = NearestDark::new(&FrameView {
+            inverted: !frame.inverted,
+            ..*frame
+        });
+
+        let distance = (0..nearest.nearest.len())
+            .map(|idx| {
+                if frame.is_dark(idx) {
+                    -light.distance(idx)
+                } else {
+                    nearest.distance(idx)
+                }
+            })
+            .collect();
+        let edges = (0..nearest.nearest.len())
+            .map(|idx| frame.is_edge(idx))
+            .collect();
+        Self {
+            nearest,
+            distance,
+            edges,
+        }
+    }
+
+    pub fn nearest(&self) -> &NearestDark {
+        &self.nearest
+    }
+
+    /// Whether pixel `idx` is on the edge of the silhouette
+    pub fn is_edge(&self, idx: usize) -> bool {
+        self.edges[idx]
+    }
+
+    /// Signed distance of the pixel under `pos`, infinite when the frame is all one color
+    pub fn distance_at(&self, pos: Vec2, playfield: &Playfield) -> f32 {
+        self.distance[playfield.pixel_index(pos)]
+    }
+
+    /// Direction in which the distance grows fastest at `pos`, away from the silhouette,
+    /// zero on flat or infinite parts of the field
+    pub fn gradient(&self, pos: Vec2, playfield: &Playfield) -> Vec2 {
+        let at = |offset: Vec2| self.distance_at(pos + offset, playfield);
+        let gradient = Vec2::new(at(Vec2::X) - at(-Vec2::X), at(Vec2::Y) - at(-Vec2::Y));
+        if gradient.is_finite() {
+            gradient.normalize_or_zero()
+        } else {
+            Vec2::ZERO
+        }
+    }
+}
+
+/// Pixels of the silhouette particles settle on
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Deserialize)]
+pub enum Settle {
+    /// Any of them
+    #[default]
+    Fill,
+    /// Only those next to the background, outlining the silhouette
+    Edges,
+}
+
+/// What particles off the silhouette steer by
+#[derive(Clone, Copy)]
+pub struct Steering<'a> {
+    pub mode: MovementMode,
+    pub settle: Settle,
+    /// Needed to orbit, particles jitter without it
+    pub centroid: Option<Vec2>,
+    /// Needed to be attracted or to flow, particles jitter without it
+    pub field: Option<&'a DistanceField>,
+}
+
+impl Steering<'_> {
+    /// Whether a particle settles on pixel `idx` of `frame`. Edges are read from the
+    /// field when there is one, which was computed when the frame was loaded.
+    fn settles(&self, frame: &FrameView, idx: usize) -> bool {
+        let settle = match self.mode {
+            MovementMode::Outline => Settle::Edges,
+            _ => self.settle,
+        };
+        match (settle, self.field) {
+            (Settle::Fill, _) => frame.is_dark(idx),
+            (Settle::Edges, Some(field)) if field.nearest.size == frame.size() => {
+                field.is_edge(idx)
+            }
+            (Settle::Edges, _) => frame.is_edge(idx),
+        }
+    }
+
+    /// New position of an unsettled particle at `pos`, `jitter` being the amplitude of random moves.
+    /// Only attracted particles keep a velocity between ticks.
+    pub fn step(
+        &self,
+        pos: Vec2,
+        velocity: &mut Vec2,
+        playfield: &Playfield,
+        jitter: f32,
+        rng: &mut impl Rng,
+    ) -> Vec2 {
+        match (self.mode, self.centroid, self.field) {
+            (MovementMode::Orbit, Some(center), _) => {
+                let offset = Vec2::from_angle(ORBIT_SPEED).rotate(pos - center);
+                // a bit of noise, so particles don't collapse on a centroid outside the shape
+                let noise = Vec2::new(rng.gen_range(-1..=1) as f32, rng.gen_range(-1..=1) as f32);
+                center + offset * ORBIT_DECAY + noise
+            }
+            (MovementMode::Attract, _, Some(field)) => {
+                let Some(target) = field.nearest().target(pos, playfield) else {
+                    return Self::jitter(pos, jitter, rng);
+                };
+                // the jitter amplitude scales the pull, so cuts and automation still apply
+                let accel = (target - pos).normalize_or_zero() * ATTRACT_ACCEL * jitter / JITTER;
+                *velocity =
+                    ((*velocity + accel) * ATTRACT_DAMPING).clamp_length_max(ATTRACT_MAX_SPEED);
+                pos + *velocity
+            }
+            (MovementMode::Flow, _, Some(field)) => {
+                let gradient = field.gradient(pos, playfield);
+                if gradient == Vec2::ZERO {
+                    return Self::jitter(pos, jitter, rng);
+                }
+                // no further than the edge, with a bit of noise to spread out along it
+                let distance = field.distance_at(pos, playfield).min(jitter);
+                let noise = Vec2::new(rng.gen_range(-1..=1) as f32, rng.gen_range(-1..=1) as f32);
+                pos - gradient * distance.max(1.0) + noise
+            }
+            (MovementMode::Outline, _, Some(field)) => {
+                let gradient = field.gradient(pos, playfield);
+                if gradient == Vec2::ZERO {
+                    return Self::jitter(pos, jitter, rng);
+                }
+                // down the field from the background, up it from inside the silhouette
+                let distance = field.distance_at(pos, playfield).clamp(-jitter, jitter);
+                let step = distance.abs().max(1.0).copysign(distance);
+                let noise = Vec2::new(rng.gen_range(-1..=1) as f32, rng.gen_range(-1..=1) as f32);
+                pos - gradient * step + noise
+            }
+            _ => Self::jitter(pos, jitter, rng),
+        }
+    }
+
+    fn jitter(pos: Vec2, jitter: f32, rng: &mut impl Rng) -> Vec2 {
+        let jitter = jitter.round() as i32;
+        pos + Vec2::new(
+            rng.gen_range(-jitter..=jitter) as f32,
+            rng.gen_range(-jitter..=jitter) as f32,
+        )
+    }
+}
+
+/// Attractor point pulling particles towards it
+#[derive(Clone, Copy, Debug)]
+pub struct Well {
+    pub position: Vec2,
+    /// In `[0, 1]`
+    pub strength: f32,
+}
+
+impl Well {
+    /// Displacement of a particle at `pos` for one tick
+    pub fn pull(&self, pos: Vec2) -> Vec2 {
+        let offset = self.position - pos;
+        let distance = offset.length();
+        if distance < 1.0 {
+            return Vec2::ZERO;
+        }
+
+        let falloff = WELL_FALLOFF / (WELL_FALLOFF + distance);
+        offset / distance * self.strength * WELL_PULL * falloff
+    }
+}
+
+/// What happened to a particle during a [`step`]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Step {
+    /// On the silhouette, stays put
+    Settled,
+    /// On the background, moved somewhere else
+    Moved,
+}
+
+/// Randomness of the particle moves. Every particle draws from its own generator, seeded
+/// from the tick and its id, so its moves don't depend on which thread steps it.
+/// Systems drawing outside the parallel step, like respawns, take theirs in turn.
+#[derive(Resource, Clone, Debug)]
+pub struct SimRng {
+    seed: u64,
+    tick: u32,
+    /// Generators handed out by [`SimRng::serial`]
+    draws: u64,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            tick: 0,
+            draws: 0,
+        }
+    }
+
+    /// Generator for a system that draws outside the parallel step, a new one on every call
+    pub fn serial(&mut self) -> SmallRng {
+        self.draws += 1;
+        // the top bit keeps clear of the particle generators for the first 2^31 ticks
+        SmallRng::seed_from_u64(self.seed ^ (1 << 63 | self.draws))
+    }
+
+    /// Generator of the particle `id` for the current tick
+    pub fn particle(&self, id: u32) -> SmallRng {
+        SmallRng::seed_from_u64(self.seed ^ ((self.tick as u64) << 32 | id as u64))
+    }
+
+    /// Starts over the draws from the seed, as on launch
+    pub fn restart(&mut self) {
+        *self = Self::new(self.seed);
+    }
+
+    pub fn advance(&mut self) {
+        self.tick = self.tick.wrapping_add(1);
+    }
+}
+
+/// Moves one particle for one tick of the simulation
+pub fn step(
+    pos: &mut Vec3,
+    velocity: &mut Vec2,
+    frame: &FrameView,
+    playfield: &Playfield,
+    steering: &Steering,
+    jitter: f32,
+    rng: &mut impl Rng,
+) -> Step {
+    let idx = playfield.pixel_index(pos.truncate());
+    if steering.settles(frame, idx) {
+        playfield.wrap(pos);
+        *velocity = Vec2::ZERO;
+        Step::Settled
+    } else {
+        // if on opposite color, move
+        drift(pos, velocity, playfield, steering, jitter, rng);
+        Step::Moved
+    }
+}
+
+/// Moves a particle for one tick as if it stood on the background, ignoring the frame
+pub fn drift(
+    pos: &mut Vec3,
+    velocity: &mut Vec2,
+    playfield: &Playfield,
+    steering: &Steering,
+    jitter: f32,
+    rng: &mut impl Rng,
+) {
+    *pos = steering
+        .step(pos.truncate(), velocity, playfield, jitter, rng)
+        .extend(pos.z);
+    playfield.wrap(pos);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: UVec2 = UVec2::new(8, 8);
+
+    /// White 8x8 frame with a black 4x4 square from (2, 2) to (5, 5)
+    fn square() -> Vec<u8> {
+        (0..SIZE.x * SIZE.y)
+            .map(|idx| {
+                let (x, y) = (idx % SIZE.x, idx / SIZE.x);
+                if (2..6).contains(&x) && (2..6).contains(&y) {
+                    0
+                } else {
+                    255
+                }
+            })
+            .collect()
+    }
+
+    fn idx(x: u32, y: u32) -> usize {
+        (y * SIZE.x + x) as usize
+    }
+
+    fn world(x: u32, y: u32) -> Vec2 {
+        Playfield::new(SIZE).position(UVec2::new(x, y).as_vec2())
+    }
+
+    #[test]
+    fn pixel_index_inverts_position() {
+        let playfield = Playfield::new(UVec2::new(8, 6));
+        for y in 0..6 {
+            for x in 0..8 {
+                let pos = playfield.position(Vec2::new(x as f32, y as f32));
+                assert_eq!(playfield.pixel_index(pos), (y * 8 + x) as usize);
+            }
+        }
+        // rows go down the frame and up the world
+        assert!(playfield.position(Vec2::ZERO).y > playfield.position(Vec2::Y).y);
+    }
+
+    #[test]
+    fn wrap_brings_positions_back_in() {
+        let playfield = Playfield::new(SIZE);
+        let mut inside = Vec3::new(1.5, -2.5, 3.0);
+        playfield.wrap(&mut inside);
+        assert_eq!(inside, Vec3::new(1.5, -2.5, 3.0));
+
+        let mut outside = Vec3::new(4.5, -4.5, 3.0);
+        playfield.wrap(&mut outside);
+        assert_eq!(outside, Vec3::new(-3.5, 3.5, 3.0));
+        assert_eq!(
+            playfield.pixel_index(outside.truncate()),
+            idx(0, 0),
+            "wrapped to the opposite corner"
+        );
+
+        // left through the bottom right corner, back in through the top left
+        for start in [world(7, 7), world(0, 0)] {
+            let mut pos = start.extend(0.0);
+            pos += Vec3::new(8.0, -8.0, 0.0);
+            playfield.wrap(&mut pos);
+            assert_eq!(pos.truncate(), start);
+        }
+    }
+
+    #[test]
+    fn centroid_is_center_of_dark_pixels() {
+        let data = square();
+        let frame = FrameView::new(&data, SIZE, 1);
+        let playfield = Playfield::new(SIZE);
+        assert_eq!(frame.centroid(&playfield), Some(Vec2::ZERO));
+
+        let white = vec![255; data.len()];
+        assert_eq!(FrameView::new(&white, SIZE, 1).centroid(&playfield), None);
+    }
+
+    #[test]
+    fn otsu_threshold_splits_two_shades() {
+        let data: Vec<u8> = (0..64)
+            .map(|idx| if idx % 2 == 0 { 40 } else { 200 })
+            .collect();
+        let threshold = FrameView::new(&data, SIZE, 1).otsu_threshold();
+        assert!((40..200).contains(&threshold), "got {threshold}");
+
+        let gray = vec![90; 64];
+        assert_eq!(FrameView::new(&gray, SIZE, 1).otsu_threshold(), THRESHOLD);
+    }
+
+    #[test]
+    fn is_dark_follows_threshold_and_inversion() {
+        let data = [0, 100, 128, 129, 255];
+        let frame = FrameView::new(&data, UVec2::new(5, 1), 1);
+        let dark: Vec<_> = (0..5).map(|idx| frame.is_dark(idx)).collect();
+        assert_eq!(dark, [true, true, true, false, false]);
+
+        let frame = frame.with_threshold(50).inverted(true);
+        let dark: Vec<_> = (0..5).map(|idx| frame.is_dark(idx)).collect();
+        assert_eq!(dark, [false, true, true, true, true]);
+    }
+
+    #[test]
+    fn dithering_covers_gray_as_densely_as_it_is_dark() {
+        let tile = UVec2::new(4, 4);
+        let count = |value: u8| {
+            let data = vec![value; 16];
+            let frame = FrameView::new(&data, tile, 1).dithered(true);
+            (0..16).filter(|&idx| frame.is_dark(idx)).count()
+        };
+        assert_eq!(count(0), 16);
+        assert_eq!(count(64), 12);
+        assert_eq!(count(128), 8);
+        assert_eq!(count(255), 0);
+    }
+
+    #[test]
+    fn is_edge_outlines_the_silhouette() {
+        let data = square();
+        let frame = FrameView::new(&data, SIZE, 1);
+        assert!(frame.is_edge(idx(2, 2)));
+        assert!(frame.is_edge(idx(5, 3)));
+        assert!(!frame.is_edge(idx(3, 3)), "inside");
+        assert!(!frame.is_edge(idx(1, 3)), "on the background");
+
+        let black = vec![0; data.len()];
+        let frame = FrameView::new(&black, SIZE, 1);
+        assert!(frame.is_edge(idx(0, 4)), "on the border of the frame");
+        assert!(!frame.is_edge(idx(4, 4)));
+    }
+
+    #[test]
+    fn nearest_dark_finds_the_closest_corner() {
+        let data = square();
+        let playfield = Playfield::new(SIZE);
+        let nearest = NearestDark::new(&FrameView::new(&data, SIZE, 1));
+        assert_eq!(nearest.target(world(0, 0), &playfield), Some(world(2, 2)));
+        assert_eq!(nearest.target(world(7, 4), &playfield), Some(world(5, 4)));
+        assert_eq!(nearest.target(world(3, 3), &playfield), Some(world(3, 3)));
+
+        let white = vec![255; data.len()];
+        let nearest = NearestDark::new(&FrameView::new(&white, SIZE, 1));
+        assert_eq!(nearest.target(world(0, 0), &playfield), None);
+    }
+
+    #[test]
+    fn distance_field_is_signed_by_side() {
+        let data = square();
+        let playfield = Playfield::new(SIZE);
+        let field = DistanceField::new(&FrameView::new(&data, SIZE, 1));
+        assert_eq!(field.distance_at(world(0, 3), &playfield), 2.0);
+        assert_eq!(field.distance_at(world(3, 3), &playfield), -2.0);
+        assert!(field.is_edge(idx(2, 4)));
+        assert!(!field.is_edge(idx(3, 4)));
+
+        // the distance grows away from the square
+        let gradient = field.gradient(world(0, 3), &playfield);
+        assert!(gradient.x < 0.0, "got {gradient}");
+    }
+
+    #[test]
+    fn step_settles_only_on_the_silhouette() {
+        let data = square();
+        let frame = FrameView::new(&data, SIZE, 1);
+        let playfield = Playfield::new(SIZE);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut steering = Steering {
+            mode: MovementMode::Jitter,
+            settle: Settle::Fill,
+            centroid: None,
+            field: None,
+        };
+
+        let mut pos = world(3, 3).extend(0.0);
+        let mut velocity = Vec2::ONE;
+        let result = step(
+            &mut pos,
+            &mut velocity,
+            &frame,
+            &playfield,
+            &steering,
+            JITTER,
+            &mut rng,
+        );
+        assert_eq!(result, Step::Settled);
+        assert_eq!(pos, world(3, 3).extend(0.0));
+        assert_eq!(velocity, Vec2::ZERO);
+
+        let mut pos = world(0, 0).extend(0.0);
+        let result = step(
+            &mut pos,
+            &mut velocity,
+            &frame,
+            &playfield,
+            &steering,
+            JITTER,
+            &mut rng,
+        );
+        assert_eq!(result, Step::Moved);
+
+        // only the outline holds particles when settling on edges
+        steering.settle = Settle::Edges;
+        let mut pos = world(3, 3).extend(0.0);
+        let result = step(
+            &mut pos,
+            &mut velocity,
+            &frame,
+            &playfield,
+            &steering,
+            JITTER,
+            &mut rng,
+        );
+        assert_eq!(result, Step::Moved);
+        let mut pos = world(2, 3).extend(0.0);
+        let result = step(
+            &mut pos,
+            &mut velocity,
+            &frame,
+            &playfield,
+            &steering,
+            JITTER,
+            &mut rng,
+        );
+        assert_eq!(result, Step::Settled);
+    }
+
+    #[test]
+    fn blend_settles_in_proportion() {
+        let (from, to) = (square(), vec![0; 64]);
+        let (from, to) = (FrameView::new(&from, SIZE, 1), FrameView::new(&to, SIZE, 1));
+        let mut blended = Vec::new();
+        let dark_at = |t, blended: &mut Vec<u8>| {
+            from.blend(&to, t, blended);
+            let view = FrameView::new(blended, SIZE, 1).dithered(true);
+            (0..64).filter(|&idx| view.is_dark(idx)).count() as f32 / 64.0
+        };
+
+        // the square stays, the rest goes dark a sixteenth of the way at a time
+        assert_eq!(dark_at(0.0, &mut blended), 0.25);
+        assert_eq!(dark_at(0.5, &mut blended), 0.25 + 0.75 / 2.0);
+        assert_eq!(dark_at(1.0, &mut blended), 1.0);
+
+        // read through the threshold, as the frames are
+        let inverted = FrameView::new(&[255; 64], SIZE, 1).inverted(true);
+        inverted.blend(&from, 0.0, &mut blended);
+        assert!(blended.iter().all(|&value| value == 0));
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{config::Config, locale::tr_with, toast::Toasts};
+
+/// Simulation ticks per second to pick from
+const RATES: [u32; 4] = [30, 60, 120, 240];
+
+/// How often particles move, independently from the video's frame rate and the display's.
+/// The simulation runs in the fixed update schedule, so a faster rate converges more
+/// smoothly, ticking several times per rendered frame if needed.
+#[derive(Resource)]
+pub struct SimRate(usize);
+
+impl Default for SimRate {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+impl SimRate {
+    pub fn slowest() -> Self {
+        Self(0)
+    }
+
+    pub fn hz(&self) -> u32 {
+        RATES[self.0]
+    }
+
+    pub fn period(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.hz() as f64)
+    }
+}
+
+pub fn change_sim_rate(
+    mut rate: ResMut<SimRate>,
+    mut fixed: ResMut<FixedTime>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+    config: Res<Config>,
+) {
+    // shifted, they change the particle count
+    if keyboard.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+        return;
+    }
+    let index = if keyboard.just_released(KeyCode::LBracket) {
+        rate.0.saturating_sub(1)
+    } else if keyboard.just_released(KeyCode::RBracket) {
+        (rate.0 + 1).min(RATES.len() - 1)
+    } else {
+        return;
+    };
+
+    rate.0 = index;
+    fixed.period = rate.period();
+    toasts.push(tr_with(
+        "sim-rate",
+        &[
+            ("hz", &rate.hz()),
+            ("ticks", &format!("{:.1}", rate.hz() as f64 / config.fps)),
+        ],
+    ));
+}
@@ -0,0 +1,99 @@
+//! Changes how many particles there are while playing, with `Shift` and `[` or `]`, to find
+//! how many a machine can handle without relaunching
+
+use bevy::prelude::*;
+
+use crate::{
+    layers::Layer,
+    locale::tr_with,
+    particle_bundle,
+    sim::{Playfield, SimRng},
+    texture::{ParticleTexture, ParticleTextures, ShapeAtlas},
+    toast::Toasts,
+    CurrentFrame, Particle, BASE_Z,
+};
+
+/// Particles added or removed at a time
+pub const STEP: usize = 5000;
+/// Most particles there can be, well past what the CPU path keeps up with
+const MAX_PARTICLES: usize = 500_000;
+
+/// How many particles there should be, spawned or despawned to match
+#[derive(Resource, Default)]
+pub struct ParticleSpawner {
+    pub target: usize,
+}
+
+impl ParticleSpawner {
+    pub fn fewer(&mut self) {
+        self.target = self.target.saturating_sub(STEP);
+    }
+
+    pub fn more(&mut self) {
+        self.target = (self.target + STEP).min(MAX_PARTICLES);
+    }
+}
+
+pub fn change_particle_count(
+    mut spawner: ResMut<ParticleSpawner>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if !keyboard.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+        return;
+    }
+    if keyboard.just_released(KeyCode::LBracket) {
+        spawner.fewer();
+    } else if keyboard.just_released(KeyCode::RBracket) {
+        spawner.more();
+    } else {
+        return;
+    }
+    toasts.push(tr_with("particle-count", &[("count", &spawner.target)]));
+}
+
+/// Fades new particles in at random places, or despawns the extra ones.
+/// Only counts those of `--particles`, layers have their own counts.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_particles(
+    mut commands: Commands,
+    spawner: Res<ParticleSpawner>,
+    particles: Query<Entity, (With<Particle>, Without<Layer>)>,
+    texture: Res<ParticleTexture>,
+    atlas: Res<ShapeAtlas>,
+    mut textures: ResMut<ParticleTextures>,
+    server: Res<AssetServer>,
+    mut sim_rng: ResMut<SimRng>,
+    playfield: Res<Playfield>,
+    current: Res<CurrentFrame>,
+) {
+    if !spawner.is_changed() {
+        return;
+    }
+
+    let count = particles.iter().len();
+    if count > spawner.target {
+        for entity in particles.iter().take(count - spawner.target) {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    let handle = textures.get(*texture, &atlas, &server);
+    let mut rng = sim_rng.serial();
+    let added: Vec<_> = (count..spawner.target)
+        .map(|_| {
+            let position = playfield.random_position(&mut rng);
+            let (rect, size) = texture.look(&atlas, position, &playfield, &mut rng);
+            particle_bundle(
+                current.front.index,
+                position.extend(BASE_Z),
+                handle.clone(),
+                rect,
+                size,
+                &mut rng,
+            )
+        })
+        .collect();
+    commands.spawn_batch(added);
+}
@@ -0,0 +1,215 @@
+use std::f32::consts::TAU;
+
+use bevy::{asset::HandleId, audio::Source, prelude::*};
+use rustfft::{num_complex::Complex, FftPlanner};
+
+use crate::{config::Config, job::Job, MusicPlayer};
+
+pub const BANDS: usize = 64;
+/// Lowest bands, averaged into the bass energy
+const BASS_BANDS: usize = 6;
+/// Samples per FFT, about 46ms at 44.1kHz
+const WINDOW: usize = 2048;
+const MIN_FREQ: f32 = 40.0;
+const MAX_FREQ: f32 = 16000.0;
+/// Frames on each side an onset has to stand out from
+const ONSET_WINDOW: usize = 8;
+/// How far above the average flux around it a frame's has to be to be an onset
+const ONSET_RATIO: f32 = 1.5;
+/// Flux below which nothing counts as an onset, to skip the noise of quiet passages
+const MIN_FLUX: f32 = 0.5;
+
+/// Loudness of each frequency band of the soundtrack, for every video frame.
+/// Empty until the analysis is done.
+#[derive(Resource, Default)]
+pub struct Spectrum {
+    /// Bands are log-spaced from low to high frequencies, each normalized to `[0, 1]`
+    pub frames: Vec<[f32; BANDS]>,
+    /// Whether the analysis is done, `frames` staying empty for a soundtrack without samples
+    pub analyzed: bool,
+    /// Frames where a note or a beat starts, in order
+    pub onsets: Vec<usize>,
+}
+
+impl Spectrum {
+    /// Bass energy of a frame from 0 to 1, `None` before the analysis is done
+    pub fn bass(&self, frame: usize) -> Option<f32> {
+        let bands = self.frames.get(frame)?;
+        Some(bands[..BASS_BANDS].iter().sum::<f32>() / BASS_BANDS as f32)
+    }
+
+    /// The onset closest to `frame` if there is one within `reach` frames, `frame` otherwise
+    pub fn snap(&self, frame: usize, reach: usize) -> usize {
+        let next = self.onsets.partition_point(|&onset| onset < frame);
+        let closest = [next.checked_sub(1), Some(next)]
+            .into_iter()
+            .flatten()
+            .filter_map(|idx| self.onsets.get(idx).copied())
+            .min_by_key(|onset| onset.abs_diff(frame));
+        closest
+            .filter(|onset| onset.abs_diff(frame) <= reach)
+            .unwrap_or(frame)
+    }
+}
+
+/// Frames where the spectrum rises well above how it rises around them, peaks of the
+/// spectral flux
+fn onsets(frames: &[[f32; BANDS]]) -> Vec<usize> {
+    let flux: Vec<f32> = frames
+        .iter()
+        .zip(frames.iter().skip(1))
+        .map(|(previous, bands)| {
+            bands
+                .iter()
+                .zip(previous)
+                .map(|(energy, previous)| (energy - previous).max(0.0))
+                .sum()
+        })
+        .collect();
+
+    (0..flux.len())
+        .filter(|&idx| {
+            let around =
+                &flux[idx.saturating_sub(ONSET_WINDOW)..(idx + ONSET_WINDOW + 1).min(flux.len())];
+            let average = around.iter().sum::<f32>() / around.len() as f32;
+            let peak = idx == 0 || flux[idx] > flux[idx - 1];
+            let peak = peak && flux.get(idx + 1).is_none_or(|&next| flux[idx] >= next);
+            peak && flux[idx] >= MIN_FLUX && flux[idx] > average * ONSET_RATIO
+        })
+        // the flux of a frame is its rise from the one before
+        .map(|idx| idx + 1)
+        .collect()
+}
+
+#[derive(Resource)]
+pub struct SpectrumTask(Job<Vec<[f32; BANDS]>>);
+
+/// Decodes and analyzes the soundtrack in the background once it has loaded
+pub fn analyze_audio(
+    mut commands: Commands,
+    mut analyzed: Local<Option<HandleId>>,
+    music_player: Res<MusicPlayer>,
+    sources: Res<Assets<AudioSource>>,
+    config: Res<Config>,
+    task: Option<ResMut<SpectrumTask>>,
+) {
+    if let Some(mut task) = task {
+        if let Some(frames) = task.0.poll() {
+            let onsets = onsets(&frames);
+            info!(
+                "analyzed {} frames of audio, {} onsets",
+                frames.len(),
+                onsets.len()
+            );
+            commands.insert_resource(Spectrum {
+                frames,
+                analyzed: true,
+                onsets,
+            });
+            commands.remove_resource::<SpectrumTask>();
+        }
+        return;
+    }
+
+    // once per soundtrack, the playlist can change it
+    if *analyzed == Some(music_player.source.id()) {
+        return;
+    }
+    if let Some(source) = sources.get(&music_player.source) {
+        let source = source.clone();
+        let fps = config.fps;
+        let task = Job::spawn(async move { analyze(source, fps) });
+
+        commands.insert_resource(SpectrumTask(task));
+        *analyzed = Some(music_player.source.id());
+    }
+}
+
+fn analyze(source: AudioSource, fps: f64) -> Vec<[f32; BANDS]> {
+    let decoder = source.decoder();
+    let channels = decoder.channels() as usize;
+    let rate = decoder.sample_rate() as f32;
+
+    // downmix to mono
+    let samples: Vec<_> = decoder.collect();
+    let samples: Vec<f32> = samples
+        .chunks(channels)
+        .map(|c| c.iter().map(|&s| s as f32 / i16::MAX as f32).sum::<f32>() / channels as f32)
+        .collect();
+
+    let edges: Vec<usize> = (0..=BANDS)
+        .map(|band| {
+            let freq = MIN_FREQ * (MAX_FREQ / MIN_FREQ).powf(band as f32 / BANDS as f32);
+            ((freq / rate * WINDOW as f32) as usize).clamp(1, WINDOW / 2)
+        })
+        .collect();
+
+    let fft = FftPlanner::new().plan_fft_forward(WINDOW);
+    let mut buffer = vec![Complex::default(); WINDOW];
+
+    let hop = rate / fps as f32;
+    let count = (samples.len() as f32 / hop) as usize;
+    let mut frames = Vec::with_capacity(count);
+
+    for frame in 0..count {
+        let start = (frame as f32 * hop) as usize;
+        for (i, value) in buffer.iter_mut().enumerate() {
+            let hann = 0.5 - 0.5 * (TAU * i as f32 / WINDOW as f32).cos();
+            let sample = samples.get(start + i).copied().unwrap_or(0.0);
+            *value = Complex::new(sample * hann, 0.0);
+        }
+        fft.process(&mut buffer);
+
+        let mut bands = [0.0; BANDS];
+        for (band, energy) in bands.iter_mut().enumerate() {
+            let (lo, hi) = (edges[band], edges[band + 1].max(edges[band] + 1));
+            *energy = buffer[lo..hi].iter().map(|c| c.norm()).sum::<f32>() / (hi - lo) as f32;
+        }
+        frames.push(bands);
+    }
+
+    // normalize each band against its loudest frame, compressing the range a bit
+    let mut max = [f32::EPSILON; BANDS];
+    for bands in &frames {
+        for (max, &energy) in max.iter_mut().zip(bands) {
+            *max = max.max(energy);
+        }
+    }
+    for bands in &mut frames {
+        for (energy, max) in bands.iter_mut().zip(max) {
+            *energy = (*energy / max).sqrt();
+        }
+    }
+
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn onsets_on_rises() {
+        // quiet, then every band jumping up on frames 20 and 45
+        let mut frames = vec![[0.1; BANDS]; 60];
+        for bands in &mut frames[20..30] {
+            *bands = [0.8; BANDS];
+        }
+        for bands in &mut frames[45..] {
+            *bands = [0.9; BANDS];
+        }
+        assert_eq!(onsets(&frames), [20, 45]);
+    }
+
+    #[test]
+    fn snaps_within_reach() {
+        let spectrum = Spectrum {
+            onsets: vec![20, 45],
+            ..default()
+        };
+        assert_eq!(spectrum.snap(23, 5), 20);
+        assert_eq!(spectrum.snap(40, 5), 45);
+        assert_eq!(spectrum.snap(32, 5), 32);
+        assert_eq!(spectrum.snap(100, 5), 100);
+    }
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+
+use crate::{locale::tr_with, sync::Lagging, toast::Toasts, MusicPlayer};
+
+/// Playback speeds the hotkeys step through
+const SPEEDS: [f64; 8] = [0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 3.0, 4.0];
+pub const MIN_SPEED: f64 = SPEEDS[0];
+pub const MAX_SPEED: f64 = SPEEDS[SPEEDS.len() - 1];
+
+/// How fast the video and the soundtrack play, 1 being real time.
+/// The soundtrack's clock counts samples of the track, so they stay in sync at any speed.
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+pub struct PlaybackSpeed(pub f64);
+
+impl Default for PlaybackSpeed {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+impl PlaybackSpeed {
+    pub fn faster(self) -> Self {
+        Self(
+            SPEEDS
+                .into_iter()
+                .find(|&s| s > self.0)
+                .unwrap_or(MAX_SPEED),
+        )
+    }
+
+    pub fn slower(self) -> Self {
+        Self(
+            SPEEDS
+                .into_iter()
+                .rev()
+                .find(|&s| s < self.0)
+                .unwrap_or(MIN_SPEED),
+        )
+    }
+}
+
+pub fn change_speed(
+    mut speed: ResMut<PlaybackSpeed>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    *speed = if keyboard.just_released(KeyCode::NumpadAdd) {
+        speed.faster()
+    } else if keyboard.just_released(KeyCode::NumpadSubtract) {
+        speed.slower()
+    } else {
+        return;
+    };
+    toasts.push(tr_with("playback-speed", &[("speed", &speed.0)]));
+}
+
+/// Plays the soundtrack at the playback speed, including sinks started after it changed
+pub fn speed_audio(
+    music_player: Res<MusicPlayer>,
+    sinks: Res<Assets<AudioSink>>,
+    speed: Res<PlaybackSpeed>,
+    lagging: Res<Lagging>,
+) {
+    if let Some(sink) = music_player.sink.as_ref().and_then(|sink| sinks.get(sink)) {
+        let speed = (speed.0 * lagging.slowdown()) as f32;
+        if sink.speed() != speed {
+            sink.set_speed(speed);
+        }
+    }
+}
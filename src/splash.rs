@@ -0,0 +1,201 @@
+use bevy::{asset::LoadState, prelude::*};
+
+use crate::{
+    buffer::FrameBudget,
+    config::Config,
+    locale::{tr, tr_with},
+    spectrum::Spectrum,
+    toast::Toasts,
+    toast::UiFont,
+    visualizer::FrameSource,
+    MusicPlayer, Player, State,
+};
+
+/// Share of the progress bar taken by each loading step
+const DISCOVERY_WEIGHT: f32 = 0.1;
+const BUFFER_WEIGHT: f32 = 0.6;
+const AUDIO_WEIGHT: f32 = 0.3;
+
+/// What is left to load before playback can start smoothly
+#[derive(Resource, Default)]
+pub struct Loading {
+    pub done: bool,
+    /// Frames of the first buffer fill that finished loading, and how many there are
+    frames: usize,
+    target: usize,
+    audio_loaded: bool,
+    /// Set when playback was asked for while loading, to start it once done
+    play_when_done: bool,
+}
+
+#[derive(Component)]
+pub struct Splash;
+
+#[derive(Component)]
+pub struct ProgressBar;
+
+#[derive(Component)]
+pub struct ProgressLabel;
+
+pub fn splash_visible(loading: Res<Loading>) -> bool {
+    !loading.done
+}
+
+pub fn setup_splash(mut commands: Commands, font: Res<UiFont>) {
+    let style = TextStyle {
+        font: font.0.clone(),
+        font_size: 14.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            Splash,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::all(Val::Percent(100.0)),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    gap: Size::height(Val::Px(12.0)),
+                    ..default()
+                },
+                background_color: Color::BLACK.into(),
+                ..default()
+            },
+        ))
+        .with_children(|splash| {
+            splash.spawn(TextBundle::from_section(
+                "Bad Apple!!",
+                TextStyle {
+                    font_size: 32.0,
+                    ..style.clone()
+                },
+            ));
+            splash
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(60.0), Val::Px(8.0)),
+                        ..default()
+                    },
+                    background_color: Color::DARK_GRAY.into(),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn((
+                        ProgressBar,
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            background_color: Color::WHITE.into(),
+                            ..default()
+                        },
+                    ));
+                });
+            splash.spawn((ProgressLabel, TextBundle::from_section("", style)));
+        });
+}
+
+/// Holds off playback while loading, as the video would start ahead of its frames
+/// and the soundtrack, starting it when done instead
+pub fn hold_playback(
+    mut loading: ResMut<Loading>,
+    mut state: ResMut<State>,
+    mut toasts: ResMut<Toasts>,
+) {
+    if matches!(*state, State::Playing) {
+        *state = State::Paused;
+        if !loading.play_when_done {
+            loading.play_when_done = true;
+            toasts.push(tr("still-loading"));
+        }
+    }
+}
+
+/// Tracks loading through asset events, and hides the splash once everything is in
+#[allow(clippy::too_many_arguments)]
+pub fn update_splash(
+    mut commands: Commands,
+    mut loading: ResMut<Loading>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    mut audio_events: EventReader<AssetEvent<AudioSource>>,
+    player: Query<&Player>,
+    music_player: Res<MusicPlayer>,
+    source: Option<Res<FrameSource>>,
+    spectrum: Res<Spectrum>,
+    server: Res<AssetServer>,
+    splash: Query<Entity, With<Splash>>,
+    mut bar: Query<&mut Style, With<ProgressBar>>,
+    mut label: Query<&mut Text, With<ProgressLabel>>,
+    budget: Res<FrameBudget>,
+    config: Res<Config>,
+    mut state: ResMut<State>,
+) {
+    let player = player.single();
+    let video = matches!(source.as_deref(), Some(FrameSource::Video));
+
+    if video && loading.target == 0 {
+        // frames after the last one never load
+        loading.target = budget
+            .frames(config.fps)
+            .min(config.frames.saturating_sub(player.play_index + 1));
+    }
+    for event in image_events.iter() {
+        if let AssetEvent::Created { handle } = event {
+            if player.buffer.contains(handle) {
+                loading.frames = (loading.frames + 1).min(loading.target);
+            }
+        }
+    }
+    for event in audio_events.iter() {
+        if let AssetEvent::Created { handle } = event {
+            if *handle == music_player.source {
+                loading.audio_loaded = true;
+            }
+        }
+    }
+
+    let discovered = source.is_some();
+    let buffered = if video && loading.target > 0 {
+        loading.frames as f32 / loading.target.max(1) as f32
+    } else {
+        1.0
+    };
+    // without a soundtrack to play, the video plays silently
+    let failed = server.get_load_state(&music_player.source) == LoadState::Failed;
+    let analyzed = spectrum.analyzed || failed;
+    let audio = match (loading.audio_loaded, analyzed) {
+        (_, true) => 1.0,
+        (true, false) => 0.5,
+        (false, false) => 0.0,
+    };
+
+    let progress = if discovered { DISCOVERY_WEIGHT } else { 0.0 }
+        + BUFFER_WEIGHT * buffered
+        + AUDIO_WEIGHT * audio;
+    bar.single_mut().size.width = Val::Percent(progress * 100.0);
+
+    label.single_mut().sections[0].value = if !discovered {
+        tr("splash-looking")
+    } else if buffered < 1.0 {
+        tr_with(
+            "splash-frames",
+            &[("frames", &loading.frames), ("target", &loading.target)],
+        )
+    } else if !loading.audio_loaded {
+        tr("splash-loading-audio")
+    } else {
+        tr("splash-decoding-audio")
+    };
+
+    if discovered && buffered >= 1.0 && analyzed {
+        loading.done = true;
+        commands.entity(splash.single()).despawn_recursive();
+        if loading.play_when_done {
+            *state = State::Playing;
+        }
+    }
+}
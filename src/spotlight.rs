@@ -0,0 +1,80 @@
+use bevy::{input::mouse::MouseWheel, prelude::*, window::PrimaryWindow};
+
+use crate::{locale::tr, toast::Toasts, MainCamera};
+
+const RADIUS: f32 = 60.0;
+const MIN_RADIUS: f32 = 15.0;
+const MAX_RADIUS: f32 = 240.0;
+/// Growth of the radius per line scrolled
+const SCROLL_FACTOR: f32 = 1.1;
+
+/// Interactive mode where particles only see the video around the cursor,
+/// and drift freely everywhere else
+#[derive(Resource)]
+pub struct Spotlight {
+    pub enabled: bool,
+    /// World position of the cursor, `None` while it is outside the window
+    pub center: Option<Vec2>,
+    pub radius: f32,
+}
+
+impl Default for Spotlight {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            center: None,
+            radius: RADIUS,
+        }
+    }
+}
+
+impl Spotlight {
+    /// Whether a particle at `pos` follows the video
+    pub fn reveals(&self, pos: Vec2) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        self.center
+            .is_some_and(|center| center.distance_squared(pos) <= self.radius * self.radius)
+    }
+}
+
+pub fn toggle_spotlight(
+    mut spotlight: ResMut<Spotlight>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::H) {
+        spotlight.enabled = !spotlight.enabled;
+        toasts.push(if spotlight.enabled {
+            tr("spotlight-on")
+        } else {
+            tr("spotlight-off")
+        });
+    }
+}
+
+pub fn spotlight_enabled(spotlight: Res<Spotlight>) -> bool {
+    spotlight.enabled
+}
+
+/// Follows the cursor, and resizes on the mouse wheel
+pub fn move_spotlight(
+    mut spotlight: ResMut<Spotlight>,
+    mut wheel: EventReader<MouseWheel>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    let (camera, transform) = camera.single();
+    spotlight.center = window
+        .single()
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(transform, cursor))
+        .map(|ray| ray.origin.truncate());
+
+    let scrolled: f32 = wheel.iter().map(|event| event.y.signum()).sum();
+    if scrolled != 0.0 {
+        spotlight.radius =
+            (spotlight.radius * SCROLL_FACTOR.powf(scrolled)).clamp(MIN_RADIUS, MAX_RADIUS);
+    }
+}
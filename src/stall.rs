@@ -0,0 +1,99 @@
+//! Holds the video and the soundtrack together when frames don't load in time,
+//! instead of letting the soundtrack run ahead of a frozen frame
+
+use bevy::prelude::*;
+
+use crate::{
+    buffer::FrameBudget, config::Config, decode::FrameStore, locale::tr, toast::UiFont, Player,
+};
+
+/// Frames buffered before playback resumes after running dry
+const LOW_WATER: usize = 8;
+
+/// Whether playback is waiting on frames to load
+#[derive(Resource, Default)]
+pub struct Stall {
+    pub stalled: bool,
+}
+
+pub fn is_stalled(stall: Res<Stall>) -> bool {
+    stall.stalled
+}
+
+#[derive(Component)]
+pub struct BufferingText;
+
+pub fn setup_stall(mut commands: Commands, font: Res<UiFont>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::width(Val::Percent(100.0)),
+                position: UiRect::top(Val::Px(8.0)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                BufferingText,
+                TextBundle::from_section(
+                    tr("buffering"),
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 14.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    padding: UiRect::new(Val::Px(6.0), Val::Px(6.0), Val::Px(2.0), Val::Px(2.0)),
+                    ..default()
+                })
+                .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.7)),
+            ));
+        });
+}
+
+/// Stalls when the frame that is due isn't loaded yet, and resumes once enough are buffered
+/// to not stall again right away
+pub fn detect_underrun(
+    mut stall: ResMut<Stall>,
+    player: Query<&Player>,
+    store: Option<Res<FrameStore>>,
+    budget: Res<FrameBudget>,
+    config: Res<Config>,
+) {
+    let player = player.single();
+    let frames = match store.as_deref() {
+        // the webcam is as late as it is, there is nothing to wait for
+        Some(FrameStore::Camera(_)) => return,
+        Some(store) => store.frames().unwrap_or(config.frames),
+        None => config.frames,
+    };
+    let remaining = frames.saturating_sub(player.play_index + 1);
+
+    if stall.stalled {
+        let low_water = LOW_WATER.min(budget.frames(config.fps)).min(remaining);
+        if player.buffer.len() >= low_water {
+            stall.stalled = false;
+        }
+        return;
+    }
+
+    let due = (player.time.elapsed_secs_f64() * config.fps).floor() as usize;
+    if due > player.play_index && player.buffer.is_empty() && remaining > 0 {
+        debug!("ran out of frames at frame {}", player.play_index);
+        stall.stalled = true;
+    }
+}
+
+pub fn show_buffering(stall: Res<Stall>, mut text: Query<&mut Visibility, With<BufferingText>>) {
+    if stall.is_changed() {
+        *text.single_mut() = if stall.stalled {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
@@ -0,0 +1,94 @@
+//! Steps through the video one frame at a time while paused, running the simulation for
+//! as long as the frame would have been shown
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    config::Config, locale::tr_with, timestamp, toast::Toasts, CurrentFrame, Frame, MusicPlayer,
+    Player,
+};
+
+/// Frame asked for while paused, and simulation left to run on it
+#[derive(Resource, Default)]
+pub struct FrameStep {
+    /// Frame to show once it is buffered
+    target: Option<usize>,
+    /// Simulation ticks left to run on the shown frame
+    ticks: u32,
+}
+
+/// Whether the simulation should tick for a frame stepped to
+pub fn is_stepping(step: Res<FrameStep>) -> bool {
+    step.ticks > 0
+}
+
+/// Steps forward with `.` and back with `,`, reloading the video before the frame stepped back to
+pub fn step_frames(
+    mut step: ResMut<FrameStep>,
+    mut player: Query<&mut Player>,
+    mut music_player: ResMut<MusicPlayer>,
+    sinks: Res<Assets<AudioSink>>,
+    config: Res<Config>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    let mut player = player.single_mut();
+    if keyboard.just_pressed(KeyCode::Period) {
+        step.target = Some(player.play_index + 1);
+    } else if keyboard.just_pressed(KeyCode::Comma) && player.play_index > 1 {
+        // the first frame is 1, the player starts at 0 before it
+        let target = player.play_index - 1;
+        *player = Player::at(target - 1, config.fps);
+        step.target = Some(target);
+    } else {
+        return;
+    }
+
+    // the soundtrack restarts from the new position when playback resumes
+    music_player.stop(&sinks);
+}
+
+/// Shows the frame stepped to once it is loaded
+pub fn show_step(
+    mut step: ResMut<FrameStep>,
+    mut player: Query<&mut Player>,
+    mut current: ResMut<CurrentFrame>,
+    mut toasts: ResMut<Toasts>,
+    fixed: Res<FixedTime>,
+    config: Res<Config>,
+) {
+    let Some(target) = step.target else {
+        return;
+    };
+    let mut player = player.single_mut();
+    if player.play_index + 1 != target {
+        step.target = None;
+        return;
+    }
+    let Some(image) = player.buffer.pop_front() else {
+        return;
+    };
+
+    player.play_index = target;
+    player
+        .time
+        .set_elapsed(Duration::from_secs_f64(target as f64 / config.fps));
+    current.publish(Frame {
+        image,
+        index: target,
+    });
+    toasts.push(tr_with(
+        "stepped",
+        &[("frame", &target), ("time", &timestamp(target, config.fps))],
+    ));
+
+    let frame_secs = 1.0 / config.fps;
+    step.ticks = (frame_secs / fixed.period.as_secs_f64()).round().max(1.0) as u32;
+    step.target = None;
+}
+
+/// Counts down the ticks run on a stepped frame, after the simulation
+pub fn tick_step(mut step: ResMut<FrameStep>) {
+    step.ticks = step.ticks.saturating_sub(1);
+}
@@ -0,0 +1,262 @@
+//! Lyrics or subtitles shown under the picture, following the player's clock.
+//! Read from an `.srt` file, or from any other as `[mm:ss.xx] line` timestamps like LRC lyrics.
+
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+
+use crate::{config::Config, locale::localized, toast::UiFont, Player};
+
+/// Subtitles looked for in the working directory
+pub const SUBTITLES_FILE: &str = "subtitles.srt";
+/// Time a line takes to fade in and out, in seconds
+const FADE_SECS: f64 = 0.3;
+/// How long the last timestamped line stays, having no next line to end it
+const LAST_LINE_SECS: f64 = 5.0;
+
+struct Line {
+    start: f64,
+    end: f64,
+    text: String,
+}
+
+/// Lines sorted by start, in seconds into the video
+#[derive(Resource, Default)]
+pub struct Subtitles {
+    lines: Vec<Line>,
+    /// Line shown on the last update
+    shown: Option<usize>,
+}
+
+impl Subtitles {
+    fn read(path: &Path) -> Self {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                info!(
+                    "no subtitles to show, couldn't read {}: {e}",
+                    path.display()
+                );
+                return default();
+            }
+        };
+
+        let srt = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("srt"));
+        let lines = if srt {
+            parse_srt(&text)
+        } else {
+            parse_timestamped(&text)
+        };
+        if lines.is_empty() {
+            warn!("found no subtitles in {}", path.display());
+        }
+
+        Self { lines, shown: None }
+    }
+
+    /// Line to show at `secs`, if any
+    fn at(&self, secs: f64) -> Option<usize> {
+        let next = self.lines.partition_point(|line| line.start <= secs);
+        next.checked_sub(1)
+            .filter(|&idx| secs < self.lines[idx].end)
+    }
+}
+
+/// `HH:MM:SS,mmm` of SubRip
+fn parse_srt_time(text: &str) -> Option<f64> {
+    let (hms, millis) = text.trim().split_once([',', '.'])?;
+    let mut parts = hms.split(':').map(|part| part.parse::<f64>().ok());
+    let (hours, minutes, seconds) = (parts.next()??, parts.next()??, parts.next()??);
+    Some(hours * 3600.0 + minutes * 60.0 + seconds + millis.parse::<f64>().ok()? / 1000.0)
+}
+
+/// Blocks of a counter, a `start --> end` line and the text, separated by blank lines,
+/// sorted by start
+fn parse_srt(text: &str) -> Vec<Line> {
+    let text = text.replace("\r\n", "\n");
+    let mut lines: Vec<Line> = text
+        .split("\n\n")
+        .filter_map(|block| {
+            let mut rows = block.lines().skip_while(|row| !row.contains("-->"));
+            let (start, end) = rows.next()?.split_once("-->")?;
+            let text = rows.collect::<Vec<_>>().join("\n");
+            Some(Line {
+                start: parse_srt_time(start)?,
+                end: parse_srt_time(end)?,
+                text,
+            })
+        })
+        .collect();
+    lines.sort_by(|a, b| a.start.total_cmp(&b.start));
+    lines
+}
+
+/// `[mm:ss.xx]` of LRC lyrics
+fn parse_timestamp(text: &str) -> Option<f64> {
+    let (minutes, seconds) = text.split_once(':')?;
+    Some(minutes.parse::<f64>().ok()? * 60.0 + seconds.parse::<f64>().ok()?)
+}
+
+/// Lines of `[mm:ss.xx] text`, each lasting until the next one, sorted by start.
+/// Lines that don't start with a timestamp, like LRC's `[ar: artist]` tags, are skipped.
+fn parse_timestamped(text: &str) -> Vec<Line> {
+    let mut lines: Vec<Line> = text
+        .lines()
+        .filter_map(|row| {
+            let (stamp, text) = row.trim().strip_prefix('[')?.split_once(']')?;
+            Some(Line {
+                start: parse_timestamp(stamp)?,
+                end: f64::INFINITY,
+                text: text.trim().to_string(),
+            })
+        })
+        .collect();
+    lines.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    for idx in 0..lines.len() {
+        lines[idx].end = match lines.get(idx + 1) {
+            Some(next) => next.start,
+            None => lines[idx].start + LAST_LINE_SECS,
+        };
+    }
+    // an empty line only ends the one before it
+    lines.retain(|line| !line.text.is_empty());
+    lines
+}
+
+#[derive(Component)]
+pub struct SubtitleText;
+
+pub fn load_subtitles(mut commands: Commands, font: Res<UiFont>, config: Res<Config>) {
+    // a translation of them next to them when there is one
+    let path = localized(&config.subtitles).unwrap_or(config.subtitles.clone());
+    commands.insert_resource(Subtitles::read(&path));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::width(Val::Percent(100.0)),
+                position: UiRect::bottom(Val::Px(32.0)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                SubtitleText,
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 20.0,
+                        color: Color::NONE,
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center)
+                .with_style(Style {
+                    padding: UiRect::new(Val::Px(8.0), Val::Px(8.0), Val::Px(2.0), Val::Px(2.0)),
+                    ..default()
+                })
+                .with_background_color(Color::NONE),
+            ));
+        });
+}
+
+/// Shows the line of the player's position, fading it in and out
+pub fn show_subtitles(
+    mut subtitles: ResMut<Subtitles>,
+    mut text: Query<(&mut Text, &mut BackgroundColor), With<SubtitleText>>,
+    player: Query<&Player>,
+) {
+    let secs = player.single().time.elapsed_secs_f64();
+    let line = subtitles.at(secs);
+    let (mut text, mut background) = text.single_mut();
+
+    if subtitles.shown != line {
+        subtitles.shown = line;
+        text.sections[0].value =
+            line.map_or(String::new(), |idx| subtitles.lines[idx].text.clone());
+    }
+
+    let alpha = line.map_or(0.0, |idx| {
+        let line = &subtitles.lines[idx];
+        ((secs - line.start).min(line.end - secs) / FADE_SECS).min(1.0) as f32
+    });
+    text.sections[0].style.color = Color::rgba(1.0, 1.0, 1.0, alpha);
+    background.0 = Color::rgba(0.0, 0.0, 0.0, alpha * 0.7);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn starts(lines: &[Line]) -> Vec<f64> {
+        lines.iter().map(|line| line.start).collect()
+    }
+
+    #[test]
+    fn srt_time() {
+        assert_eq!(parse_srt_time("01:02:03,450"), Some(3723.45));
+        assert_eq!(parse_srt_time(" 00:00:07.250 "), Some(7.25));
+        assert_eq!(parse_srt_time("00:07,250"), None);
+        assert_eq!(parse_srt_time("00:00:07"), None);
+    }
+
+    #[test]
+    fn srt_with_crlf() {
+        let text = "2\r\n00:00:04,000 --> 00:00:05,500\r\nSecond\r\n\r\n\
+                    1\r\n00:00:01,000 --> 00:00:02,000\r\nFirst line\r\nand the next\r\n";
+        let lines = parse_srt(text);
+
+        assert_eq!(starts(&lines), [1.0, 4.0]);
+        assert_eq!(lines[0].end, 2.0);
+        assert_eq!(lines[0].text, "First line\nand the next");
+        assert_eq!(lines[1].end, 5.5);
+        assert_eq!(lines[1].text, "Second");
+    }
+
+    #[test]
+    fn srt_skips_broken_blocks() {
+        let text = "1\n00:00:01,000 -> 00:00:02,000\nNo arrow\n\n\
+                    2\n00:00:03,000 --> 00:00:04,000\nKept\n";
+        let lines = parse_srt(text);
+        assert_eq!(starts(&lines), [3.0]);
+    }
+
+    #[test]
+    fn timestamped_skips_tags() {
+        let text = "[ar: Alstroemeria Records]\n[ti:Bad Apple!!]\n[00:01.50] Nagareteku\n";
+        let lines = parse_timestamped(text);
+        assert_eq!(starts(&lines), [1.5]);
+        assert_eq!(lines[0].text, "Nagareteku");
+    }
+
+    #[test]
+    fn timestamped_lines_last_until_the_next() {
+        let text = "[00:10.00] third\r\n[00:01.00] first\r\n[00:04.00]\r\n[00:02.00] second\r\n";
+        let lines = parse_timestamped(text);
+
+        assert_eq!(starts(&lines), [1.0, 2.0, 10.0]);
+        assert_eq!(lines[0].end, 2.0);
+        // the empty line ends the one before it
+        assert_eq!(lines[1].end, 4.0);
+        assert_eq!(lines[1].text, "second");
+        assert_eq!(lines[2].end, 10.0 + LAST_LINE_SECS);
+    }
+
+    #[test]
+    fn line_at() {
+        let subtitles = Subtitles {
+            lines: parse_timestamped("[00:01.00] a\n[00:02.00] b\n[00:03.00]\n"),
+            shown: None,
+        };
+        assert_eq!(subtitles.at(0.5), None);
+        assert_eq!(subtitles.at(1.0), Some(0));
+        assert_eq!(subtitles.at(2.5), Some(1));
+        assert_eq!(subtitles.at(3.5), None);
+    }
+}
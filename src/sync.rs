@@ -0,0 +1,85 @@
+//! Keeps the video on the soundtrack, which plays on its own clock and would otherwise
+//! drift away from the player's stopwatch
+
+use bevy::prelude::*;
+
+use crate::{config::Config, MusicPlayer, Player};
+
+/// Fraction of the drift made up every frame when nudging
+const NUDGE: f64 = 0.1;
+/// Speed of the video and the soundtrack while the video is catching up with [`Lag::Slow`]
+pub const SLOWDOWN: f64 = 0.9;
+/// Furthest the video falls behind with [`Lag::Slow`], in seconds, before dropping frames
+/// anyway, as slowing down doesn't make up for a display much slower than the video
+const MAX_LAG_SECS: f64 = 0.5;
+
+/// How the video catches up with the soundtrack once they drift apart
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CatchUp {
+    /// Skip or hold frames to be back in sync at once
+    Jump,
+    /// Play a little faster or slower until back in sync
+    Nudge,
+}
+
+/// What the player does once several frames are due at once, after a hitch or with a
+/// display slower than the video
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Lag {
+    /// Skip the frames it is late on, to show the one that is due
+    Drop,
+    /// Show every frame, slowing down the soundtrack until the video is back on time,
+    /// unless it falls too far behind
+    Slow,
+}
+
+impl Lag {
+    /// Frames the video may be late on at `fps` before skipping some
+    pub fn max_behind(self, fps: f64) -> usize {
+        match self {
+            Lag::Drop => 1,
+            Lag::Slow => ((MAX_LAG_SECS * fps).ceil() as usize).max(1),
+        }
+    }
+}
+
+/// Whether the video is more than a frame late and being slowed down to catch up
+#[derive(Resource, Default)]
+pub struct Lagging(pub bool);
+
+impl Lagging {
+    /// Multiplier on the playback speed
+    pub fn slowdown(&self) -> f64 {
+        if self.0 {
+            SLOWDOWN
+        } else {
+            1.0
+        }
+    }
+}
+
+pub fn sync_video(
+    mut player: Query<&mut Player>,
+    music_player: Res<MusicPlayer>,
+    config: Res<Config>,
+) {
+    let Some(audio) = music_player
+        .clock
+        .as_ref()
+        .and_then(|clock| clock.position())
+    else {
+        return;
+    };
+    let mut player = player.single_mut();
+    let video = player.time.elapsed();
+
+    let drift = audio.as_secs_f64() - video.as_secs_f64();
+    if drift.abs() <= config.sync_tolerance.as_secs_f64() {
+        return;
+    }
+    let target = match config.catch_up {
+        CatchUp::Jump => audio,
+        CatchUp::Nudge => video.mul_f64(1.0 - NUDGE) + audio.mul_f64(NUDGE),
+    };
+    player.time.set_elapsed(target);
+}
@@ -0,0 +1,287 @@
+use std::{fs, path::PathBuf};
+
+use bevy::{prelude::*, utils::HashMap};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locale::tr_with,
+    palette::ColorScheme,
+    sim::{Playfield, SimRng},
+    toast::Toasts,
+    Particle,
+};
+
+/// Shapes of the mixed particles, read from the working directory
+pub const SHAPES_FILE: &str = "shapes.ron";
+
+/// Shape each particle is drawn with
+#[derive(Resource, Clone, Copy, Default, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum ParticleTexture {
+    /// Plain 2x2 square
+    #[default]
+    Square,
+    Dot,
+    /// Horizontal streak, fading towards its tail
+    Streak,
+    Star,
+    Petal,
+    /// Every particle drawn with one of the shapes of the atlas in `shapes.ron`
+    Mixed,
+}
+
+impl ParticleTexture {
+    const ALL: [ParticleTexture; 6] = [
+        ParticleTexture::Square,
+        ParticleTexture::Dot,
+        ParticleTexture::Streak,
+        ParticleTexture::Star,
+        ParticleTexture::Petal,
+        ParticleTexture::Mixed,
+    ];
+
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&t| t == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    fn path(self, atlas: &ShapeAtlas) -> PathBuf {
+        PathBuf::from(match self {
+            ParticleTexture::Square => "particle.png",
+            ParticleTexture::Dot => "particles/dot.png",
+            ParticleTexture::Streak => "particles/streak.png",
+            ParticleTexture::Star => "particles/star.png",
+            ParticleTexture::Petal => "particles/petal.png",
+            ParticleTexture::Mixed => return atlas.image.clone(),
+        })
+    }
+
+    /// Size of a particle on screen, in pixels, the largest of the shapes when mixed
+    pub fn size(self, atlas: &ShapeAtlas) -> Vec2 {
+        match self {
+            ParticleTexture::Square => Vec2::splat(2.0),
+            ParticleTexture::Dot => Vec2::splat(3.0),
+            ParticleTexture::Streak => Vec2::new(6.0, 3.0),
+            ParticleTexture::Star | ParticleTexture::Petal => Vec2::splat(5.0),
+            ParticleTexture::Mixed => atlas.largest(),
+        }
+    }
+
+    /// Part of the texture and size to draw a particle at `position` with
+    pub fn look(
+        self,
+        atlas: &ShapeAtlas,
+        position: Vec2,
+        playfield: &Playfield,
+        rng: &mut impl Rng,
+    ) -> (Option<Rect>, Vec2) {
+        match self {
+            ParticleTexture::Mixed => match atlas.pick(position, playfield, rng) {
+                Some(shape) => (Some(atlas.rect(shape)), Vec2::from(shape.size)),
+                None => (None, Vec2::ZERO),
+            },
+            texture => (None, texture.size(atlas)),
+        }
+    }
+}
+
+/// Size a particle is drawn at, before crystallization grows it
+#[derive(Component, Clone, Copy)]
+pub struct ParticleSize(pub Vec2);
+
+/// How the mixed particles get their shape
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Deserialize)]
+pub enum Assignment {
+    /// Each one at random, more often the heavier shapes
+    #[default]
+    Random,
+    /// By where they are when the shapes are handed out, in bands across the playfield
+    /// from left to right, wider for the heavier shapes
+    Region,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+pub struct AtlasShape {
+    /// Cell of the atlas, numbered left to right then top to bottom
+    pub cell: u32,
+    /// Width and height on screen, in pixels
+    pub size: (f32, f32),
+    #[serde(default = "AtlasShape::default_weight")]
+    pub weight: f32,
+}
+
+impl AtlasShape {
+    fn default_weight() -> f32 {
+        1.0
+    }
+}
+
+/// Image of several particle shapes in a grid, and how to hand them out, from `shapes.ron`
+#[derive(Resource, Clone, PartialEq, Debug, Deserialize)]
+#[serde(default)]
+pub struct ShapeAtlas {
+    /// Relative to the assets folder
+    pub image: PathBuf,
+    /// Width and height of a cell, in pixels
+    pub cell_size: (u32, u32),
+    /// Cells on a row of the image
+    pub columns: u32,
+    pub assignment: Assignment,
+    pub shapes: Vec<AtlasShape>,
+}
+
+impl Default for ShapeAtlas {
+    /// The circle, square, star and note of `assets/particles/atlas.png`
+    fn default() -> Self {
+        let shape = |cell, size| AtlasShape {
+            cell,
+            size: (size, size),
+            weight: 1.0,
+        };
+        Self {
+            image: PathBuf::from("particles/atlas.png"),
+            cell_size: (16, 16),
+            columns: 4,
+            assignment: Assignment::Random,
+            shapes: vec![shape(0, 3.0), shape(1, 2.0), shape(2, 5.0), shape(3, 6.0)],
+        }
+    }
+}
+
+impl ShapeAtlas {
+    /// Reads `shapes.ron`, `None` when it can't be read or parsed
+    pub fn read() -> Option<Self> {
+        let text = match fs::read_to_string(SHAPES_FILE) {
+            Ok(text) => text,
+            Err(e) => {
+                info!("using the default shapes, couldn't read {SHAPES_FILE}: {e}");
+                return None;
+            }
+        };
+
+        ron::from_str(&text)
+            .map_err(|e| warn!("couldn't parse {SHAPES_FILE}: {e}"))
+            .ok()
+    }
+
+    fn largest(&self) -> Vec2 {
+        self.shapes
+            .iter()
+            .map(|shape| Vec2::from(shape.size))
+            .fold(Vec2::ZERO, Vec2::max)
+    }
+
+    /// Where a shape's cell is in the image, in pixels
+    fn rect(&self, shape: &AtlasShape) -> Rect {
+        let size = UVec2::from(self.cell_size);
+        let columns = self.columns.max(1);
+        let corner = UVec2::new(shape.cell % columns, shape.cell / columns) * size;
+        Rect::from_corners(corner.as_vec2(), (corner + size).as_vec2())
+    }
+
+    /// Shape of a particle at `position`, `None` without any shape
+    fn pick(
+        &self,
+        position: Vec2,
+        playfield: &Playfield,
+        rng: &mut impl Rng,
+    ) -> Option<&AtlasShape> {
+        let total: f32 = self.shapes.iter().map(|shape| shape.weight.max(0.0)).sum();
+        let mut at = match self.assignment {
+            Assignment::Random => rng.gen_range(0.0..=1.0),
+            Assignment::Region => (position.x / playfield.size.x as f32 + 0.5).clamp(0.0, 1.0),
+        } * total;
+
+        self.shapes
+            .iter()
+            .find(|shape| {
+                at -= shape.weight.max(0.0);
+                at <= 0.0
+            })
+            .or(self.shapes.last())
+    }
+}
+
+pub fn load_shapes(mut atlas: ResMut<ShapeAtlas>) {
+    if let Some(loaded) = ShapeAtlas::read() {
+        *atlas = loaded;
+    }
+}
+
+/// Textures loaded so far, each one the first time it is picked
+#[derive(Resource, Default)]
+pub struct ParticleTextures(HashMap<ParticleTexture, Handle<Image>>);
+
+impl ParticleTextures {
+    pub fn get(
+        &mut self,
+        texture: ParticleTexture,
+        atlas: &ShapeAtlas,
+        server: &AssetServer,
+    ) -> Handle<Image> {
+        self.0
+            .entry(texture)
+            .or_insert_with(|| server.load(texture.path(atlas)))
+            .clone()
+    }
+}
+
+pub fn cycle_texture(
+    mut texture: ResMut<ParticleTexture>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::X) {
+        *texture = texture.next();
+        toasts.push(tr_with(
+            "particle-shape",
+            &[("shape", &format!("{:?}", *texture))],
+        ));
+    }
+}
+
+/// Draws the particles with the shape picked, or the one of the colors when they change
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn apply_texture(
+    mut texture: ResMut<ParticleTexture>,
+    scheme: Res<ColorScheme>,
+    atlas: Res<ShapeAtlas>,
+    mut textures: ResMut<ParticleTextures>,
+    server: Res<AssetServer>,
+    mut sim_rng: ResMut<SimRng>,
+    playfield: Res<Playfield>,
+    mut particles: Query<
+        (
+            &mut Handle<Image>,
+            &mut Sprite,
+            &mut ParticleSize,
+            &Transform,
+        ),
+        With<Particle>,
+    >,
+) {
+    // cycling with X still works until they change again
+    if let Some(themed) = scheme.texture.filter(|_| scheme.is_changed()) {
+        if *texture != themed {
+            *texture = themed;
+        }
+    }
+    if !texture.is_changed() && !atlas.is_changed() {
+        return;
+    }
+
+    let handle = textures.get(*texture, &atlas, &server);
+    let mut rng = sim_rng.serial();
+    for (mut image, mut sprite, mut size, transform) in &mut particles {
+        let (rect, look_size) = texture.look(
+            &atlas,
+            transform.translation.truncate(),
+            &playfield,
+            &mut rng,
+        );
+        *image = handle.clone();
+        sprite.rect = rect;
+        sprite.custom_size = Some(look_size);
+        size.0 = look_size;
+    }
+}
@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+
+use crate::{config::Config, locale::tr, sim::FrameView, toast::Toasts, CurrentFrame};
+
+/// Fraction of dark or light pixels under which a frame is considered blank
+const UNIFORM: f32 = 0.01;
+
+/// Brightness below which particles settle on a pixel
+#[derive(Resource)]
+pub struct Threshold {
+    /// Picks a threshold for every frame, for sources not as contrasted as the original
+    pub adaptive: bool,
+    index: usize,
+    pub value: u8,
+    /// Threshold given on the command line, that turning the adaptive one off goes back to
+    fixed: u8,
+    /// Reads color sources by the luma of their pixels
+    pub luma: bool,
+    /// Dithers gray pixels so that particles cover them as densely as they are dark
+    pub dithered: bool,
+    /// Particles settle on light pixels instead, for sources with a light silhouette on black
+    pub inverted: bool,
+    /// Whether `inverted` was picked, from the first frame or by hand
+    inversion_known: bool,
+}
+
+impl Threshold {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            adaptive: false,
+            index: 0,
+            value: config.threshold,
+            fixed: config.threshold,
+            luma: config.luma,
+            dithered: config.dither,
+            inverted: false,
+            inversion_known: false,
+        }
+    }
+
+    /// Looks at a frame the way particles should see it
+    pub fn apply<'a>(&self, frame: FrameView<'a>) -> FrameView<'a> {
+        self.read(frame)
+            .with_threshold(self.value)
+            .inverted(self.inverted)
+            .dithered(self.dithered)
+    }
+
+    /// Reads the brightness of a frame's pixels the way particles should
+    pub fn read<'a>(&self, frame: FrameView<'a>) -> FrameView<'a> {
+        frame.with_luma(self.luma)
+    }
+
+    /// Detects the inversion again from the first frames of a new source
+    pub fn forget_inversion(&mut self) {
+        self.inverted = false;
+        self.inversion_known = false;
+    }
+}
+
+pub fn toggle_threshold(
+    mut threshold: ResMut<Threshold>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::T) {
+        threshold.adaptive = !threshold.adaptive;
+        threshold.value = threshold.fixed;
+        // recompute on the current frame
+        threshold.index = usize::MAX;
+        toasts.push(if threshold.adaptive {
+            tr("threshold-adaptive")
+        } else {
+            tr("threshold-fixed")
+        });
+    }
+}
+
+pub fn toggle_inversion(
+    mut threshold: ResMut<Threshold>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::N) {
+        threshold.inverted = !threshold.inverted;
+        threshold.inversion_known = true;
+        toasts.push(if threshold.inverted {
+            tr("threshold-light")
+        } else {
+            tr("threshold-dark")
+        });
+    }
+}
+
+/// Inverts sources whose first frame with a picture is mostly dark, as their background must be black
+pub fn detect_inversion(
+    mut threshold: ResMut<Threshold>,
+    mut toasts: ResMut<Toasts>,
+    current: Res<CurrentFrame>,
+    images: Res<Assets<Image>>,
+) {
+    if threshold.inversion_known {
+        return;
+    }
+    let Some(image) = images.get(&current.front.image) else {
+        return;
+    };
+
+    let dark = threshold
+        .read(FrameView::from_image(image))
+        .with_threshold(threshold.value)
+        .dark_fraction();
+    // blank frames, like a fade from black, don't tell the background from the silhouette
+    if !(UNIFORM..=1.0 - UNIFORM).contains(&dark) {
+        return;
+    }
+
+    threshold.inversion_known = true;
+    if dark > 0.5 {
+        threshold.inverted = true;
+        toasts.push(tr("dark-background"));
+    }
+}
+
+pub fn adapt_threshold(
+    mut threshold: ResMut<Threshold>,
+    current: Res<CurrentFrame>,
+    images: Res<Assets<Image>>,
+) {
+    if !threshold.adaptive || threshold.index == current.front.index {
+        return;
+    }
+    let Some(image) = images.get(&current.front.image) else {
+        return;
+    };
+
+    threshold.index = current.front.index;
+    threshold.value = threshold
+        .read(FrameView::from_image(image))
+        .otsu_threshold();
+    debug!(
+        "threshold for frame {}: {}",
+        threshold.index, threshold.value
+    );
+}
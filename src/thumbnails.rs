@@ -0,0 +1,232 @@
+//! Tiny previews of the video every few seconds, shown under the panel's timeline while it
+//! is hovered. They are made in the background once the panel is first opened, and cached
+//! on disk so that the next launch only reads them back.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    time::Duration,
+};
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    config::Config,
+    decode::{decode_png, spawn_decode, FrameStore},
+    job::Job,
+    panel::{bar_fraction, SeekBar, SeekPreview},
+};
+
+/// Seconds of video between two thumbnails
+const THUMBNAIL_SECS: f64 = 5.0;
+/// Width of the thumbnails in pixels, their height following the video's aspect ratio
+const WIDTH: u32 = 80;
+/// Thumbnails made at once, leaving the pool to the frames being played
+const JOBS: usize = 2;
+/// Folder the thumbnails are cached in, a subfolder for each source
+const CACHE: &str = "thumbnails";
+
+/// Where a thumbnail is in the making
+enum Stage {
+    /// Its frame being decoded whole
+    Decoding(Job<(Option<Image>, Duration)>),
+    /// Being scaled down and cached, or read back from the cache
+    Shrinking(Job<Option<Image>>),
+}
+
+#[derive(Resource, Default)]
+pub struct Thumbnails {
+    /// Source they were made for, they are made again for another one
+    source: u64,
+    /// Frames between two thumbnails
+    step: usize,
+    /// Thumbnail of every step once made
+    images: Vec<Option<Handle<Image>>>,
+    /// Next step to make a thumbnail for
+    next: usize,
+    pending: Vec<(usize, Stage)>,
+}
+
+impl Thumbnails {
+    /// Frame shown by the thumbnail at `idx`, numbered from 1
+    fn frame(&self, idx: usize) -> usize {
+        idx * self.step + 1
+    }
+
+    fn cached(&self, idx: usize) -> PathBuf {
+        PathBuf::from(CACHE)
+            .join(format!("{:016x}", self.source))
+            .join(format!("{}.png", self.frame(idx)))
+    }
+
+    /// Closest thumbnail made to `frame`
+    fn near(&self, frame: usize) -> Option<&Handle<Image>> {
+        let idx = (frame as f64 / self.step.max(1) as f64).round() as usize;
+        self.images.get(idx)?.as_ref()
+    }
+}
+
+/// Identifies the frames of the source across launches
+fn source_key(store: &FrameStore, config: &Config, frames: usize) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    matches!(store, FrameStore::Packed(_)).hash(&mut hasher);
+    config.frames_dir.hash(&mut hasher);
+    frames.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Scales a frame down to a thumbnail, saving it to `path` when there is a disk to cache it on
+fn shrink(frame: Image, path: PathBuf) -> Option<Image> {
+    let frame = frame
+        .try_into_dynamic()
+        .map_err(|e| warn!("couldn't make a thumbnail of {}: {e}", path.display()))
+        .ok()?;
+    let height = (frame.height() * WIDTH / frame.width().max(1)).max(1);
+    let thumbnail = frame.thumbnail(WIDTH, height);
+
+    if !cfg!(target_arch = "wasm32") {
+        let saved = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .map_err(|e| e.to_string())
+            .and_then(|()| thumbnail.save(&path).map_err(|e| e.to_string()));
+        if let Err(e) = saved {
+            debug!("couldn't cache {}: {e}", path.display());
+        }
+    }
+    Some(Image::from_dynamic(thumbnail, true))
+}
+
+/// Reads a thumbnail cached by an earlier launch
+fn read_cached(path: PathBuf) -> Option<Image> {
+    let bytes = fs::read(&path).ok()?;
+    decode_png(&bytes)
+        .map_err(|e| debug!("couldn't read {}: {e}", path.display()))
+        .ok()
+}
+
+/// Makes the thumbnails a few at a time, from the cache when they are in it.
+/// The video file and the webcam stream their frames in order, they get none.
+pub fn make_thumbnails(
+    mut thumbnails: ResMut<Thumbnails>,
+    mut images: ResMut<Assets<Image>>,
+    store: Res<FrameStore>,
+    server: Res<AssetServer>,
+    config: Res<Config>,
+) {
+    if matches!(*store, FrameStore::Video(_) | FrameStore::Camera(_)) {
+        return;
+    }
+    let frames = store.frames().unwrap_or(config.frames);
+    let source = source_key(&store, &config, frames);
+    if thumbnails.source != source || thumbnails.images.is_empty() {
+        for handle in thumbnails.images.drain(..).flatten() {
+            images.remove(&handle);
+        }
+        let step = ((THUMBNAIL_SECS * config.fps).round() as usize).max(1);
+        *thumbnails = Thumbnails {
+            source,
+            step,
+            images: vec![None; frames.saturating_sub(1) / step + 1],
+            next: 0,
+            pending: Vec::new(),
+        };
+    }
+
+    while thumbnails.pending.len() < JOBS && thumbnails.next < thumbnails.images.len() {
+        let idx = thumbnails.next;
+        let path = thumbnails.cached(idx);
+        let stage = if !cfg!(target_arch = "wasm32") && path.is_file() {
+            Stage::Shrinking(Job::spawn(async move { read_cached(path) }))
+        } else {
+            Stage::Decoding(spawn_decode(
+                &store,
+                &server,
+                &config,
+                thumbnails.frame(idx),
+            ))
+        };
+        thumbnails.pending.push((idx, stage));
+        thumbnails.next += 1;
+    }
+
+    let Thumbnails {
+        pending,
+        images: made,
+        ..
+    } = &mut *thumbnails;
+    let mut shrinking = Vec::new();
+    pending.retain_mut(|(idx, stage)| match stage {
+        Stage::Decoding(job) => match job.poll() {
+            Some((frame, _)) => {
+                if let Some(frame) = frame {
+                    shrinking.push((*idx, frame));
+                }
+                false
+            }
+            None => true,
+        },
+        Stage::Shrinking(job) => match job.poll() {
+            Some(thumbnail) => {
+                made[*idx] = thumbnail.map(|thumbnail| images.add(thumbnail));
+                false
+            }
+            None => true,
+        },
+    });
+    for (idx, frame) in shrinking {
+        let path = thumbnails.cached(idx);
+        let job = Job::spawn(async move { shrink(frame, path) });
+        thumbnails.pending.push((idx, Stage::Shrinking(job)));
+    }
+}
+
+/// Shows the thumbnail closest to the hovered spot of the timeline under the cursor
+pub fn preview_thumbnail(
+    mut preview: Query<(&mut Style, &mut UiImage), With<SeekPreview>>,
+    bar: Query<(&Interaction, &Node, &GlobalTransform), With<SeekBar>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    thumbnails: Res<Thumbnails>,
+    images: Res<Assets<Image>>,
+    store: Res<FrameStore>,
+    config: Res<Config>,
+) {
+    let (mut style, mut image) = preview.single_mut();
+    let (interaction, node, transform) = bar.single();
+    let cursor = window.single().cursor_position();
+
+    let thumbnail = cursor
+        .filter(|_| *interaction == Interaction::Hovered)
+        .and_then(|cursor| {
+            let fraction = bar_fraction(node, transform, cursor);
+            let frames = store.frames().unwrap_or(config.frames);
+            let handle = thumbnails.near((frames as f32 * fraction) as usize)?;
+            Some((fraction, handle, images.get(handle)?.size()))
+        });
+    let Some((fraction, handle, size)) = thumbnail else {
+        if style.display != Display::None {
+            style.display = Display::None;
+        }
+        return;
+    };
+
+    // laid out again when changed
+    let shown = Style {
+        display: Display::Flex,
+        size: Size::new(Val::Px(size.x), Val::Px(size.y)),
+        position: UiRect {
+            // centered under the cursor
+            left: Val::Px(fraction * node.size().x - size.x / 2.0),
+            ..style.position
+        },
+        ..style.clone()
+    };
+    if *style != shown {
+        *style = shown;
+    }
+    if image.texture != *handle {
+        image.texture = handle.clone();
+    }
+}
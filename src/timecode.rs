@@ -0,0 +1,356 @@
+//! Slaves the player's clock to the LTC timecode coming in on an audio input with
+//! `--timecode`, for a show-control system to drive the video.
+//!
+//! LTC sends 80 bits per timecode frame, biphase mark coded: the signal flips at the start of
+//! every bit, and in its middle too for a 1. Each frame ends with a sync word, after which the
+//! frame's hours, minutes, seconds and frame number are read from its first 64 bits.
+//! MIDI timecode would need a MIDI input, which nothing in the app reads yet.
+
+// the browser has no audio input to decode
+#![cfg_attr(target_arch = "wasm32", allow(dead_code))]
+
+use std::{
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use bevy::{prelude::*, utils::Instant};
+
+use crate::{
+    config::Config, decode::FrameStore, locale::tr, toast::Toasts, MusicPlayer, Player, State,
+};
+
+/// Bits of a timecode frame
+const FRAME_BITS: u32 = 80;
+/// Last 16 bits of a frame, first received bit lowest
+const SYNC_WORD: u128 = 0xBFFC;
+/// Highest timecode frame rate, for the first guess at the length of a bit
+const MAX_FPS: u32 = 30;
+/// Seconds the timecode has to differ from the player by to seek to it instead of nudging
+const JUMP_SECS: f64 = 1.0;
+/// Seconds without a timecode after which it counts as stopped, pausing the player
+const STOPPED_SECS: f64 = 0.25;
+
+/// Reads timecode frames from the samples of an audio channel
+pub struct LtcDecoder {
+    rate: f32,
+    /// Samples since the start
+    sample: u64,
+    /// Sign of the signal, it flips when past the hysteresis the other way
+    high: bool,
+    /// Sample of the last flip
+    flipped: u64,
+    /// Samples in a bit, following the incoming rate
+    bit_length: f32,
+    /// Whether the first half of a 1 was read
+    half: bool,
+    /// Last bits received, the latest one highest once 80 are in
+    bits: u128,
+    /// Sample at the end of the last frame, to measure the frame rate with
+    last_frame: Option<u64>,
+    /// Timecode frames per second, once two frames went by in a row
+    fps: Option<f32>,
+}
+
+impl LtcDecoder {
+    /// Signal level the input has to cross to count as flipping
+    const HYSTERESIS: f32 = 0.02;
+
+    pub fn new(rate: u32) -> Self {
+        Self {
+            rate: rate as f32,
+            sample: 0,
+            high: false,
+            flipped: 0,
+            bit_length: rate as f32 / (MAX_FPS * FRAME_BITS) as f32,
+            half: false,
+            bits: 0,
+            last_frame: None,
+            fps: None,
+        }
+    }
+
+    /// Reads the next sample, returning the timecode in seconds when a frame just ended.
+    /// The timecode is that of the frame starting then.
+    pub fn push(&mut self, value: f32) -> Option<f64> {
+        self.sample += 1;
+        let flips = if self.high {
+            value < -Self::HYSTERESIS
+        } else {
+            value > Self::HYSTERESIS
+        };
+        if !flips {
+            return None;
+        }
+        self.high = !self.high;
+        let interval = (self.sample - self.flipped) as f32;
+        self.flipped = self.sample;
+
+        let bit = if interval > self.bit_length * 0.75 {
+            self.bit_length = self.bit_length * 0.9 + interval * 0.1;
+            self.half = false;
+            false
+        } else {
+            self.bit_length = self.bit_length * 0.9 + interval * 2.0 * 0.1;
+            self.half = !self.half;
+            if self.half {
+                return None;
+            }
+            true
+        };
+        self.bits = (self.bits >> 1) | (u128::from(bit) << (FRAME_BITS - 1));
+        if self.bits >> 64 != SYNC_WORD {
+            return None;
+        }
+        self.frame()
+    }
+
+    fn frame(&mut self) -> Option<f64> {
+        let field = |start: u32, len: u32| ((self.bits >> start) & ((1 << len) - 1)) as u32;
+        let frames = field(8, 2) * 10 + field(0, 4);
+        let secs = field(24, 3) * 10 + field(16, 4);
+        let mins = field(40, 3) * 10 + field(32, 4);
+        let hours = field(56, 2) * 10 + field(48, 4);
+
+        // a frame right after the last one tells how long frames are
+        let length = self.last_frame.map(|last| (self.sample - last) as f32);
+        self.last_frame = Some(self.sample);
+        if let Some(length) = length.filter(|&length| length < self.rate / 20.0) {
+            self.fps = Some((self.rate / length).round());
+        }
+        let fps = self.fps?;
+
+        let whole = (hours * 3600 + mins * 60 + secs) as f64;
+        Some(whole + (frames + 1) as f64 / fps as f64)
+    }
+}
+
+/// Timecodes decoded on the thread reading the audio input
+#[derive(Resource)]
+pub struct TimecodeInput {
+    received: Mutex<Receiver<f64>>,
+    /// When the last timecode came in
+    last: Option<Instant>,
+    running: bool,
+}
+
+impl TimecodeInput {
+    /// Starts reading the timecode from the input device named `device`, or the default one
+    /// for `default`, warning if it can't
+    pub fn listen(device: &str) -> Self {
+        let (sender, received) = mpsc::channel();
+        listen(device.to_string(), sender);
+        Self {
+            received: Mutex::new(received),
+            last: None,
+            running: false,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn listen(name: String, sender: mpsc::Sender<f64>) {
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::{FromSample, SampleFormat, SizedSample};
+
+    fn open<T: SizedSample>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        sender: mpsc::Sender<f64>,
+    ) -> Result<cpal::Stream, cpal::BuildStreamError>
+    where
+        f32: FromSample<T>,
+    {
+        let channels = config.channels as usize;
+        let mut decoder = LtcDecoder::new(config.sample_rate.0);
+        device.build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                // the timecode is on the first channel
+                for frame in data.chunks(channels) {
+                    if let Some(secs) = decoder.push(frame[0].to_sample()) {
+                        let _ = sender.send(secs);
+                    }
+                }
+            },
+            |e| warn!("lost the timecode input: {e}"),
+            None,
+        )
+    }
+
+    // the stream can't leave the thread it was made on with every audio backend
+    std::thread::spawn(move || {
+        let host = cpal::default_host();
+        let device = match name.as_str() {
+            "default" => host.default_input_device(),
+            _ => host.input_devices().ok().and_then(|mut devices| {
+                devices.find(|device| device.name().is_ok_and(|found| found == name))
+            }),
+        };
+        let Some(device) = device else {
+            warn!("no audio input named {name} to read the timecode from");
+            return;
+        };
+        let config = match device.default_input_config() {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("couldn't open {name} to read the timecode from: {e}");
+                return;
+            }
+        };
+
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => open::<f32>(&device, &config.config(), sender),
+            SampleFormat::I16 => open::<i16>(&device, &config.config(), sender),
+            SampleFormat::U16 => open::<u16>(&device, &config.config(), sender),
+            format => {
+                warn!("can't read the timecode from {format} samples");
+                return;
+            }
+        };
+        match stream.map_err(|e| e.to_string()).and_then(|stream| {
+            stream.play().map_err(|e| e.to_string())?;
+            Ok(stream)
+        }) {
+            Ok(_stream) => {
+                info!("reading the timecode from {name}");
+                // kept alive with the app
+                loop {
+                    std::thread::park();
+                }
+            }
+            Err(e) => warn!("couldn't read the timecode from {name}: {e}"),
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+fn listen(_name: String, _sender: mpsc::Sender<f64>) {
+    warn!("the browser has no audio input to read the timecode from");
+}
+
+/// Moves the player to the timecode, seeking when it jumps and nudging it otherwise.
+/// Playback starts when the timecode does and pauses when it stops.
+#[allow(clippy::too_many_arguments)]
+pub fn follow_timecode(
+    mut input: ResMut<TimecodeInput>,
+    mut player: Query<&mut Player>,
+    mut state: ResMut<State>,
+    mut music_player: ResMut<MusicPlayer>,
+    mut toasts: ResMut<Toasts>,
+    sinks: Res<Assets<AudioSink>>,
+    store: Option<Res<FrameStore>>,
+    config: Res<Config>,
+) {
+    let latest = input.received.lock().unwrap().try_iter().last();
+    let now = Instant::now();
+
+    if let Some(secs) = latest {
+        input.last = Some(now);
+        let mut player = player.single_mut();
+        let drift = secs - player.time.elapsed_secs_f64();
+        if drift.abs() > JUMP_SECS {
+            let frames = store
+                .and_then(|store| store.frames())
+                .unwrap_or(config.frames);
+            let frame = ((secs * config.fps) as usize).min(frames.saturating_sub(1));
+            *player = Player::at(frame, config.fps);
+            // the soundtrack restarts from the new position
+            music_player.stop(&sinks);
+        } else if drift.abs() > config.sync_tolerance.as_secs_f64() {
+            player.time.set_elapsed(Duration::from_secs_f64(secs));
+        }
+    }
+
+    // only on changes, pausing by hand still works while it runs
+    let running = input
+        .last
+        .is_some_and(|last| (now - last).as_secs_f64() < STOPPED_SECS);
+    if running != input.running {
+        input.running = running;
+        *state = if running {
+            State::Playing
+        } else {
+            State::Paused
+        };
+        toasts.push(if running {
+            tr("timecode-on")
+        } else {
+            tr("timecode-off")
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48000;
+    const FPS: u32 = 25;
+
+    /// The 80 bits of a timecode frame, in the order they are sent
+    fn frame_bits(hours: u32, mins: u32, secs: u32, frames: u32) -> Vec<bool> {
+        let mut bits = [false; FRAME_BITS as usize];
+        let mut set = |start: usize, len: usize, value: u32| {
+            for bit in 0..len {
+                bits[start + bit] = value >> bit & 1 == 1;
+            }
+        };
+        set(0, 4, frames % 10);
+        set(8, 2, frames / 10);
+        set(16, 4, secs % 10);
+        set(24, 3, secs / 10);
+        set(32, 4, mins % 10);
+        set(40, 3, mins / 10);
+        set(48, 4, hours % 10);
+        set(56, 2, hours / 10);
+        set(64, 16, SYNC_WORD as u32);
+        bits.to_vec()
+    }
+
+    /// Biphase mark coded samples of the bits
+    fn encode(bits: &[bool]) -> Vec<f32> {
+        let bit_length = (RATE / (FPS * FRAME_BITS)) as usize;
+        let mut level = 0.5;
+        let mut samples = Vec::new();
+        for &bit in bits {
+            level = -level;
+            for sample in 0..bit_length {
+                if bit && sample == bit_length / 2 {
+                    level = -level;
+                }
+                samples.push(level);
+            }
+        }
+        // the flip starting the next bit ends the last one
+        samples.push(-level);
+        samples
+    }
+
+    #[test]
+    fn reads_consecutive_frames() {
+        let frames = [(10, 59, 59, 23), (10, 59, 59, 24), (11, 0, 0, 0)];
+        let bits: Vec<bool> = frames
+            .iter()
+            .flat_map(|&(hours, mins, secs, frame)| frame_bits(hours, mins, secs, frame))
+            .collect();
+
+        let mut decoder = LtcDecoder::new(RATE);
+        let decoded: Vec<f64> = encode(&bits)
+            .into_iter()
+            .filter_map(|sample| decoder.push(sample))
+            .collect();
+
+        // the first frame only tells when frames end
+        let ends = |hours: f64, mins: f64, secs: f64, frame: f64| {
+            hours * 3600.0 + mins * 60.0 + secs + (frame + 1.0) / FPS as f64
+        };
+        assert_eq!(
+            decoded,
+            [ends(10.0, 59.0, 59.0, 24.0), ends(11.0, 0.0, 0.0, 0.0)]
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/// How long a toast stays on screen
+const DURATION: Duration = Duration::from_secs(3);
+/// Time a toast takes to fade out at the end of its duration
+const FADE_SECS: f32 = 0.5;
+const MAX_SHOWN: usize = 5;
+
+/// Short notices describing what the app is doing, shown in the corner of the window
+#[derive(Resource, Default)]
+pub struct Toasts {
+    pending: Vec<String>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        info!("{text}");
+        self.pending.push(text);
+    }
+}
+
+#[derive(Resource)]
+pub struct UiFont(pub Handle<Font>);
+
+#[derive(Component)]
+pub struct ToastList;
+
+#[derive(Component)]
+pub struct Toast(Timer);
+
+pub fn setup_toasts(mut commands: Commands, server: Res<AssetServer>) {
+    commands.insert_resource(UiFont(server.load("fonts/DejaVuSans.ttf")));
+
+    commands.spawn((
+        ToastList,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(8.0),
+                    bottom: Val::Px(8.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexStart,
+                gap: Size::height(Val::Px(4.0)),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+pub fn show_toasts(
+    mut commands: Commands,
+    mut toasts: ResMut<Toasts>,
+    font: Res<UiFont>,
+    list: Query<(Entity, Option<&Children>), With<ToastList>>,
+    mut shown: Query<(Entity, &mut Toast, &mut Text, &mut BackgroundColor)>,
+    time: Res<Time>,
+) {
+    let (list, children) = list.single();
+
+    // make room for new toasts by expiring the oldest ones
+    let count = children.map_or(0, |c| c.len()) + toasts.pending.len();
+    if let Some(children) = children {
+        for &child in children.iter().take(count.saturating_sub(MAX_SHOWN)) {
+            if let Ok((_, mut toast, ..)) = shown.get_mut(child) {
+                toast.0.set_elapsed(DURATION);
+            }
+        }
+    }
+
+    for text in toasts.pending.drain(..) {
+        let toast = commands
+            .spawn((
+                Toast(Timer::new(DURATION, TimerMode::Once)),
+                TextBundle::from_section(
+                    text,
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 14.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    padding: UiRect::new(Val::Px(6.0), Val::Px(6.0), Val::Px(2.0), Val::Px(2.0)),
+                    ..default()
+                })
+                .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.7)),
+            ))
+            .id();
+        commands.entity(list).add_child(toast);
+    }
+
+    for (entity, mut toast, mut text, mut background) in &mut shown {
+        toast.0.tick(time.delta());
+        if toast.0.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let alpha = (toast.0.remaining_secs() / FADE_SECS).min(1.0);
+        for section in &mut text.sections {
+            section.style.color.set_a(alpha);
+        }
+        background.0.set_a(alpha * 0.7);
+    }
+}
@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{layering::BASE_Z, locale::tr, toast::Toasts, CurrentFrame, Particle};
+
+/// Ghost sprites recycled to draw the streaks
+const POOL: usize = 16384;
+
+/// Fading streaks left behind moving particles
+#[derive(Resource, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Trails {
+    pub enabled: bool,
+    /// Seconds a streak lasts
+    pub length: f32,
+    /// Exponent on the fade of a streak, higher fading out sooner
+    pub fade: f32,
+}
+
+impl Default for Trails {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            length: 0.4,
+            fade: 2.0,
+        }
+    }
+}
+
+/// Copy of a particle left where it passed
+#[derive(Component)]
+pub struct Ghost {
+    /// Elapsed seconds at which it was left
+    born: f32,
+    color: Color,
+}
+
+/// Ghosts in the order they are reused, only spawned while trails are on
+#[derive(Resource, Default)]
+pub struct GhostPool {
+    ghosts: Vec<Entity>,
+    next: usize,
+}
+
+pub fn toggle_trails(
+    mut trails: ResMut<Trails>,
+    mut toasts: ResMut<Toasts>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if keyboard.just_released(KeyCode::U) {
+        trails.enabled = !trails.enabled;
+        toasts.push(if trails.enabled {
+            tr("trails-on")
+        } else {
+            tr("trails-off")
+        });
+    }
+}
+
+pub fn spawn_ghosts(mut commands: Commands, mut pool: ResMut<GhostPool>, trails: Res<Trails>) {
+    if !trails.is_changed() {
+        return;
+    }
+
+    if trails.enabled && pool.ghosts.is_empty() {
+        pool.ghosts = (0..POOL)
+            .map(|_| {
+                commands
+                    .spawn((
+                        Ghost {
+                            born: 0.0,
+                            color: Color::NONE,
+                        },
+                        SpriteBundle {
+                            visibility: Visibility::Hidden,
+                            ..default()
+                        },
+                    ))
+                    .id()
+            })
+            .collect();
+    } else if !trails.enabled {
+        for ghost in pool.ghosts.drain(..) {
+            commands.entity(ghost).despawn();
+        }
+    }
+}
+
+/// Leaves ghosts behind moving particles, skipping some when there are more than
+/// the pool can hold over the length of a streak
+#[allow(clippy::type_complexity)]
+pub fn leave_trails(
+    mut pool: ResMut<GhostPool>,
+    particles: Query<(&Transform, &Sprite, &Handle<Image>, &Visibility, &Particle)>,
+    mut ghosts: Query<
+        (
+            &mut Transform,
+            &mut Sprite,
+            &mut Handle<Image>,
+            &mut Visibility,
+            &mut Ghost,
+        ),
+        Without<Particle>,
+    >,
+    current: Res<CurrentFrame>,
+    trails: Res<Trails>,
+    time: Res<Time>,
+) {
+    if pool.ghosts.is_empty() {
+        return;
+    }
+
+    let moving = || {
+        particles.iter().filter(|(.., visibility, standstill)| {
+            standstill.0 != current.front.index && *visibility != Visibility::Hidden
+        })
+    };
+    let budget = (POOL as f32 * time.delta_seconds() / trails.length).ceil() as usize;
+    let stride = moving().count().div_ceil(budget.max(1)).max(1);
+    let now = time.elapsed_seconds();
+
+    for (transform, sprite, texture, ..) in moving().step_by(stride) {
+        let entity = pool.ghosts[pool.next];
+        pool.next = (pool.next + 1) % pool.ghosts.len();
+        let Ok((
+            mut ghost_transform,
+            mut ghost_sprite,
+            mut ghost_texture,
+            mut visibility,
+            mut ghost,
+        )) = ghosts.get_mut(entity)
+        else {
+            continue;
+        };
+
+        // under the particles
+        ghost_transform.translation = transform.translation.truncate().extend(BASE_Z - 1.0);
+        ghost_sprite.custom_size = sprite.custom_size;
+        ghost_sprite.color = sprite.color;
+        if *ghost_texture != *texture {
+            *ghost_texture = texture.clone();
+        }
+        *visibility = Visibility::Inherited;
+        *ghost = Ghost {
+            born: now,
+            color: sprite.color,
+        };
+    }
+}
+
+pub fn fade_ghosts(
+    mut ghosts: Query<(&mut Sprite, &mut Visibility, &Ghost)>,
+    trails: Res<Trails>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    for (mut sprite, mut visibility, ghost) in &mut ghosts {
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+
+        let t = (now - ghost.born) / trails.length;
+        if t >= 1.0 {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        let alpha = ghost.color.a() * (1.0 - t).powf(trails.fade);
+        sprite.color = ghost.color.with_a(alpha);
+    }
+}
@@ -0,0 +1,117 @@
+//! Dissolve between playlist entries: the particles scatter off the last frame, the next entry
+//! loads while they fly apart, and its first frame is held until they have gathered on it
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{config::Config, sim::Playfield, CurrentFrame, Frame, Particle, Player, Velocity};
+
+/// Seconds the particles fly apart for before the next entry starts
+const SCATTER_SECS: f32 = 1.0;
+/// Seconds the first frame of the next entry is held for
+const CONVERGE_SECS: f32 = 1.5;
+/// Speed the particles fly out from the middle at, in pixels per tick, slowing down to a stop
+const SCATTER_SPEED: f32 = 4.0;
+
+/// Systems of the dissolve, ticked before the playlist looks at whether to switch entries
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct TransitionSet;
+
+#[derive(Resource, Default)]
+pub enum Transition {
+    #[default]
+    Idle,
+    /// Particles flying apart, the playlist switching entries once the timer is done
+    Scatter(Timer),
+    /// The next entry's first frame held while the particles gather, from when it is shown
+    Converge(Timer),
+}
+
+impl Transition {
+    pub fn scatter() -> Self {
+        Self::Scatter(Timer::from_seconds(SCATTER_SECS, TimerMode::Once))
+    }
+
+    pub fn converge() -> Self {
+        Self::Converge(Timer::from_seconds(CONVERGE_SECS, TimerMode::Once))
+    }
+
+    /// Whether the particles are done scattering, for the next entry to start
+    pub fn scattered(&self) -> bool {
+        matches!(self, Self::Scatter(timer) if timer.finished())
+    }
+}
+
+pub fn is_transitioning(transition: Res<Transition>) -> bool {
+    !matches!(*transition, Transition::Idle)
+}
+
+pub fn is_scattering(transition: Res<Transition>) -> bool {
+    matches!(*transition, Transition::Scatter(_))
+}
+
+pub fn is_converging(transition: Res<Transition>) -> bool {
+    matches!(*transition, Transition::Converge(_))
+}
+
+pub fn advance_transition(
+    mut transition: ResMut<Transition>,
+    player: Query<&Player>,
+    time: Res<Time>,
+) {
+    match &mut *transition {
+        Transition::Idle => {}
+        Transition::Scatter(timer) => {
+            timer.tick(time.delta());
+        }
+        Transition::Converge(timer) => {
+            let shown = player.single().play_index > 0;
+            if shown && timer.tick(time.delta()).finished() {
+                *transition = Transition::Idle;
+            }
+        }
+    }
+}
+
+/// Pushes the particles away from the middle of the playfield, blowing the last frame apart
+pub fn scatter_particles(
+    mut particles: Query<(&mut Transform, &mut Velocity), With<Particle>>,
+    transition: Res<Transition>,
+    playfield: Res<Playfield>,
+) {
+    let Transition::Scatter(timer) = &*transition else {
+        return;
+    };
+    let speed = SCATTER_SPEED * timer.percent_left();
+    for (mut transform, mut velocity) in &mut particles {
+        let out = transform.translation.truncate().normalize_or_zero();
+        transform.translation += (out * speed).extend(0.0);
+        // attracted afresh to the next entry
+        velocity.0 = Vec2::ZERO;
+        playfield.wrap(&mut transform.translation);
+    }
+}
+
+/// Shows the first frame of the next entry once it is loaded, the player holding it
+/// until the particles have gathered
+pub fn show_first_frame(
+    mut player: Query<&mut Player>,
+    mut current: ResMut<CurrentFrame>,
+    config: Res<Config>,
+) {
+    let mut player = player.single_mut();
+    if player.play_index > 0 {
+        return;
+    }
+    let Some(image) = player.buffer.pop_front() else {
+        return;
+    };
+
+    // the first frame is 1, the player starts at 0 before it
+    player.play_index = 1;
+    player
+        .time
+        .set_elapsed(Duration::from_secs_f64(1.0 / config.fps));
+    current.publish(Frame { image, index: 1 });
+}
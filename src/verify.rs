@@ -0,0 +1,234 @@
+//! `--verify` mode: runs the simulation twice from the same seed and checks that both runs
+//! agree, then compares particle density at a few checkpoints with golden images recorded
+//! by `--verify --bless`, failing when there are none. Runs without a window, on the first
+//! seconds of the video.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    decode::decode_png,
+    sim::{self, FrameView, MovementMode, Playfield, Settle, Steering, JITTER},
+    HEIGHT, PARTICLES, WIDTH,
+};
+
+const SEED: u64 = 0xBAD_A991E;
+/// Video frames simulated, 5 seconds
+const FRAMES: usize = 150;
+/// Frames between two comparisons
+const CHECKPOINT: usize = 30;
+/// Simulation ticks per video frame, as with the default 60Hz rate
+const TICKS_PER_FRAME: usize = 2;
+const GOLDEN_DIR: &str = "verify";
+
+pub struct Frames {
+    pub data: Vec<Vec<u8>>,
+    pub size: UVec2,
+    block_size: usize,
+    /// Which frames these are, each with its own golden images
+    source: &'static str,
+}
+
+impl Frames {
+    /// The first frames of the video, or a moving disc if they haven't been extracted
+    fn load() -> Self {
+        let mut frames = Self {
+            data: Vec::new(),
+            size: UVec2::new(WIDTH, HEIGHT),
+            block_size: 1,
+            source: "video",
+        };
+
+        for idx in 1..=FRAMES {
+            let path = format!("assets/frames/out{idx:04}.png");
+            let Ok(bytes) = fs::read(&path) else {
+                break;
+            };
+            let image = match decode_png(&bytes) {
+                Ok(image) => image,
+                Err(e) => {
+                    eprintln!("couldn't decode {path}: {e}");
+                    break;
+                }
+            };
+
+            frames.size = image.size().as_uvec2();
+            frames.block_size = FrameView::from_image(&image).block_size();
+            frames.data.push(image.data);
+        }
+
+        if frames.data.len() < FRAMES {
+            println!("no extracted frames, verifying on a moving disc instead");
+            return Self::disc();
+        }
+        frames
+    }
+
+    /// Black disc crossing a white frame from left to right, one byte per pixel
+    pub fn disc() -> Self {
+        let size = UVec2::new(WIDTH, HEIGHT);
+        let radius = HEIGHT as f32 / 4.0;
+
+        let data = (0..FRAMES)
+            .map(|frame| {
+                let t = frame as f32 / FRAMES as f32;
+                let center = Vec2::new(size.x as f32 * (0.25 + 0.5 * t), size.y as f32 / 2.0);
+                (0..size.x * size.y)
+                    .map(|idx| {
+                        let pixel = Vec2::new((idx % size.x) as f32, (idx / size.x) as f32);
+                        if pixel.distance(center) < radius {
+                            0
+                        } else {
+                            255
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            data,
+            size,
+            block_size: 1,
+            source: "disc",
+        }
+    }
+
+    fn view(&self, frame: usize) -> FrameView<'_> {
+        FrameView::new(&self.data[frame], self.size, self.block_size)
+    }
+}
+
+/// Particle positions at every checkpoint
+fn simulate(frames: &Frames, mode: MovementMode) -> Vec<Vec<Vec3>> {
+    let playfield = Playfield::new(frames.size);
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let mut particles: Vec<_> = (0..PARTICLES)
+        .map(|_| playfield.random_position(&mut rng).extend(0.0))
+        .collect();
+    let mut velocities = vec![Vec2::ZERO; PARTICLES];
+
+    let mut checkpoints = Vec::new();
+    for frame in 0..FRAMES {
+        let view = frames.view(frame);
+        let steering = Steering {
+            mode,
+            settle: Settle::Fill,
+            centroid: view.centroid(&playfield),
+            field: None,
+        };
+
+        for _ in 0..TICKS_PER_FRAME {
+            for (pos, velocity) in particles.iter_mut().zip(&mut velocities) {
+                sim::step(
+                    pos, velocity, &view, &playfield, &steering, JITTER, &mut rng,
+                );
+            }
+        }
+
+        if (frame + 1) % CHECKPOINT == 0 {
+            checkpoints.push(particles.clone());
+        }
+    }
+    checkpoints
+}
+
+/// Grayscale picture of where the particles are, darker where there are more
+fn rasterize(particles: &[Vec3], playfield: &Playfield) -> Vec<u8> {
+    let mut image = vec![255u8; (playfield.size.x * playfield.size.y) as usize];
+    for pos in particles {
+        let pixel = &mut image[playfield.pixel_index(pos.truncate())];
+        *pixel = pixel.saturating_sub(64);
+    }
+    image
+}
+
+fn write_pgm(path: &Path, size: UVec2, data: &[u8]) -> std::io::Result<()> {
+    let mut bytes = format!("P5\n{} {}\n255\n", size.x, size.y).into_bytes();
+    bytes.extend_from_slice(data);
+    fs::write(path, bytes)
+}
+
+/// Pixels of a binary PGM written by [`write_pgm`]
+fn read_pgm(path: &Path, size: UVec2) -> Option<Vec<u8>> {
+    let bytes = fs::read(path).ok()?;
+    let header = format!("P5\n{} {}\n255\n", size.x, size.y);
+    bytes.strip_prefix(header.as_bytes()).map(<[u8]>::to_vec)
+}
+
+fn golden_path(frames: &Frames, mode: MovementMode, frame: usize) -> PathBuf {
+    let mode = format!("{mode:?}").to_lowercase();
+    Path::new(GOLDEN_DIR).join(format!("{}_{mode}_{frame:04}.pgm", frames.source))
+}
+
+pub fn run(bless: bool) -> ExitCode {
+    let frames = Frames::load();
+    let playfield = Playfield::new(frames.size);
+    let mut failed = false;
+
+    if bless {
+        if let Err(e) = fs::create_dir_all(GOLDEN_DIR) {
+            eprintln!("couldn't create {GOLDEN_DIR}: {e}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    for mode in [MovementMode::Jitter, MovementMode::Orbit] {
+        let first = simulate(&frames, mode);
+        let second = simulate(&frames, mode);
+
+        for (checkpoint, (a, b)) in first.iter().zip(&second).enumerate() {
+            let frame = (checkpoint + 1) * CHECKPOINT;
+
+            let diverged = a.iter().zip(b).filter(|(a, b)| a != b).count();
+            if diverged > 0 {
+                println!("{mode:?} frame {frame}: {diverged} particles differ between runs");
+                failed = true;
+            }
+
+            let image = rasterize(a, &playfield);
+            let path = golden_path(&frames, mode, frame);
+            if bless {
+                if let Err(e) = write_pgm(&path, playfield.size, &image) {
+                    eprintln!("couldn't write {}: {e}", path.display());
+                    failed = true;
+                }
+                continue;
+            }
+
+            match read_pgm(&path, playfield.size) {
+                Some(golden) => {
+                    let mismatched = golden.iter().zip(&image).filter(|(a, b)| a != b).count();
+                    if mismatched > 0 {
+                        println!(
+                            "{mode:?} frame {frame}: {mismatched} pixels differ from {}",
+                            path.display()
+                        );
+                        failed = true;
+                    }
+                }
+                None => {
+                    println!(
+                        "{mode:?} frame {frame}: no golden image at {}, record them with --verify --bless",
+                        path.display()
+                    );
+                    failed = true;
+                }
+            }
+        }
+    }
+
+    if failed {
+        println!("verification failed");
+        ExitCode::FAILURE
+    } else {
+        println!("verification passed");
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,168 @@
+//! Streams the frames of a video file through ffmpeg, for when they haven't been extracted,
+//! or of a webcam with `--webcam`
+
+use std::{
+    ffi::OsString,
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+    thread,
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    config::{assets_dir, Config},
+    decode::gray_image,
+};
+
+/// Video played when there are no extracted frames, in the assets folder
+pub const VIDEO: &str = "bad_apple.mp4";
+/// Frames decoded ahead of the player's buffer
+const AHEAD: usize = 8;
+
+/// Path of the video on disk, if ffmpeg is around to decode it
+pub fn find_video() -> Option<PathBuf> {
+    let path = assets_dir().join(VIDEO);
+    if !path.is_file() {
+        return None;
+    }
+
+    if has_ffmpeg() {
+        Some(path)
+    } else {
+        warn!("found {VIDEO} but not ffmpeg, install it to play the video directly");
+        None
+    }
+}
+
+/// Whether ffmpeg can be run
+pub fn has_ffmpeg() -> bool {
+    // no processes to spawn in the browser
+    if cfg!(target_arch = "wasm32") {
+        return false;
+    }
+
+    Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Arguments reading from the webcam `device` with the capture API of the platform
+fn camera_input(device: &str) -> Vec<OsString> {
+    let (format, input) = if cfg!(target_os = "windows") {
+        ("dshow", format!("video={device}"))
+    } else if cfg!(target_os = "macos") {
+        ("avfoundation", device.to_string())
+    } else {
+        ("v4l2", device.to_string())
+    };
+    ["-f", format, "-i", &input].map(OsString::from).into()
+}
+
+/// Frames decoded by ffmpeg on a background thread, in order
+pub struct VideoStream {
+    /// Behind a mutex to be shared with the ECS, only the frame loader ever locks it
+    frames: Mutex<Receiver<Image>>,
+    /// Index of the frame `try_next` returns next
+    next: usize,
+}
+
+impl VideoStream {
+    /// Starts decoding at frame `start`, numbered from 1 like the extracted PNGs
+    pub fn open(path: &Path, start: usize, config: &Config) -> Self {
+        let seek = start.saturating_sub(1) as f64 / config.fps;
+        let input = [OsString::from("-ss"), seek.to_string().into(), "-i".into()]
+            .into_iter()
+            .chain([path.as_os_str().to_owned()])
+            .collect();
+        Self::spawn(input, "", path.display().to_string(), start, config)
+    }
+
+    /// Starts capturing the webcam `device`, mirrored so that it moves like a mirror would
+    pub fn capture(device: &str, start: usize, config: &Config) -> Self {
+        Self::spawn(
+            camera_input(device),
+            "hflip,",
+            device.to_string(),
+            start,
+            config,
+        )
+    }
+
+    /// Decodes `input` through `filters` then fitted to the frame rate and size
+    fn spawn(
+        input: Vec<OsString>,
+        filters: &str,
+        name: String,
+        start: usize,
+        config: &Config,
+    ) -> Self {
+        let (sender, frames) = mpsc::sync_channel(AHEAD);
+        let size = config.size;
+        let filters = format!("{filters}fps={},scale={}:{}", config.fps, size.x, size.y);
+
+        thread::spawn(move || {
+            let result = decode(&input, &filters, size, |image| sender.send(image).is_ok());
+            if let Err(e) = result {
+                warn!("couldn't decode {name}: {e}");
+            }
+        });
+
+        Self {
+            frames: Mutex::new(frames),
+            next: start,
+        }
+    }
+
+    pub fn next_index(&self) -> usize {
+        self.next
+    }
+
+    /// Next frame if it is decoded already
+    pub fn try_next(&mut self) -> Option<Image> {
+        // disconnected once the video is over
+        let image = self.frames.lock().ok()?.try_recv().ok()?;
+        self.next += 1;
+        Some(image)
+    }
+}
+
+/// Feeds every frame to `send` until the video ends, or `send` returns false
+fn decode(
+    input: &[OsString],
+    filters: &str,
+    size: UVec2,
+    mut send: impl FnMut(Image) -> bool,
+) -> io::Result<()> {
+    let mut ffmpeg = Command::new("ffmpeg")
+        .args(["-loglevel", "error"])
+        .args(input)
+        .args(["-vf", filters, "-pix_fmt", "gray", "-f", "rawvideo", "-"])
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdout = BufReader::new(ffmpeg.stdout.take().expect("stdout is piped"));
+
+    let mut frame = vec![0; (size.x * size.y) as usize];
+    loop {
+        match stdout.read_exact(&mut frame) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+        // the player went away
+        if !send(gray_image(&frame, size)) {
+            ffmpeg.kill()?;
+            break;
+        }
+    }
+
+    ffmpeg.wait()?;
+    Ok(())
+}
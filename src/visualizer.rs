@@ -0,0 +1,140 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{
+    config::Config,
+    decode::FrameStore,
+    locale::tr,
+    pack::ARCHIVE,
+    sim::Playfield,
+    spectrum::{Spectrum, BANDS},
+    toast::Toasts,
+    CurrentFrame, Frame, Player,
+};
+
+/// Empty columns between two spectrum bars
+const GAP: u32 = 1;
+
+/// What the particles are following
+#[derive(Resource, Clone, PartialEq, Eq, Debug)]
+pub enum FrameSource {
+    /// The frames of the video, from the PNGs or the archive
+    Video,
+    /// Bars of the soundtrack's spectrum, when there are no frames to play, drawn in turns in
+    /// these two images so that the shown one isn't changed under the particles
+    Spectrum([Handle<Image>; 2]),
+}
+
+pub fn is_video(source: Res<FrameSource>) -> bool {
+    matches!(*source, FrameSource::Video)
+}
+
+pub fn is_visualizing(source: Res<FrameSource>) -> bool {
+    matches!(*source, FrameSource::Spectrum(_))
+}
+
+/// Falls back to visualizing the soundtrack when no frames have been extracted
+pub fn detect_source(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut toasts: ResMut<Toasts>,
+    config: Res<Config>,
+) {
+    if let Some(store) = FrameStore::open(server.asset_io(), &config) {
+        match &store {
+            FrameStore::Packed(pack) => {
+                info!("playing {} frames from {}", pack.metadata.frames(), ARCHIVE)
+            }
+            FrameStore::Video(path) => info!("decoding {} with ffmpeg", path.display()),
+            FrameStore::Camera(device) => info!("capturing {device} with ffmpeg"),
+            FrameStore::Png => {}
+        }
+        commands.insert_resource(store);
+        commands.insert_resource(FrameSource::Video);
+        return;
+    }
+
+    let image = Image::new_fill(
+        Extent3d {
+            width: config.size.x,
+            height: config.size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[255, 255, 255, 255],
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    commands.insert_resource(FrameSource::Spectrum([
+        images.add(image.clone()),
+        images.add(image),
+    ]));
+    toasts.push(tr("no-frames"));
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn visualize_spectrum(
+    mut player: Query<&mut Player>,
+    mut current: ResMut<CurrentFrame>,
+    mut images: ResMut<Assets<Image>>,
+    source: Res<FrameSource>,
+    spectrum: Res<Spectrum>,
+    playfield: Res<Playfield>,
+    config: Res<Config>,
+    time: Res<Time>,
+) {
+    let FrameSource::Spectrum(handles) = &*source else {
+        return;
+    };
+    let handle = handles
+        .iter()
+        .find(|&handle| *handle != current.front.image)
+        .expect("two images");
+    let mut player = player.single_mut();
+
+    player.time.tick(time.delta());
+
+    let idx = (player.time.elapsed_secs_f64() * config.fps).floor() as usize;
+    if idx == player.play_index {
+        return;
+    }
+    let (Some(bands), Some(image)) = (spectrum.frames.get(idx), images.get_mut(handle)) else {
+        return;
+    };
+    if image.size().as_uvec2() != playfield.size {
+        return;
+    }
+
+    draw_bars(&mut image.data, playfield.size, bands);
+
+    player.play_index = idx;
+    current.publish(Frame {
+        image: handle.clone(),
+        index: idx,
+    });
+}
+
+/// Draws the bands as black bars rising from the bottom of a white RGBA image
+fn draw_bars(data: &mut [u8], size: UVec2, bands: &[f32; BANDS]) {
+    let bar_width = (size.x / BANDS as u32).max(GAP + 1);
+
+    for y in 0..size.y {
+        // distance from the bottom, as a fraction of the height
+        let level = (size.y - y) as f32 / size.y as f32;
+
+        for x in 0..size.x {
+            let band = ((x / bar_width) as usize).min(BANDS - 1);
+            let in_gap = x % bar_width < GAP;
+            let value = if !in_gap && bands[band] >= level {
+                0
+            } else {
+                255
+            };
+
+            let idx = (y * size.x + x) as usize * 4;
+            data[idx..idx + 3].fill(value);
+        }
+    }
+}
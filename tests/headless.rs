@@ -0,0 +1,54 @@
+use bad_apple_particle::{config::Config, headless, verify::Frames};
+
+const FRAMES: usize = 5;
+
+fn config(seed: u64) -> Config {
+    Config::from_args([
+        "bad-apple-particle",
+        "--seed",
+        &seed.to_string(),
+        "--particles",
+        "2000",
+    ])
+}
+
+#[test]
+fn plays_the_frames() {
+    let config = config(7);
+    let outcome = headless::simulate(&config, FRAMES);
+
+    // the simulation ticks faster than the video
+    assert!(outcome.ticks > FRAMES);
+    assert_eq!(outcome.positions.len(), config.particles);
+    // on the playfield, the size of the generated frames
+    let half = Frames::disc().size.as_vec2() / 2.0;
+    assert!(outcome
+        .positions
+        .iter()
+        .all(|pos| pos.abs().cmple(half).all()));
+}
+
+#[test]
+fn particles_gather_on_the_silhouette() {
+    let outcome = headless::simulate(&config(7), FRAMES);
+
+    // more than would be on it scattered at random, the share of the frame it covers
+    let frame = &Frames::disc().data[0];
+    let covered = frame.iter().filter(|&&value| value == 0).count() as f32 / frame.len() as f32;
+    assert!(
+        outcome.on_silhouette > covered,
+        "{:.0}% on the silhouette, covering {:.0}% of the frame",
+        outcome.on_silhouette * 100.0,
+        covered * 100.0
+    );
+}
+
+#[test]
+fn seed_replays_the_same_run() {
+    let first = headless::simulate(&config(7), FRAMES);
+    let again = headless::simulate(&config(7), FRAMES);
+    let other = headless::simulate(&config(8), FRAMES);
+
+    assert_eq!(first.positions, again.positions);
+    assert_ne!(first.positions, other.positions);
+}